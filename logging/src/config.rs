@@ -1,7 +1,10 @@
 use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
 
 use serde_derive::Deserialize;
 use serde_derive::Serialize;
+use serde_json::Value;
 
 /// Logging configuration options.
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
@@ -14,6 +17,18 @@ pub struct Config {
     #[serde(default)]
     pub backend: LoggingBackend,
 
+    /// Include the system hostname in every log record.
+    #[serde(default = "Config::default_include_hostname")]
+    pub include_hostname: bool,
+
+    /// Include an ID unique to the process, generated at startup, in every log record.
+    #[serde(default = "Config::default_include_instance_id")]
+    pub include_instance_id: bool,
+
+    /// Include the source file and line that emitted the record in every log record.
+    #[serde(default = "Config::default_include_location")]
+    pub include_location: bool,
+
     /// Include the version in every log record.
     #[serde(default = "Config::default_include_version")]
     pub include_version: bool,
@@ -47,6 +62,9 @@ impl Default for Config {
         Config {
             async_flush: Config::default_async_flush(),
             backend: LoggingBackend::default(),
+            include_hostname: Config::default_include_hostname(),
+            include_instance_id: Config::default_include_instance_id(),
+            include_location: Config::default_include_location(),
             include_version: Config::default_include_version(),
             level: LoggingLevel::default(),
             modules: BTreeMap::new(),
@@ -56,9 +74,122 @@ impl Default for Config {
 }
 
 impl Config {
+    /// Create a configuration by merging partial documents in order of precedence.
+    ///
+    /// See `replicante_util_config::merge` for how layers are merged.
+    pub fn from_layers<I>(layers: I) -> serde_json::Result<Config>
+    where
+        I: IntoIterator<Item = Value>,
+    {
+        replicante_util_config::merge_layers(layers)
+    }
+
+    /// Create a configuration from a `RUST_LOG` style directives string.
+    ///
+    /// Directives are comma separated and can be either:
+    ///
+    ///   * A level (for example `info`), which sets the minimum logging `level`.
+    ///   * A `module=level` pair, which sets the level for the module prefix in `modules`.
+    ///
+    /// For example `info,replicante::store=debug`.
+    /// The `warn` and `trace` aliases are accepted, with `trace` mapping to `debug`.
+    /// All other options are set to their defaults.
+    pub fn from_directives(directives: &str) -> Result<Config, ConfigError> {
+        let mut config = Config::default();
+        let invalid = || ConfigError::InvalidDirectives(directives.to_string());
+        for directive in directives.split(',').map(str::trim) {
+            if directive.is_empty() {
+                continue;
+            }
+            if !directive.contains('=') {
+                config.level = LoggingLevel::parse(directive).ok_or_else(invalid)?;
+                continue;
+            }
+            let (module, level) = Config::parse_module_directive(directive).ok_or_else(invalid)?;
+            config.modules.insert(module, level);
+        }
+        Ok(config)
+    }
+
+    /// Overlay logging options set in environment variables onto this configuration.
+    ///
+    /// Environment variables take precedence over values loaded from files:
+    ///
+    ///   * `LOG_LEVEL`: replaces the minimum logging `level` (for example `debug`).
+    ///   * `LOG_MODULES`: comma separated `module=level` pairs merged into `modules`,
+    ///     replacing the level of modules already configured.
+    ///
+    /// Unset or empty variables leave the configuration unchanged.
+    pub fn apply_env_overrides(&mut self) -> Result<(), ConfigError> {
+        self.apply_overrides(|name| env::var(name).ok())
+    }
+
+    /// Overlay options returned by `lookup` onto this configuration.
+    fn apply_overrides<F>(&mut self, lookup: F) -> Result<(), ConfigError>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let lookup = |name| lookup(name).filter(|value| !value.is_empty());
+        if let Some(value) = lookup("LOG_LEVEL") {
+            self.level =
+                LoggingLevel::parse(&value).ok_or_else(|| ConfigError::InvalidEnvOverride {
+                    name: "LOG_LEVEL".into(),
+                    value: value.clone(),
+                })?;
+        }
+        if let Some(value) = lookup("LOG_MODULES") {
+            for pair in value.split(',') {
+                match Config::parse_module_directive(pair) {
+                    Some((module, level)) => {
+                        self.modules.insert(module, level);
+                    }
+                    None => {
+                        return Err(ConfigError::InvalidEnvOverride {
+                            name: "LOG_MODULES".into(),
+                            value,
+                        })
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Parse a `module=level` pair into a module prefix and its level.
+    fn parse_module_directive(directive: &str) -> Option<(String, LoggingLevel)> {
+        let mut parts = directive.splitn(2, '=');
+        let module = parts.next().unwrap_or("").trim();
+        let level = parts
+            .next()
+            .and_then(|level| LoggingLevel::parse(level.trim()))?;
+        if module.is_empty() {
+            return None;
+        }
+        Some((module.to_string(), level))
+    }
+
+    /// Check the configuration is valid without configuring any logger.
+    ///
+    /// Useful to validate configuration changes before they are applied.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.modules.keys().any(String::is_empty) {
+            return Err(ConfigError::EmptyModulePrefix);
+        }
+        self.backend.validate()
+    }
+
     fn default_async_flush() -> bool {
         true
     }
+    fn default_include_hostname() -> bool {
+        false
+    }
+    fn default_include_instance_id() -> bool {
+        false
+    }
+    fn default_include_location() -> bool {
+        false
+    }
     fn default_include_version() -> bool {
         false
     }
@@ -71,6 +202,12 @@ impl Config {
 #[derive(Clone, Default, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
 #[serde(tag = "name", content = "options")]
 pub enum LoggingBackend {
+    /// Log JSON objects to a file, appending to it if it exists.
+    ///
+    /// Use `reopen_files` to start writing to a new file once the current one is rotated.
+    #[serde(rename = "file")]
+    File { path: String },
+
     /// Log objects to systemd journal (journald).
     #[cfg(feature = "journald")]
     #[serde(rename = "journald")]
@@ -80,8 +217,80 @@ pub enum LoggingBackend {
     #[default]
     #[serde(rename = "json")]
     Json,
+
+    /// Log JSON objects to standard error.
+    #[serde(rename = "json_stderr")]
+    JsonStderr,
+
+    /// Send records to different backends based on their level.
+    ///
+    /// Records at or above the `threshold` level are sent to the `errors` backend
+    /// while all other records are sent to the `rest` backend.
+    #[serde(rename = "split")]
+    Split {
+        errors: Box<LoggingBackend>,
+        rest: Box<LoggingBackend>,
+        #[serde(default = "LoggingBackend::default_split_threshold")]
+        threshold: LoggingLevel,
+    },
+}
+
+impl LoggingBackend {
+    fn default_split_threshold() -> LoggingLevel {
+        LoggingLevel::Error
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        match self {
+            LoggingBackend::Split { errors, rest, .. } => {
+                errors.validate()?;
+                rest.validate()
+            }
+            LoggingBackend::File { path } if path.is_empty() => Err(ConfigError::EmptyFilePath),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Invalid configuration details returned by `Config::validate`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ConfigError {
+    /// The `file` backend must have a non-empty path.
+    EmptyFilePath,
+
+    /// Module level overrides must have a non-empty prefix.
+    EmptyModulePrefix,
+
+    /// An environment variable override has an invalid value.
+    InvalidEnvOverride { name: String, value: String },
+
+    /// A directives string passed to `Config::from_directives` is invalid.
+    InvalidDirectives(String),
 }
 
+impl fmt::Display for ConfigError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::EmptyFilePath => write!(fmt, "the file backend needs a non-empty path"),
+            ConfigError::EmptyModulePrefix => {
+                write!(fmt, "module level overrides must have a non-empty prefix")
+            }
+            ConfigError::InvalidEnvOverride { name, value } => {
+                write!(
+                    fmt,
+                    "invalid value '{}' for environment variable {}",
+                    value, name
+                )
+            }
+            ConfigError::InvalidDirectives(directives) => {
+                write!(fmt, "invalid logging directives '{}'", directives)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
 /// Possible logging levels.
 #[derive(Clone, Default, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
 pub enum LoggingLevel {
@@ -103,6 +312,20 @@ pub enum LoggingLevel {
     Critical = 5,
 }
 
+impl LoggingLevel {
+    /// Parse a level from its configuration name.
+    fn parse(level: &str) -> Option<LoggingLevel> {
+        match level {
+            "critical" => Some(LoggingLevel::Critical),
+            "error" => Some(LoggingLevel::Error),
+            "warning" | "warn" => Some(LoggingLevel::Warning),
+            "info" => Some(LoggingLevel::Info),
+            "debug" | "trace" => Some(LoggingLevel::Debug),
+            _ => None,
+        }
+    }
+}
+
 impl From<LoggingLevel> for ::slog::Level {
     fn from(level: LoggingLevel) -> Self {
         match level {
@@ -114,3 +337,148 @@ impl From<LoggingLevel> for ::slog::Level {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::Config;
+    use super::ConfigError;
+    use super::LoggingBackend;
+    use super::LoggingLevel;
+
+    fn lookup(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        move |name| vars.get(name).cloned()
+    }
+
+    #[test]
+    fn from_layers() {
+        let layers = vec![
+            serde_json::json!({"level": "info", "modules": {"a": "debug"}}),
+            serde_json::json!({"level": "warning", "modules": {"b": "error"}}),
+        ];
+        let config = Config::from_layers(layers).unwrap();
+        assert_eq!(config.level, LoggingLevel::Warning);
+        assert_eq!(config.modules["a"], LoggingLevel::Debug);
+        assert_eq!(config.modules["b"], LoggingLevel::Error);
+        assert_eq!(config.async_flush, Config::default().async_flush);
+    }
+
+    #[test]
+    fn env_overrides() {
+        std::env::set_var("LOG_LEVEL", "error");
+        std::env::set_var("LOG_MODULES", "hyper=warning");
+        let mut config = Config::default();
+        let result = config.apply_env_overrides();
+        std::env::remove_var("LOG_LEVEL");
+        std::env::remove_var("LOG_MODULES");
+        result.unwrap();
+        assert_eq!(config.level, LoggingLevel::Error);
+        assert_eq!(config.modules.get("hyper"), Some(&LoggingLevel::Warning));
+    }
+
+    #[test]
+    fn env_overrides_invalid_level() {
+        let mut config = Config::default();
+        let error = config
+            .apply_overrides(lookup(&[("LOG_LEVEL", "loud")]))
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid value 'loud' for environment variable LOG_LEVEL"
+        );
+    }
+
+    #[test]
+    fn env_overrides_merge_modules() {
+        let mut config = Config::default();
+        config.modules.insert("hyper".into(), LoggingLevel::Error);
+        config.modules.insert("tokio".into(), LoggingLevel::Error);
+        config
+            .apply_overrides(lookup(&[("LOG_MODULES", "hyper=debug, reqwest=info")]))
+            .unwrap();
+        assert_eq!(config.modules.get("hyper"), Some(&LoggingLevel::Debug));
+        assert_eq!(config.modules.get("reqwest"), Some(&LoggingLevel::Info));
+        assert_eq!(config.modules.get("tokio"), Some(&LoggingLevel::Error));
+    }
+
+    #[test]
+    fn env_overrides_unset() {
+        let mut config = Config::default();
+        config
+            .apply_overrides(lookup(&[("LOG_LEVEL", "")]))
+            .unwrap();
+        assert_eq!(config, Config::default());
+    }
+
+    #[test]
+    fn from_directives() {
+        let config = Config::from_directives("warn,replicante::store=debug, hyper=error").unwrap();
+        assert_eq!(config.level, LoggingLevel::Warning);
+        assert_eq!(config.modules.len(), 2);
+        assert_eq!(
+            config.modules.get("replicante::store"),
+            Some(&LoggingLevel::Debug)
+        );
+        assert_eq!(config.modules.get("hyper"), Some(&LoggingLevel::Error));
+    }
+
+    #[test]
+    fn from_directives_invalid() {
+        let error = Config::from_directives("info,hyper=loud").unwrap_err();
+        assert_eq!(
+            error,
+            ConfigError::InvalidDirectives("info,hyper=loud".into())
+        );
+        let error = Config::from_directives("=debug").unwrap_err();
+        assert_eq!(error, ConfigError::InvalidDirectives("=debug".into()));
+    }
+
+    #[test]
+    fn from_directives_modules_only() {
+        let config = Config::from_directives("tokio=trace").unwrap();
+        assert_eq!(config.level, LoggingLevel::default());
+        assert_eq!(config.modules.get("tokio"), Some(&LoggingLevel::Debug));
+    }
+
+    #[test]
+    fn validate() {
+        let mut config = Config::default();
+        config.modules.insert("hyper".into(), LoggingLevel::Warning);
+        config.validate().unwrap();
+    }
+
+    #[test]
+    fn validate_empty_module_prefix() {
+        let mut config = Config::default();
+        config.modules.insert("".into(), LoggingLevel::Warning);
+        let error = config.validate().unwrap_err();
+        assert_eq!(error, ConfigError::EmptyModulePrefix);
+    }
+
+    #[test]
+    fn validate_empty_file_path() {
+        let config = Config {
+            backend: LoggingBackend::File { path: "".into() },
+            ..Config::default()
+        };
+        let error = config.validate().unwrap_err();
+        assert_eq!(error, ConfigError::EmptyFilePath);
+
+        // Nested backends are checked too.
+        let config = Config {
+            backend: LoggingBackend::Split {
+                errors: Box::new(LoggingBackend::File { path: "".into() }),
+                rest: Box::new(LoggingBackend::Json),
+                threshold: LoggingLevel::Error,
+            },
+            ..Config::default()
+        };
+        let error = config.validate().unwrap_err();
+        assert_eq!(error, ConfigError::EmptyFilePath);
+    }
+}
@@ -3,11 +3,13 @@ use slog::Logger;
 use slog::Never;
 use slog::SendSyncRefUnwindSafeDrain;
 use slog::SendSyncUnwindSafeDrain;
-use slog_async::Async;
 
 use super::into_logger;
+use super::queue_depth;
+use super::queue_depth::Enqueue;
 use super::Config;
 use super::Opts;
+use crate::LogFlusher;
 
 /// Optionally wrap the drain into an [`Async`] drain.
 ///
@@ -20,12 +22,33 @@ where
         + SendSyncRefUnwindSafeDrain<Ok = (), Err = Never>,
 {
     if config.async_flush {
-        into_logger(
-            opts,
-            Async::new(drain).build().ignore_res(),
-            config.include_version,
-        )
+        let metrics = queue_depth::metrics(opts);
+        let core = queue_depth::core(drain, metrics.clone()).build();
+        let drain = Enqueue::new(core, metrics).ignore_res();
+        into_logger(&config, opts, drain)
     } else {
-        into_logger(opts, drain, config.include_version)
+        into_logger(&config, opts, drain)
+    }
+}
+
+/// Optionally wrap the drain into an [`Async`] drain that can be explicitly flushed.
+///
+/// [`Async`]: slog_async/struct.Async.html
+#[allow(clippy::needless_pass_by_value)]
+pub fn async_flush_with_flusher<D>(config: Config, opts: &Opts, drain: D) -> (Logger, LogFlusher)
+where
+    D: 'static
+        + SendSyncUnwindSafeDrain<Ok = (), Err = Never>
+        + SendSyncRefUnwindSafeDrain<Ok = (), Err = Never>,
+{
+    if config.async_flush {
+        let metrics = queue_depth::metrics(opts);
+        let (core, guard) = queue_depth::core(drain, metrics.clone()).build_with_guard();
+        let drain = Enqueue::new(core, metrics).ignore_res();
+        let logger = into_logger(&config, opts, drain);
+        (logger, LogFlusher::new(Some(guard)))
+    } else {
+        let logger = into_logger(&config, opts, drain);
+        (logger, LogFlusher::new(None))
     }
 }
@@ -1,14 +1,21 @@
+use once_cell::sync::Lazy;
 use slog::o;
+use slog::FnValue;
 use slog::Logger;
 use slog::Never;
+use slog::Record;
 use slog::SendSyncRefUnwindSafeDrain;
 use slog::SendSyncUnwindSafeDrain;
 
+use replicante_util_rndid::RndId;
+
 use super::Config;
+use super::LogFlusher;
 use super::Opts;
 
 mod async_flush;
 mod level;
+mod queue_depth;
 
 /// Apply decorators to the drain.
 pub fn decorate<D>(config: Config, opts: &Opts, drain: D) -> Logger
@@ -21,19 +28,250 @@ where
     async_flush::async_flush(config, opts, drain)
 }
 
+/// Apply decorators to the drain and return a `LogFlusher` for it.
+pub fn decorate_with_flusher<D>(config: Config, opts: &Opts, drain: D) -> (Logger, LogFlusher)
+where
+    D: 'static
+        + SendSyncUnwindSafeDrain<Ok = (), Err = Never>
+        + SendSyncRefUnwindSafeDrain<Ok = (), Err = Never>,
+{
+    let drain = level::level(&config, drain);
+    async_flush::async_flush_with_flusher(config, opts, drain)
+}
+
+/// ID of this process included in log records when `Config::include_instance_id` is set.
+///
+/// Generated once so all loggers in the process report the same ID.
+static INSTANCE_ID: Lazy<String> = Lazy::new(|| RndId::new().to_string());
+
 /// Converts a [`Drain`] into a [`Logger`] setting global tags.
 ///
 /// [`Drain`]: slog/trait.Drain.html
 /// [`Logger`]: slog/struct.Logger.html
-pub fn into_logger<D>(opts: &Opts, drain: D, include_version: bool) -> Logger
+pub fn into_logger<D>(config: &Config, opts: &Opts, drain: D) -> Logger
 where
     D: 'static
         + SendSyncUnwindSafeDrain<Ok = (), Err = Never>
         + SendSyncRefUnwindSafeDrain<Ok = (), Err = Never>,
 {
-    if include_version {
+    let mut logger = if config.include_version {
         Logger::root(drain, o!("version" => opts.version.clone()))
     } else {
         Logger::root(drain, o!())
+    };
+    if config.include_hostname {
+        let hostname = hostname::get()
+            .map(|hostname| hostname.to_string_lossy().into_owned())
+            .unwrap_or_else(|_| "<unknown>".to_string());
+        logger = logger.new(o!("hostname" => hostname));
+    }
+    if config.include_instance_id {
+        logger = logger.new(o!("instance_id" => INSTANCE_ID.as_str()));
+    }
+    if !config.include_location {
+        return logger;
+    }
+    logger.new(o!(
+        "file" => FnValue(|record: &Record| record.file()),
+        "line" => FnValue(|record: &Record| record.line()),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::channel;
+    use std::sync::mpsc::Receiver;
+    use std::sync::Mutex;
+    use std::time::Duration;
+    use std::time::Instant;
+
+    use prometheus::Registry;
+    use slog::info;
+    use slog::Drain;
+    use slog::Never;
+    use slog::OwnedKVList;
+    use slog::Record;
+
+    use replicante_util_rndid::RndId;
+
+    use super::decorate_with_flusher;
+    use super::into_logger;
+    use crate::Config;
+    use crate::Opts;
+    use crate::TestDrain;
+    use crate::TestSink;
+
+    #[test]
+    fn flush_async_records() {
+        let sink = TestSink::default();
+        let opts = Opts::new("test".into());
+        let config = Config {
+            async_flush: true,
+            ..Default::default()
+        };
+        let (logger, flusher) = decorate_with_flusher(config, &opts, TestDrain::new(sink.clone()));
+        for index in 0..100 {
+            info!(logger, "test"; "index" => index);
+        }
+        flusher.flush();
+        assert_eq!(sink.records().len(), 100);
+    }
+
+    /// Drain that waits for permission before discarding each record.
+    struct GateDrain(Mutex<Receiver<()>>);
+
+    impl Drain for GateDrain {
+        type Ok = ();
+        type Err = Never;
+
+        fn log(&self, _: &Record, _: &OwnedKVList) -> Result<(), Never> {
+            let _ = self.0.lock().unwrap().recv();
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn async_queue_depth_gauge() {
+        let registry = Registry::new();
+        let opts = Opts::new("test".into()).with_registry(registry.clone());
+        let config = Config {
+            async_flush: true,
+            ..Default::default()
+        };
+        let (gate, receiver) = channel();
+        let drain = GateDrain(Mutex::new(receiver));
+        let (logger, flusher) = decorate_with_flusher(config, &opts, drain);
+        let depth = || {
+            registry
+                .gather()
+                .iter()
+                .find(|family| family.get_name() == "logging_async_queue_depth")
+                .map(|family| family.get_metric()[0].get_gauge().get_value())
+                .expect("queue depth gauge to be registered")
+        };
+
+        for index in 0..10 {
+            info!(logger, "test"; "index" => index);
+        }
+        assert_eq!(depth(), 10.0);
+
+        for _ in 0..4 {
+            gate.send(()).unwrap();
+        }
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while depth() > 6.0 {
+            assert!(Instant::now() < deadline, "queue depth did not decrease");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(depth(), 6.0);
+
+        drop(gate);
+        flusher.flush();
+        assert_eq!(depth(), 0.0);
+    }
+
+    #[test]
+    fn async_queue_overflow_drops_records() {
+        let registry = Registry::new();
+        let opts = Opts::new("test".into()).with_registry(registry.clone());
+        let config = Config {
+            async_flush: true,
+            ..Default::default()
+        };
+        let (gate, receiver) = channel();
+        let drain = GateDrain(Mutex::new(receiver));
+        let (logger, flusher) = decorate_with_flusher(config, &opts, drain);
+        let metric = |name: &str| {
+            registry
+                .gather()
+                .iter()
+                .find(|family| family.get_name() == name)
+                .map(|family| {
+                    let metric = &family.get_metric()[0];
+                    match name {
+                        "logging_async_dropped_records" => metric.get_counter().get_value(),
+                        _ => metric.get_gauge().get_value(),
+                    }
+                })
+                .unwrap_or_else(|| panic!("metric {} to be registered", name))
+        };
+
+        // Logging more records than the queue holds while the drain is stuck does not block.
+        let writer = std::thread::spawn(move || {
+            for index in 0..500 {
+                info!(logger, "test"; "index" => index);
+            }
+        });
+        writer.join().unwrap();
+        let dropped = metric("logging_async_dropped_records");
+        let depth = metric("logging_async_queue_depth");
+        assert!(dropped > 0.0);
+        // The queue may also hold a report of the records dropped so far.
+        assert!(depth + dropped >= 500.0);
+        assert!(depth + dropped <= 501.0);
+
+        drop(gate);
+        flusher.flush();
+        assert_eq!(metric("logging_async_queue_depth"), 0.0);
+    }
+
+    #[test]
+    fn hostname_and_instance_id_disabled() {
+        let sink = TestSink::default();
+        let opts = Opts::new("test".into());
+        let logger = into_logger(&Config::default(), &opts, TestDrain::new(sink.clone()));
+        info!(logger, "test");
+        let records = sink.records();
+        assert!(!records[0].fields.contains_key("hostname"));
+        assert!(!records[0].fields.contains_key("instance_id"));
+    }
+
+    #[test]
+    fn hostname_and_instance_id_enabled() {
+        let sink = TestSink::default();
+        let opts = Opts::new("test".into());
+        let config = Config {
+            include_hostname: true,
+            include_instance_id: true,
+            ..Default::default()
+        };
+        let logger = into_logger(&config, &opts, TestDrain::new(sink.clone()));
+        info!(logger, "test");
+        let other = into_logger(&config, &opts, TestDrain::new(sink.clone()));
+        info!(other, "test");
+        let records = sink.records();
+        let hostname = hostname::get().unwrap();
+        assert_eq!(records[0].fields["hostname"], hostname.to_string_lossy());
+        assert!(RndId::is_valid(&records[0].fields["instance_id"]));
+        assert_eq!(
+            records[0].fields["instance_id"],
+            records[1].fields["instance_id"]
+        );
+    }
+
+    #[test]
+    fn location_disabled() {
+        let sink = TestSink::default();
+        let opts = Opts::new("test".into());
+        let logger = into_logger(&Config::default(), &opts, TestDrain::new(sink.clone()));
+        info!(logger, "test");
+        let records = sink.records();
+        assert!(!records[0].fields.contains_key("file"));
+        assert!(!records[0].fields.contains_key("line"));
+    }
+
+    #[test]
+    fn location_enabled() {
+        let sink = TestSink::default();
+        let opts = Opts::new("test".into());
+        let config = Config {
+            include_location: true,
+            ..Default::default()
+        };
+        let logger = into_logger(&config, &opts, TestDrain::new(sink.clone()));
+        info!(logger, "test");
+        let records = sink.records();
+        assert_eq!(records[0].fields["file"], file!());
+        assert!(records[0].fields.contains_key("line"));
     }
 }
@@ -0,0 +1,156 @@
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+
+use prometheus::IntCounter;
+use prometheus::IntGauge;
+use slog::b;
+use slog::record;
+use slog::Drain;
+use slog::Level;
+use slog::Never;
+use slog::OwnedKVList;
+use slog::Record;
+use slog_async::AsyncCore;
+use slog_async::AsyncCoreBuilder;
+use slog_async::AsyncError;
+
+use super::Opts;
+
+/// Metrics about the async logging queue.
+#[derive(Clone)]
+pub struct QueueMetrics {
+    depth: IntGauge,
+    dropped: IntCounter,
+}
+
+/// Create the async queue metrics and register them with the registry in the options.
+pub fn metrics(opts: &Opts) -> Option<QueueMetrics> {
+    let registry = opts.registry()?;
+    let depth = IntGauge::new(
+        "logging_async_queue_depth",
+        "Number of log records waiting to be written by the async drain",
+    )
+    .expect("unable to configure logging queue depth gauge");
+    let dropped = IntCounter::new(
+        "logging_async_dropped_records",
+        "Number of log records dropped because the async queue was full",
+    )
+    .expect("unable to configure logging dropped records counter");
+    // Registration errors are ignored as documented in `Opts::with_registry`.
+    let _ = registry.register(Box::new(depth.clone()));
+    let _ = registry.register(Box::new(dropped.clone()));
+    Some(QueueMetrics { depth, dropped })
+}
+
+/// Async drain core that counts records as they are written out of the queue.
+///
+/// The returned core never blocks: wrap it in `Enqueue` to handle full queues.
+pub fn core<D>(drain: D, metrics: Option<QueueMetrics>) -> AsyncCoreBuilder<Dequeue<D>>
+where
+    D: Drain<Ok = (), Err = Never> + Send + 'static,
+{
+    let gauge = metrics.map(|metrics| metrics.depth);
+    AsyncCore::custom(Dequeue::new(drain, gauge)).blocking(false)
+}
+
+/// Pass records to an `AsyncCore`, dropping them when its queue is full.
+///
+/// This is the default `DropAndReport` strategy of `slog_async::Async`:
+/// the number of dropped records is logged once there is room in the queue again.
+/// Unlike `Async`, it knows which records are queued so it keeps the queue depth
+/// gauge accurate and counts dropped records when metrics are exported.
+pub struct Enqueue {
+    core: AsyncCore,
+    dropped: AtomicUsize,
+    metrics: Option<QueueMetrics>,
+}
+
+impl Enqueue {
+    pub fn new(core: AsyncCore, metrics: Option<QueueMetrics>) -> Enqueue {
+        Enqueue {
+            core,
+            dropped: AtomicUsize::new(0),
+            metrics,
+        }
+    }
+
+    /// Report records dropped since the last report, if any.
+    fn report_dropped(&self, values: &OwnedKVList) -> Result<(), AsyncError> {
+        let dropped = self.dropped.swap(0, Ordering::Relaxed);
+        if dropped == 0 {
+            return Ok(());
+        }
+        let result = self.send(
+            &record!(
+                Level::Error,
+                "slog-async",
+                &format_args!("slog-async: logger dropped messages due to channel overflow"),
+                b!("count" => dropped)
+            ),
+            values,
+        );
+        match result {
+            Err(AsyncError::Full) => {
+                self.dropped.fetch_add(dropped, Ordering::Relaxed);
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
+    /// Send a record to the queue, tracking it in the queue depth gauge if it is accepted.
+    fn send(&self, record: &Record, values: &OwnedKVList) -> Result<(), AsyncError> {
+        if let Some(metrics) = self.metrics.as_ref() {
+            metrics.depth.inc();
+        }
+        let result = self.core.log(record, values);
+        if let (Some(metrics), Err(_)) = (self.metrics.as_ref(), result.as_ref()) {
+            metrics.depth.dec();
+        }
+        result
+    }
+}
+
+impl Drain for Enqueue {
+    type Ok = ();
+    type Err = AsyncError;
+
+    fn log(&self, record: &Record, values: &OwnedKVList) -> Result<(), AsyncError> {
+        self.report_dropped(values)?;
+        match self.send(record, values) {
+            Err(AsyncError::Full) => {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+                if let Some(metrics) = self.metrics.as_ref() {
+                    metrics.dropped.inc();
+                }
+                Ok(())
+            }
+            result => result,
+        }
+    }
+}
+
+/// Wrap a drain to count records as they are written out of the async queue.
+pub struct Dequeue<D> {
+    drain: D,
+    gauge: Option<IntGauge>,
+}
+
+impl<D> Dequeue<D> {
+    pub fn new(drain: D, gauge: Option<IntGauge>) -> Dequeue<D> {
+        Dequeue { drain, gauge }
+    }
+}
+
+impl<D: Drain> Drain for Dequeue<D> {
+    type Ok = D::Ok;
+    type Err = D::Err;
+
+    fn log(&self, record: &Record, values: &OwnedKVList) -> Result<Self::Ok, Self::Err> {
+        let result = self.drain.log(record, values);
+        if let Some(gauge) = self.gauge.as_ref() {
+            gauge.dec();
+        }
+        result
+    }
+}
@@ -0,0 +1,220 @@
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::Weak;
+
+use once_cell::sync::Lazy;
+
+/// Log files opened by file backends, to be reopened by `reopen_files`.
+static FILES: Lazy<Mutex<Vec<Weak<LogFile>>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+/// Flush and reopen the files written to by `file` logging backends.
+///
+/// This implements the classic `logrotate` contract: once log files are moved,
+/// the process is signalled (usually with `SIGHUP`) to start writing to new files
+/// at the configured paths. For example, with `replicante_util_upkeep`:
+///
+/// ```ignore
+/// upkeep.register_reload_signal()?;
+/// upkeep.on_reload(|| {
+///     if let Err(error) = replicante_logging::reopen_files() {
+///         eprintln!("Unable to reopen log files: {}", error);
+///     }
+/// });
+/// ```
+///
+/// All files are reopened even if some fail and the first error is returned.
+pub fn reopen_files() -> io::Result<()> {
+    let mut files = FILES.lock().expect("log files registry lock poisoned");
+    files.retain(|file| file.strong_count() > 0);
+    let mut result = Ok(());
+    for file in files.iter().filter_map(Weak::upgrade) {
+        let reopened = file.reopen();
+        if result.is_ok() {
+            result = reopened;
+        }
+    }
+    result
+}
+
+/// Log file that can be reopened at its original path.
+struct LogFile {
+    file: Mutex<File>,
+    path: PathBuf,
+}
+
+impl LogFile {
+    fn open(path: &Path) -> io::Result<File> {
+        OpenOptions::new().create(true).append(true).open(path)
+    }
+
+    /// Flush the current file and replace it with a newly opened one.
+    ///
+    /// The lock is held while the file is swapped so no line is written to
+    /// the old file once the new one is in place.
+    fn reopen(&self) -> io::Result<()> {
+        let mut file = self.file.lock().expect("log file lock poisoned");
+        file.flush()?;
+        *file = LogFile::open(&self.path)?;
+        Ok(())
+    }
+
+    /// Write a set of complete lines to the current file.
+    fn write_lines(&self, lines: &[u8]) -> io::Result<()> {
+        let mut file = self.file.lock().expect("log file lock poisoned");
+        file.write_all(lines)
+    }
+}
+
+/// `Write`r for the file backend that only writes complete lines to the file.
+///
+/// Records are written to the underlying file a line at a time so records are
+/// never split across files when they are reopened.
+pub struct FileWriter {
+    buffer: Vec<u8>,
+    file: Arc<LogFile>,
+}
+
+impl FileWriter {
+    /// Open (or create) the file at `path` to append log lines to.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<FileWriter> {
+        let path = path.as_ref().to_path_buf();
+        let file = LogFile::open(&path)?;
+        let file = Arc::new(LogFile {
+            file: Mutex::new(file),
+            path,
+        });
+        FILES
+            .lock()
+            .expect("log files registry lock poisoned")
+            .push(Arc::downgrade(&file));
+        Ok(FileWriter {
+            buffer: Vec::new(),
+            file,
+        })
+    }
+}
+
+impl Write for FileWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        if let Some(end) = self.buffer.iter().rposition(|byte| *byte == b'\n') {
+            let lines: Vec<u8> = self.buffer.drain(..=end).collect();
+            self.file.write_lines(&lines)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        // Partial lines are kept in the buffer until they are complete.
+        let mut file = self.file.file.lock().expect("log file lock poisoned");
+        file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+    use std::path::PathBuf;
+    use std::sync::Arc;
+    use std::thread;
+
+    use replicante_util_rndid::RndId;
+    use slog::info;
+    use slog::o;
+    use slog::Logger;
+
+    use super::reopen_files;
+    use crate::config::LoggingBackend;
+
+    /// Unique path in the temporary directory for a test log file.
+    fn log_path() -> PathBuf {
+        std::env::temp_dir().join(format!("replicante-logging-{}.log", RndId::new()))
+    }
+
+    fn logger(path: &Path) -> Logger {
+        let backend = LoggingBackend::File {
+            path: path.to_string_lossy().to_string(),
+        };
+        Logger::root(crate::backend(&backend).unwrap(), o!())
+    }
+
+    fn lines(path: &PathBuf) -> Vec<String> {
+        let content = fs::read_to_string(path).unwrap_or_default();
+        content.lines().map(String::from).collect()
+    }
+
+    #[test]
+    fn open_failure_is_an_error() {
+        let path = log_path().join("missing-directory.log");
+        let backend = LoggingBackend::File {
+            path: path.to_string_lossy().to_string(),
+        };
+        let error = match crate::backend(&backend) {
+            Err(error) => error,
+            Ok(_) => panic!("log file in a missing directory was opened"),
+        };
+        assert_eq!(error.kind(), std::io::ErrorKind::NotFound);
+        assert!(error.to_string().starts_with("unable to open log file"));
+    }
+
+    #[test]
+    fn reopen_writes_to_new_file() {
+        let path = log_path();
+        let rotated = path.with_extension("log.1");
+        let logger = logger(&path);
+        info!(logger, "before rotation");
+        fs::rename(&path, &rotated).unwrap();
+        info!(logger, "still to the moved file");
+        reopen_files().unwrap();
+        info!(logger, "after rotation");
+
+        let old = lines(&rotated);
+        let new = lines(&path);
+        fs::remove_file(&rotated).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(old.len(), 2);
+        assert!(old[0].contains("before rotation"));
+        assert!(old[1].contains("still to the moved file"));
+        assert_eq!(new.len(), 1);
+        assert!(new[0].contains("after rotation"));
+    }
+
+    #[test]
+    fn reopen_during_writes_loses_no_lines() {
+        let path = log_path();
+        let rotated = path.with_extension("log.1");
+        let logger = Arc::new(logger(&path));
+        let writers: Vec<_> = (0..4)
+            .map(|writer| {
+                let logger = Arc::clone(&logger);
+                thread::spawn(move || {
+                    for line in 0..250 {
+                        info!(logger, "concurrent line"; "writer" => writer, "line" => line);
+                    }
+                })
+            })
+            .collect();
+        fs::rename(&path, &rotated).unwrap();
+        reopen_files().unwrap();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+
+        let mut all = lines(&rotated);
+        all.extend(lines(&path));
+        let _ = fs::remove_file(&rotated);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(all.len(), 1000);
+        for line in all {
+            let record: serde_json::Value = serde_json::from_str(&line).unwrap();
+            assert_eq!(record["msg"], "concurrent line");
+        }
+    }
+}
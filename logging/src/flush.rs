@@ -0,0 +1,37 @@
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use slog_async::AsyncGuard;
+
+/// Handle to flush buffered log records before the process exits.
+///
+/// When asynchronous flushing is enabled, records are buffered and written by
+/// a background thread that may not have processed all records when the process exits.
+/// Calling `LogFlusher::flush` waits for all buffered records to be written.
+///
+/// Records logged after the flush are discarded so flushing should be one of the last
+/// steps before the process exits (for example in an `Upkeep::on_shutdown` callback).
+///
+/// Dropping the last clone of a `LogFlusher` also flushes the logs.
+#[derive(Clone)]
+pub struct LogFlusher {
+    guard: Arc<Mutex<Option<AsyncGuard>>>,
+}
+
+impl LogFlusher {
+    pub(crate) fn new(guard: Option<AsyncGuard>) -> LogFlusher {
+        let guard = Arc::new(Mutex::new(guard));
+        LogFlusher { guard }
+    }
+
+    /// Wait for all buffered records to be written.
+    ///
+    /// This is a noop if asynchronous flushing is disabled or logs were already flushed.
+    pub fn flush(&self) {
+        let guard = match self.guard.lock() {
+            Ok(mut guard) => guard.take(),
+            Err(poisoned) => poisoned.into_inner().take(),
+        };
+        drop(guard);
+    }
+}
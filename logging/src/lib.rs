@@ -1,4 +1,9 @@
+use std::io;
+use std::io::stderr;
 use std::io::stdout;
+use std::io::Write;
+use std::panic::RefUnwindSafe;
+use std::panic::UnwindSafe;
 use std::sync::Mutex;
 
 use slog::o;
@@ -6,6 +11,7 @@ use slog::Drain;
 use slog::FnValue;
 use slog::IgnoreResult;
 use slog::Logger;
+use slog::Never;
 use slog::Record;
 #[cfg(feature = "journald")]
 use slog_journald::JournaldDrain;
@@ -13,14 +19,36 @@ use slog_json::Json;
 
 mod config;
 mod decorator;
+mod file;
+mod flush;
 mod options;
+mod ring;
+mod split;
+#[cfg(any(test, feature = "with_test_support"))]
+mod test_support;
 
 pub use self::config::Config;
+pub use self::config::ConfigError;
 pub use self::config::LoggingLevel;
+pub use self::file::reopen_files;
+pub use self::flush::LogFlusher;
 pub use self::options::Opts;
+pub use self::ring::RecentLogs;
+pub use self::ring::RingBufferDrain;
+#[cfg(any(test, feature = "with_test_support"))]
+pub use self::test_support::buffer_logger;
+#[cfg(any(test, feature = "with_test_support"))]
+pub use self::test_support::TestDrain;
+#[cfg(any(test, feature = "with_test_support"))]
+pub use self::test_support::TestRecord;
+#[cfg(any(test, feature = "with_test_support"))]
+pub use self::test_support::TestSink;
 
 use self::config::LoggingBackend;
 use self::decorator::decorate;
+use self::decorator::decorate_with_flusher;
+use self::file::FileWriter;
+use self::split::SplitDrain;
 
 /// Creates a [`Logger`] based on the given configuration.
 ///
@@ -31,39 +59,106 @@ use self::decorator::decorate;
 ///
 /// [`Drain`]: slog/trait.Drain.html
 /// [`Logger`]: slog/struct.Logger.html
-pub fn configure(config: Config, opts: &Opts) -> Logger {
-    match config.backend {
+///
+/// # Errors
+/// If the `file` backend is configured and the log file can't be opened.
+pub fn configure(config: Config, opts: &Opts) -> io::Result<Logger> {
+    let drain = backend(&config.backend)?;
+    Ok(decorate(config, opts, drain))
+}
+
+/// Creates a [`Logger`] based on the given configuration and a [`LogFlusher`] for it.
+///
+/// Use the returned [`LogFlusher`] to ensure buffered records are written before exit.
+///
+/// [`LogFlusher`]: struct.LogFlusher.html
+/// [`Logger`]: slog/struct.Logger.html
+///
+/// # Errors
+/// If the `file` backend is configured and the log file can't be opened.
+pub fn configure_with_flusher(config: Config, opts: &Opts) -> io::Result<(Logger, LogFlusher)> {
+    let drain = backend(&config.backend)?;
+    Ok(decorate_with_flusher(config, opts, drain))
+}
+
+/// Creates a [`Logger`] based on the given configuration that also retains recent records.
+///
+/// The last `capacity` records that pass level filtering are kept in memory as JSON
+/// and can be read with the returned [`RecentLogs`], for example to serve debug endpoints.
+///
+/// [`Logger`]: slog/struct.Logger.html
+/// [`RecentLogs`]: struct.RecentLogs.html
+///
+/// # Errors
+/// If the `file` backend is configured and the log file can't be opened.
+pub fn configure_with_recent_logs(
+    config: Config,
+    opts: &Opts,
+    capacity: usize,
+) -> io::Result<(Logger, RecentLogs)> {
+    let drain = RingBufferDrain::new(capacity, backend(&config.backend)?);
+    let recent = drain.recent();
+    Ok((decorate(config, opts, drain), recent))
+}
+
+/// Type alias for boxed drains returned by `backend`.
+type BackendDrain = Box<dyn Drain<Ok = (), Err = Never> + Send + Sync + RefUnwindSafe + UnwindSafe>;
+
+/// Creates the [`Drain`] for a logging backend.
+///
+/// Drains are boxed to support backends composed of other backends.
+///
+/// [`Drain`]: slog/trait.Drain.html
+fn backend(backend: &LoggingBackend) -> io::Result<BackendDrain> {
+    let drain: BackendDrain = match backend {
         #[cfg(feature = "journald")]
-        LoggingBackend::Journald => decorate(config, opts, JournaldDrain.ignore_res()),
-        LoggingBackend::Json => {
-            // rustc can't infer lifetimes correctly when using Record::module.
-            // Without this allow, clipply complainants that we do not use Record::module.
-            #[allow(clippy::redundant_closure)]
-            let drain = Json::new(stdout())
-                .add_default_keys()
-                .add_key_value(o!(
-                    "module" => FnValue(
-                        |rinfo: &Record| rinfo.module()
-                    )
-                ))
-                .build();
-            let drain = Mutex::new(drain).map(IgnoreResult::new);
-            decorate(config, opts, drain)
+        LoggingBackend::Journald => Box::new(JournaldDrain.ignore_res()),
+        LoggingBackend::File { path } => {
+            let writer = FileWriter::open(path).map_err(|error| {
+                let message = format!("unable to open log file '{}': {}", path, error);
+                io::Error::new(error.kind(), message)
+            })?;
+            Box::new(json(writer))
         }
-    }
+        LoggingBackend::Json => Box::new(json(stdout())),
+        LoggingBackend::JsonStderr => Box::new(json(stderr())),
+        LoggingBackend::Split {
+            errors,
+            rest,
+            threshold,
+        } => Box::new(SplitDrain::new(
+            threshold.clone().into(),
+            self::backend(errors)?,
+            self::backend(rest)?,
+        )),
+    };
+    Ok(drain)
 }
 
-/// Creates a fixed [`Logger`] to be used until configuration is loaded.
+/// Creates a JSON [`Drain`] writing records to the given writer.
 ///
-/// [`Logger`]: slog/struct.Logger.html
-pub fn starter(opts: &Opts) -> Logger {
+/// [`Drain`]: slog/trait.Drain.html
+fn json<W>(writer: W) -> impl Drain<Ok = (), Err = Never> + Send + Sync + RefUnwindSafe + UnwindSafe
+where
+    W: Write + Send + UnwindSafe + 'static,
+{
     // rustc can't infer lifetimes correctly when using Record::module.
     // Without this allow, clipply complainants that we do not use Record::module.
     #[allow(clippy::redundant_closure)]
-    let drain = Json::new(stdout())
+    let drain = Json::new(writer)
         .add_default_keys()
-        .add_key_value(o!("module" => FnValue(|rinfo : &Record| rinfo.module())))
+        .add_key_value(o!(
+            "module" => FnValue(
+                |rinfo: &Record| rinfo.module()
+            )
+        ))
         .build();
-    let drain = Mutex::new(drain).map(IgnoreResult::new);
-    decorator::into_logger(opts, drain, false)
+    Mutex::new(drain).map(IgnoreResult::new)
+}
+
+/// Creates a fixed [`Logger`] to be used until configuration is loaded.
+///
+/// [`Logger`]: slog/struct.Logger.html
+pub fn starter(opts: &Opts) -> Logger {
+    decorator::into_logger(&Config::default(), opts, json(stdout()))
 }
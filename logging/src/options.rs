@@ -1,12 +1,37 @@
+use prometheus::Registry;
+
 /// Additional program options given to the logging configuration.
 #[derive(Clone)]
 pub struct Opts {
     /// The version string to attack to logs.
     pub version: String,
+
+    /// Registry to export logging metrics with, if any.
+    registry: Option<Registry>,
 }
 
 impl Opts {
     pub fn new(version: String) -> Opts {
-        Opts { version }
+        Opts {
+            version,
+            registry: None,
+        }
+    }
+
+    /// Export logging metrics with the given registry.
+    ///
+    /// When asynchronous flushing is enabled the number of records waiting to be
+    /// written is reported by the `logging_async_queue_depth` gauge and records
+    /// dropped because the async queue was full by the `logging_async_dropped_records` counter.
+    /// Registration errors (for example because a logger was already configured with
+    /// the same registry) are ignored and the gauge is not exported.
+    pub fn with_registry(mut self, registry: Registry) -> Opts {
+        self.registry = Some(registry);
+        self
+    }
+
+    /// Registry to export logging metrics with, if any.
+    pub(crate) fn registry(&self) -> Option<&Registry> {
+        self.registry.as_ref()
     }
 }
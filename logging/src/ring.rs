@@ -0,0 +1,186 @@
+use std::collections::VecDeque;
+use std::io;
+use std::io::Write;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use slog::Drain;
+use slog::Never;
+use slog::OwnedKVList;
+use slog::Record;
+
+use super::BackendDrain;
+
+/// Shared access to the records retained by a [`RingBufferDrain`].
+///
+/// Records are stored as serialized JSON documents, oldest first.
+///
+/// [`RingBufferDrain`]: struct.RingBufferDrain.html
+#[derive(Clone)]
+pub struct RecentLogs {
+    capacity: usize,
+    records: Arc<Mutex<VecDeque<String>>>,
+}
+
+impl RecentLogs {
+    fn new(capacity: usize) -> RecentLogs {
+        RecentLogs {
+            capacity,
+            records: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+        }
+    }
+
+    /// Maximum number of records retained.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Return a copy of the retained records, oldest first.
+    pub fn records(&self) -> Vec<String> {
+        let records = self.records.lock().expect("RecentLogs lock poisoned");
+        records.iter().cloned().collect()
+    }
+
+    fn push(&self, record: String) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut records = self.records.lock().expect("RecentLogs lock poisoned");
+        if records.len() == self.capacity {
+            records.pop_front();
+        }
+        records.push_back(record);
+    }
+}
+
+/// [`Drain`] forwarding records to another drain while retaining the most recent ones.
+///
+/// Records are serialized as JSON, just like the `json` backend would, and the
+/// last `capacity` of them are kept in memory to be read with [`RecentLogs`].
+///
+/// [`Drain`]: slog/trait.Drain.html
+/// [`RecentLogs`]: struct.RecentLogs.html
+pub struct RingBufferDrain<D> {
+    inner: D,
+    recent: RecentLogs,
+    ring: BackendDrain,
+}
+
+impl<D> RingBufferDrain<D> {
+    pub fn new(capacity: usize, inner: D) -> RingBufferDrain<D> {
+        let recent = RecentLogs::new(capacity);
+        let writer = RingWriter {
+            buffer: Vec::new(),
+            recent: recent.clone(),
+        };
+        let ring = Box::new(super::json(writer));
+        RingBufferDrain {
+            inner,
+            recent,
+            ring,
+        }
+    }
+
+    /// Access the records retained by this drain.
+    pub fn recent(&self) -> RecentLogs {
+        self.recent.clone()
+    }
+}
+
+impl<D> Drain for RingBufferDrain<D>
+where
+    D: Drain<Ok = (), Err = Never>,
+{
+    type Ok = ();
+    type Err = Never;
+
+    fn log(&self, record: &Record, logger_values: &OwnedKVList) -> Result<Self::Ok, Self::Err> {
+        self.ring.log(record, logger_values)?;
+        self.inner.log(record, logger_values)
+    }
+}
+
+/// Writer splitting the JSON drain output into records for a `RecentLogs` buffer.
+struct RingWriter {
+    buffer: Vec<u8>,
+    recent: RecentLogs,
+}
+
+impl Write for RingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        while let Some(end) = self.buffer.iter().position(|byte| *byte == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line[..end]).into_owned();
+            self.recent.push(line);
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+    use slog::info;
+    use slog::o;
+    use slog::Logger;
+
+    use super::RingBufferDrain;
+    use crate::TestDrain;
+    use crate::TestSink;
+
+    fn messages(records: Vec<String>) -> Vec<String> {
+        records
+            .iter()
+            .map(|record| {
+                let record: Value = serde_json::from_str(record).expect("record to be JSON");
+                record["msg"].as_str().unwrap().to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn keeps_last_records_in_order() {
+        let sink = TestSink::default();
+        let drain = RingBufferDrain::new(3, TestDrain::new(sink.clone()));
+        let recent = drain.recent();
+        let logger = Logger::root(drain, o!());
+        for index in 0..5 {
+            info!(logger, "record {}", index);
+        }
+        assert_eq!(recent.capacity(), 3);
+        assert_eq!(
+            messages(recent.records()),
+            vec!["record 2", "record 3", "record 4"]
+        );
+        assert_eq!(sink.records().len(), 5);
+    }
+
+    #[test]
+    fn records_include_fields() {
+        let drain = RingBufferDrain::new(2, TestDrain::new(TestSink::default()));
+        let recent = drain.recent();
+        let logger = Logger::root(drain, o!("component" => "test"));
+        info!(logger, "with fields"; "index" => 42);
+        let records = recent.records();
+        assert_eq!(records.len(), 1);
+        let record: Value = serde_json::from_str(&records[0]).unwrap();
+        assert_eq!(record["component"], "test");
+        assert_eq!(record["index"], 42);
+    }
+
+    #[test]
+    fn zero_capacity_keeps_nothing() {
+        let sink = TestSink::default();
+        let drain = RingBufferDrain::new(0, TestDrain::new(sink.clone()));
+        let recent = drain.recent();
+        let logger = Logger::root(drain, o!());
+        info!(logger, "dropped");
+        assert!(recent.records().is_empty());
+        assert_eq!(sink.records().len(), 1);
+    }
+}
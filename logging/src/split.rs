@@ -0,0 +1,78 @@
+use slog::Drain;
+use slog::Level;
+use slog::Never;
+use slog::OwnedKVList;
+use slog::Record;
+
+/// [`Drain`] routing records to one of two drains based on their level.
+///
+/// Records at or above the threshold level are sent to the `errors` drain
+/// while all other records are sent to the `rest` drain.
+///
+/// [`Drain`]: slog/trait.Drain.html
+pub struct SplitDrain<E, R> {
+    errors: E,
+    rest: R,
+    threshold: Level,
+}
+
+impl<E, R> SplitDrain<E, R> {
+    pub fn new(threshold: Level, errors: E, rest: R) -> SplitDrain<E, R> {
+        SplitDrain {
+            errors,
+            rest,
+            threshold,
+        }
+    }
+}
+
+impl<E, R> Drain for SplitDrain<E, R>
+where
+    E: Drain<Ok = (), Err = Never>,
+    R: Drain<Ok = (), Err = Never>,
+{
+    type Ok = ();
+    type Err = Never;
+
+    fn log(&self, record: &Record, logger_values: &OwnedKVList) -> Result<Self::Ok, Self::Err> {
+        if record.level().is_at_least(self.threshold) {
+            self.errors.log(record, logger_values)
+        } else {
+            self.rest.log(record, logger_values)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use slog::error;
+    use slog::info;
+    use slog::o;
+    use slog::Level;
+    use slog::Logger;
+
+    use super::SplitDrain;
+    use crate::TestDrain;
+    use crate::TestSink;
+
+    #[test]
+    fn records_are_split() {
+        let errors = TestSink::default();
+        let rest = TestSink::default();
+        let drain = SplitDrain::new(
+            Level::Error,
+            TestDrain::new(errors.clone()),
+            TestDrain::new(rest.clone()),
+        );
+        let logger = Logger::root(drain, o!());
+        error!(logger, "error record");
+        info!(logger, "info record");
+
+        let errors = errors.records();
+        let rest = rest.records();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "error record");
+        assert_eq!(rest.len(), 1);
+        assert_eq!(rest[0].message, "info record");
+    }
+}
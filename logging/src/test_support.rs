@@ -0,0 +1,135 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use slog::o;
+use slog::Drain;
+use slog::Key;
+use slog::Level;
+use slog::Logger;
+use slog::Never;
+use slog::OwnedKVList;
+use slog::Record;
+use slog::Serializer;
+use slog::KV;
+
+/// Log record captured by a [`TestDrain`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TestRecord {
+    /// Structured fields attached to the record and its logger, formatted as strings.
+    pub fields: BTreeMap<String, String>,
+
+    /// Level the record was emitted at.
+    pub level: Level,
+
+    /// Formatted log message.
+    pub message: String,
+}
+
+/// Shared access to the records captured by a [`TestDrain`].
+#[derive(Clone, Default)]
+pub struct TestSink {
+    records: Arc<Mutex<Vec<TestRecord>>>,
+}
+
+impl TestSink {
+    /// Discard all records captured so far.
+    pub fn clear(&self) {
+        self.records.lock().expect("TestSink lock poisoned").clear();
+    }
+
+    /// Return a copy of all records captured so far.
+    pub fn records(&self) -> Vec<TestRecord> {
+        self.records.lock().expect("TestSink lock poisoned").clone()
+    }
+}
+
+/// [`Drain`] that stores records in memory so tests can make assertions on them.
+///
+/// [`Drain`]: slog/trait.Drain.html
+pub struct TestDrain {
+    sink: TestSink,
+}
+
+impl TestDrain {
+    pub fn new(sink: TestSink) -> TestDrain {
+        TestDrain { sink }
+    }
+}
+
+impl Drain for TestDrain {
+    type Ok = ();
+    type Err = Never;
+
+    fn log(&self, record: &Record, logger_values: &OwnedKVList) -> Result<Self::Ok, Self::Err> {
+        let mut fields = FieldsSerializer::default();
+        // Serializing into a map can't fail.
+        let _ = logger_values.serialize(record, &mut fields);
+        let _ = record.kv().serialize(record, &mut fields);
+        let record = TestRecord {
+            fields: fields.0,
+            level: record.level(),
+            message: record.msg().to_string(),
+        };
+        self.sink
+            .records
+            .lock()
+            .expect("TestSink lock poisoned")
+            .push(record);
+        Ok(())
+    }
+}
+
+/// Creates a [`Logger`] that captures all records in memory.
+///
+/// [`Logger`]: slog/struct.Logger.html
+pub fn buffer_logger() -> (Logger, TestSink) {
+    let sink = TestSink::default();
+    let logger = Logger::root(TestDrain::new(sink.clone()), o!());
+    (logger, sink)
+}
+
+/// Collect structured fields into a map of formatted values.
+#[derive(Default)]
+struct FieldsSerializer(BTreeMap<String, String>);
+
+impl Serializer for FieldsSerializer {
+    fn emit_arguments(&mut self, key: Key, value: &fmt::Arguments) -> slog::Result {
+        self.0.insert(key.into(), value.to_string());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use slog::debug;
+    use slog::o;
+    use slog::warn;
+    use slog::Level;
+
+    use super::buffer_logger;
+
+    #[test]
+    fn records_are_captured() {
+        let (logger, sink) = buffer_logger();
+        let logger = logger.new(o!("component" => "test"));
+        debug!(logger, "first message"; "attempt" => 1);
+        warn!(logger, "second {}", "message");
+
+        let records = sink.records();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].level, Level::Debug);
+        assert_eq!(records[0].message, "first message");
+        assert_eq!(records[0].fields.get("attempt"), Some(&"1".to_string()));
+        assert_eq!(
+            records[0].fields.get("component"),
+            Some(&"test".to_string())
+        );
+        assert_eq!(records[1].level, Level::Warning);
+        assert_eq!(records[1].message, "second message");
+
+        sink.clear();
+        assert!(sink.records().is_empty());
+    }
+}
@@ -11,6 +11,7 @@ use super::ActionRequester;
 
 /// Action information returned by the API.
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct ActionInfoResponse {
     pub action: ActionModel,
     pub history: Vec<ActionHistoryItem>,
@@ -18,6 +19,7 @@ pub struct ActionInfoResponse {
 
 /// Parameters passed to the action scheduling API.
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct ActionScheduleRequest {
     /// Optional pre-generated action ID.
     ///
@@ -54,3 +56,47 @@ impl Default for ActionScheduleRequest {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use chrono::Utc;
+    use serde_json::json;
+    use uuid::Uuid;
+
+    use super::ActionInfoResponse;
+    use super::ActionScheduleRequest;
+    use crate::actions::ActionHistoryItem;
+    use crate::actions::ActionModel;
+    use crate::actions::ActionRequester;
+    use crate::actions::ActionState;
+    use crate::clock::FixedClock;
+    use crate::test_support::assert_binary_round_trip;
+
+    #[test]
+    fn info_binary_round_trip() {
+        let clock = FixedClock(Utc.with_ymd_and_hms(2022, 9, 12, 10, 20, 30).unwrap());
+        let action =
+            ActionModel::new_with_clock("test", json!({"a": 1}), ActionRequester::CoreApi, &clock);
+        let history = vec![ActionHistoryItem {
+            action_id: action.id,
+            timestamp: action.created_ts,
+            state: ActionState::New,
+            state_payload: None,
+        }];
+        let response = ActionInfoResponse { action, history };
+        assert_binary_round_trip(&response);
+    }
+
+    #[test]
+    fn schedule_binary_round_trip() {
+        assert_binary_round_trip(&ActionScheduleRequest::default());
+        let request = ActionScheduleRequest {
+            action_id: Some(Uuid::new_v4()),
+            args: json!({"replicas": 3}),
+            created_ts: Some(Utc.with_ymd_and_hms(2022, 9, 12, 10, 20, 30).unwrap()),
+            requester: Some(ActionRequester::CorePlaybook),
+        };
+        assert_binary_round_trip(&request);
+    }
+}
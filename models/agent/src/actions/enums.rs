@@ -1,44 +1,64 @@
-use serde_derive::Deserialize;
-use serde_derive::Serialize;
-
-/// Entity (system, user, ...) that requested the action to be performed.
-#[derive(Clone, Eq, PartialEq, Hash, Debug, Serialize, Deserialize)]
-pub enum ActionRequester {
-    /// Action requested over the Agent API.
-    #[serde(rename = "AGENT_API")]
-    AgentApi,
-
-    /// Action requested over the Replicante Core API.
-    #[serde(rename = "CORE_API")]
-    CoreApi,
-
-    /// Action requested by Replicante Core as part of a playbook.
-    #[serde(rename = "CORE_PLAYBOOK")]
-    CorePlaybook,
-
-    /// Action requested by Replicante Core while converging a declarative cluster.
-    #[serde(rename = "CORE_DECLARATIVE")]
-    CoreDeclarative,
+use thiserror::Error;
+
+/// Error returned when parsing a string that is not a known value of an enum.
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+#[error("unknown {kind} value '{value}'")]
+pub struct UnknownEnumValue {
+    kind: &'static str,
+    value: String,
+}
+
+impl UnknownEnumValue {
+    pub(crate) fn new<S: Into<String>>(kind: &'static str, value: S) -> UnknownEnumValue {
+        UnknownEnumValue {
+            kind,
+            value: value.into(),
+        }
+    }
+
+    /// Name of the enum the value was parsed as.
+    pub fn kind(&self) -> &'static str {
+        self.kind
+    }
+
+    /// The value that could not be parsed.
+    pub fn value(&self) -> &str {
+        &self.value
+    }
 }
 
-/// Current state of an action execution.
-#[derive(Clone, Eq, PartialEq, Hash, Debug, Serialize, Deserialize)]
-pub enum ActionState {
-    /// The action was successfully completed.
-    #[serde(rename = "DONE")]
-    Done,
+forward_compatible_enum! {
+    /// Entity (system, user, ...) that requested the action to be performed.
+    pub enum ActionRequester {
+        /// Action requested over the Agent API.
+        AgentApi => "AGENT_API",
 
-    /// The action ended with an error.
-    #[serde(rename = "FAILED")]
-    Failed,
+        /// Action requested over the Replicante Core API.
+        CoreApi => "CORE_API",
 
-    /// The action has just been sheduled and is not being executed yet.
-    #[serde(rename = "NEW")]
-    New,
+        /// Action requested by Replicante Core as part of a playbook.
+        CorePlaybook => "CORE_PLAYBOOK",
 
-    /// The action was started by the agent and is in progress.
-    #[serde(rename = "RUNNING")]
-    Running,
+        /// Action requested by Replicante Core while converging a declarative cluster.
+        CoreDeclarative => "CORE_DECLARATIVE",
+    }
+}
+
+forward_compatible_enum! {
+    /// Current state of an action execution.
+    pub enum ActionState {
+        /// The action was successfully completed.
+        Done => "DONE",
+
+        /// The action ended with an error.
+        Failed => "FAILED",
+
+        /// The action has just been sheduled and is not being executed yet.
+        New => "NEW",
+
+        /// The action was started by the agent and is in progress.
+        Running => "RUNNING",
+    }
 }
 
 impl ActionState {
@@ -47,3 +67,83 @@ impl ActionState {
         matches!(self, ActionState::Done | ActionState::Failed)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::ActionRequester;
+    use super::ActionState;
+    use super::UnknownEnumValue;
+    use crate::test_support::assert_binary_round_trip;
+
+    #[test]
+    fn known_state_round_trip() {
+        let state: ActionState = serde_json::from_str(r#""RUNNING""#).unwrap();
+        assert_eq!(state, ActionState::Running);
+        assert_eq!(serde_json::to_string(&state).unwrap(), r#""RUNNING""#);
+    }
+
+    #[test]
+    fn parse_known_values() {
+        let requesters = [
+            ("AGENT_API", ActionRequester::AgentApi),
+            ("CORE_API", ActionRequester::CoreApi),
+            ("CORE_PLAYBOOK", ActionRequester::CorePlaybook),
+            ("CORE_DECLARATIVE", ActionRequester::CoreDeclarative),
+        ];
+        for (value, expected) in requesters {
+            assert_eq!(value.parse::<ActionRequester>(), Ok(expected));
+        }
+        let states = [
+            ("DONE", ActionState::Done),
+            ("FAILED", ActionState::Failed),
+            ("NEW", ActionState::New),
+            ("RUNNING", ActionState::Running),
+        ];
+        for (value, expected) in states {
+            assert_eq!(value.parse::<ActionState>(), Ok(expected));
+        }
+    }
+
+    #[test]
+    fn parse_rejects_unknown_values() {
+        let error = "FROZEN".parse::<ActionState>().unwrap_err();
+        assert_eq!(error, UnknownEnumValue::new("ActionState", "FROZEN"));
+        assert_eq!(error.to_string(), "unknown ActionState value 'FROZEN'");
+        let error = "running".parse::<ActionState>().unwrap_err();
+        assert_eq!(error.value(), "running");
+        let error = "CORE_CRON".parse::<ActionRequester>().unwrap_err();
+        assert_eq!(error.kind(), "ActionRequester");
+    }
+
+    #[test]
+    fn try_from_rejects_unknown_values() {
+        assert_eq!(
+            ActionState::try_from("RUNNING").unwrap(),
+            ActionState::Running
+        );
+        let error = ActionRequester::try_from("CORE_CRON").unwrap_err();
+        assert_eq!(error, UnknownEnumValue::new("ActionRequester", "CORE_CRON"));
+    }
+
+    #[test]
+    fn unknown_requester() {
+        let requester: ActionRequester = serde_json::from_str(r#""CORE_CRON""#).unwrap();
+        assert_eq!(requester, ActionRequester::Unknown("CORE_CRON".into()));
+    }
+
+    #[test]
+    fn unknown_state_round_trip() {
+        let state: ActionState = serde_json::from_str(r#""FROZEN""#).unwrap();
+        assert_eq!(state, ActionState::Unknown("FROZEN".into()));
+        assert!(!state.is_finished());
+        assert_eq!(serde_json::to_string(&state).unwrap(), r#""FROZEN""#);
+    }
+
+    #[test]
+    fn binary_round_trip() {
+        assert_binary_round_trip(&ActionRequester::CoreDeclarative);
+        assert_binary_round_trip(&ActionRequester::Unknown("CORE_CRON".into()));
+        assert_binary_round_trip(&ActionState::Done);
+        assert_binary_round_trip(&ActionState::Unknown("FROZEN".into()));
+    }
+}
@@ -1,20 +1,26 @@
 use std::collections::HashMap;
 
 use chrono::DateTime;
+use chrono::Duration;
 use chrono::Utc;
 use serde_derive::Deserialize;
 use serde_derive::Serialize;
 use serde_json::Value as Json;
 use uuid::Uuid;
 
+use crate::clock::Clock;
+use crate::clock::SystemClock;
+
 pub mod api;
 mod enums;
 
 pub use self::enums::ActionRequester;
 pub use self::enums::ActionState;
+pub use self::enums::UnknownEnumValue;
 
 /// Transition history records for actions.
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct ActionHistoryItem {
     /// ID of the action that transitioned.
     pub action_id: Uuid,
@@ -29,9 +35,32 @@ pub struct ActionHistoryItem {
     pub state_payload: Option<Json>,
 }
 
+/// Current state and state payload of an action based on its transition history.
+///
+/// The history does not need to be sorted: the most recent item by `timestamp` is used.
+/// When multiple items share the most recent timestamp the one listed last wins.
+/// Returns `None` if the history is empty.
+pub fn current_state(history: &[ActionHistoryItem]) -> Option<(ActionState, Option<Json>)> {
+    history
+        .iter()
+        .max_by_key(|item| item.timestamp)
+        .map(|item| (item.state.clone(), item.state_payload.clone()))
+}
+
 /// Summary info about an action returned in lists.
+///
+/// The `created_ts` and `finished_ts` attributes may be omitted by older agents.
 #[derive(Clone, Eq, PartialEq, Hash, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct ActionListItem {
+    /// Time the action was first created (by the agent, by core, ...).
+    #[serde(default)]
+    pub created_ts: Option<DateTime<Utc>>,
+
+    /// Time the action entered a finished state.
+    #[serde(default)]
+    pub finished_ts: Option<DateTime<Utc>>,
+
     pub id: Uuid,
     pub kind: String,
     pub state: ActionState,
@@ -39,6 +68,7 @@ pub struct ActionListItem {
 
 /// Action state and metadata information.
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct ActionModel {
     /// Arguments passed to the action when invoked.
     pub args: Json,
@@ -70,3 +100,218 @@ pub struct ActionModel {
     /// Optional payload attached to the current state.
     pub state_payload: Option<Json>,
 }
+
+impl ActionModel {
+    /// Create a `New` action of the given kind, timestamped with the current time.
+    pub fn new<S>(kind: S, args: Json, requester: ActionRequester) -> ActionModel
+    where
+        S: Into<String>,
+    {
+        ActionModel::new_with_clock(kind, args, requester, &SystemClock)
+    }
+
+    /// Create a `New` action of the given kind, timestamped with the given `Clock`.
+    pub fn new_with_clock<S>(
+        kind: S,
+        args: Json,
+        requester: ActionRequester,
+        clock: &dyn Clock,
+    ) -> ActionModel
+    where
+        S: Into<String>,
+    {
+        let now = clock.now();
+        ActionModel {
+            args,
+            created_ts: now,
+            finished_ts: None,
+            headers: HashMap::new(),
+            id: Uuid::new_v4(),
+            kind: kind.into(),
+            requester,
+            scheduled_ts: now,
+            state: ActionState::New,
+            state_payload: None,
+        }
+    }
+
+    /// Time the action took to finish, if it is finished.
+    pub fn duration(&self) -> Option<Duration> {
+        self.finished_ts
+            .map(|finished_ts| finished_ts - self.created_ts)
+    }
+
+    /// True if the action was started and is in progress.
+    pub fn is_running(&self) -> bool {
+        self.state == ActionState::Running
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use chrono::Duration;
+    use chrono::TimeZone;
+    use chrono::Utc;
+    use serde_json::json;
+    use serde_json::Value as Json;
+    use uuid::Uuid;
+
+    use super::current_state;
+    use super::ActionHistoryItem;
+    use super::ActionListItem;
+    use super::ActionModel;
+    use super::ActionRequester;
+    use super::ActionState;
+    use crate::clock::FixedClock;
+    use crate::test_support::assert_binary_round_trip;
+
+    const ID: &str = "6b3e9f5c-2f3a-4b6e-8f2a-3c9d1e7a5b40";
+
+    fn action(state: ActionState) -> ActionModel {
+        let created_ts = Utc.with_ymd_and_hms(2022, 9, 12, 10, 20, 30).unwrap();
+        ActionModel {
+            args: json!({}),
+            created_ts,
+            finished_ts: None,
+            headers: HashMap::new(),
+            id: Uuid::parse_str(ID).unwrap(),
+            kind: "test".into(),
+            requester: ActionRequester::AgentApi,
+            scheduled_ts: created_ts,
+            state,
+            state_payload: None,
+        }
+    }
+
+    fn history_item(second: u32, state: ActionState, payload: Option<Json>) -> ActionHistoryItem {
+        ActionHistoryItem {
+            action_id: Uuid::parse_str(ID).unwrap(),
+            timestamp: Utc.with_ymd_and_hms(2022, 9, 12, 10, 20, second).unwrap(),
+            state,
+            state_payload: payload,
+        }
+    }
+
+    #[test]
+    fn current_state_empty_history() {
+        assert_eq!(current_state(&[]), None);
+    }
+
+    #[test]
+    fn current_state_unsorted_history() {
+        let history = vec![
+            history_item(10, ActionState::Running, Some(json!({"step": 1}))),
+            history_item(30, ActionState::Done, Some(json!({"result": "ok"}))),
+            history_item(0, ActionState::New, None),
+            history_item(20, ActionState::Running, Some(json!({"step": 2}))),
+        ];
+        let state = current_state(&history);
+        assert_eq!(
+            state,
+            Some((ActionState::Done, Some(json!({"result": "ok"}))))
+        );
+    }
+
+    #[test]
+    fn current_state_ties_use_last_item() {
+        let history = vec![
+            history_item(10, ActionState::Running, None),
+            history_item(10, ActionState::Failed, Some(json!("error"))),
+            history_item(0, ActionState::New, None),
+        ];
+        let state = current_state(&history);
+        assert_eq!(state, Some((ActionState::Failed, Some(json!("error")))));
+    }
+
+    #[test]
+    fn new_with_fixed_clock() {
+        let now = Utc.with_ymd_and_hms(2022, 9, 12, 10, 20, 30).unwrap();
+        let clock = FixedClock(now);
+        let action =
+            ActionModel::new_with_clock("test", json!({}), ActionRequester::AgentApi, &clock);
+        assert_eq!(action.created_ts, now);
+        assert_eq!(action.scheduled_ts, now);
+        assert_eq!(action.state, ActionState::New);
+    }
+
+    #[test]
+    fn duration_finished() {
+        let mut action = action(ActionState::Done);
+        action.finished_ts = Some(action.created_ts + Duration::seconds(90));
+        assert_eq!(action.duration(), Some(Duration::seconds(90)));
+        assert!(!action.is_running());
+    }
+
+    #[test]
+    fn duration_running() {
+        let action = action(ActionState::Running);
+        assert_eq!(action.duration(), None);
+        assert!(action.is_running());
+    }
+
+    #[test]
+    fn list_item_from_json_without_timestamps() {
+        let payload = format!(r#"{{"id":"{}","kind":"test","state":"RUNNING"}}"#, ID);
+        let item: ActionListItem = serde_json::from_str(&payload).unwrap();
+        let expected = ActionListItem {
+            created_ts: None,
+            finished_ts: None,
+            id: Uuid::parse_str(ID).unwrap(),
+            kind: "test".into(),
+            state: ActionState::Running,
+        };
+        assert_eq!(item, expected);
+    }
+
+    #[test]
+    fn list_item_round_trip() {
+        let item = ActionListItem {
+            created_ts: Some(Utc.with_ymd_and_hms(2022, 9, 12, 10, 20, 30).unwrap()),
+            finished_ts: Some(Utc.with_ymd_and_hms(2022, 9, 12, 10, 21, 0).unwrap()),
+            id: Uuid::parse_str(ID).unwrap(),
+            kind: "test".into(),
+            state: ActionState::Done,
+        };
+        let payload = serde_json::to_string(&item).unwrap();
+        let expected = format!(
+            concat!(
+                r#"{{"created_ts":"2022-09-12T10:20:30Z","finished_ts":"2022-09-12T10:21:00Z","#,
+                r#""id":"{}","kind":"test","state":"DONE"}}"#,
+            ),
+            ID
+        );
+        assert_eq!(payload, expected);
+        let decoded: ActionListItem = serde_json::from_str(&payload).unwrap();
+        assert_eq!(decoded, item);
+    }
+
+    #[test]
+    fn binary_round_trip() {
+        let mut action = action(ActionState::Failed);
+        action.args = json!({"force": true, "nodes": [1, 2, 3], "ratio": 0.5, "skip": null});
+        action.finished_ts = Some(action.created_ts + Duration::seconds(90));
+        action.headers.insert("trace".into(), "abc".into());
+        action.requester = ActionRequester::Unknown("CORE_CRON".into());
+        action.state_payload = Some(json!({"error": "failed"}));
+        assert_binary_round_trip(&action);
+
+        let history = ActionHistoryItem {
+            action_id: action.id,
+            timestamp: action.created_ts,
+            state: ActionState::Unknown("FROZEN".into()),
+            state_payload: Some(json!(["a", {"b": -1}])),
+        };
+        assert_binary_round_trip(&history);
+
+        let item = ActionListItem {
+            created_ts: Some(action.created_ts),
+            finished_ts: None,
+            id: action.id,
+            kind: "test".into(),
+            state: ActionState::Running,
+        };
+        assert_binary_round_trip(&item);
+    }
+}
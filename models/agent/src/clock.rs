@@ -0,0 +1,32 @@
+//! Sources of the current time for models that record timestamps.
+//!
+//! Code that creates timestamped models should accept a [`Clock`] so tests
+//! can inject deterministic timestamps with a [`FixedClock`].
+use chrono::DateTime;
+use chrono::Utc;
+
+/// Source of the current UTC time.
+pub trait Clock {
+    /// Return the current time.
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// `Clock` that always returns the same time, useful in tests.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct FixedClock(pub DateTime<Utc>);
+
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.0
+    }
+}
+
+/// `Clock` returning the current system time.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
@@ -3,6 +3,7 @@ use serde_derive::Serialize;
 
 /// Agent-specific information.
 #[derive(Clone, Eq, PartialEq, Hash, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct AgentInfo {
     pub version: AgentVersion,
 }
@@ -15,6 +16,7 @@ impl AgentInfo {
 
 /// Agent version details.
 #[derive(Clone, Eq, PartialEq, Hash, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct AgentVersion {
     pub checkout: String,
     pub number: String,
@@ -39,8 +41,6 @@ impl AgentVersion {
 #[cfg(test)]
 mod tests {
     mod info {
-        use serde_json;
-
         use super::super::AgentInfo;
         use super::super::AgentVersion;
 
@@ -63,8 +63,6 @@ mod tests {
     }
 
     mod version {
-        use serde_json;
-
         use super::super::AgentVersion;
 
         #[test]
@@ -83,4 +81,14 @@ mod tests {
             assert_eq!(payload, expected);
         }
     }
+
+    #[test]
+    fn binary_round_trip() {
+        use super::AgentInfo;
+        use super::AgentVersion;
+        use crate::test_support::assert_binary_round_trip;
+
+        let agent = AgentInfo::new(AgentVersion::new("abc123", "1.2.3", "tainted"));
+        assert_binary_round_trip(&agent);
+    }
 }
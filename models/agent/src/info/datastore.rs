@@ -1,8 +1,11 @@
+use std::fmt;
+
 use serde_derive::Deserialize;
 use serde_derive::Serialize;
 
 /// Datastore version details.
 #[derive(Clone, Eq, PartialEq, Hash, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct DatastoreInfo {
     pub cluster_display_name: Option<String>,
     pub cluster_id: String,
@@ -33,13 +36,208 @@ impl DatastoreInfo {
             version: version.into(),
         }
     }
+
+    /// List attributes that changed between this and the `other` snapshot.
+    ///
+    /// Changes are reported from `self` to `other` and in attribute name order.
+    /// Identical snapshots return an empty list.
+    pub fn diff(&self, other: &DatastoreInfo) -> Vec<DatastoreChange> {
+        let mut changes = Vec::new();
+        if self.cluster_display_name != other.cluster_display_name {
+            changes.push(DatastoreChange::ClusterDisplayName {
+                from: self.cluster_display_name.clone(),
+                to: other.cluster_display_name.clone(),
+            });
+        }
+        if self.cluster_id != other.cluster_id {
+            changes.push(DatastoreChange::ClusterId {
+                from: self.cluster_id.clone(),
+                to: other.cluster_id.clone(),
+            });
+        }
+        if self.kind != other.kind {
+            changes.push(DatastoreChange::Kind {
+                from: self.kind.clone(),
+                to: other.kind.clone(),
+            });
+        }
+        if self.node_id != other.node_id {
+            changes.push(DatastoreChange::NodeId {
+                from: self.node_id.clone(),
+                to: other.node_id.clone(),
+            });
+        }
+        if self.version != other.version {
+            changes.push(DatastoreChange::Version {
+                from: self.version.clone(),
+                to: other.version.clone(),
+            });
+        }
+        changes
+    }
+
+    /// Normalised kind of the datastore, for comparisons and metrics labels.
+    pub fn datastore_kind(&self) -> DatastoreKind {
+        DatastoreKind::from(self.kind.as_str())
+    }
+
+    /// Name to display for the cluster the node belongs to.
+    ///
+    /// Returns the `cluster_display_name` when set and not empty, the `cluster_id` otherwise.
+    pub fn display_name(&self) -> &str {
+        match self.cluster_display_name.as_deref() {
+            Some(name) if !name.is_empty() => name,
+            _ => &self.cluster_id,
+        }
+    }
+}
+
+/// Change to an attribute of `DatastoreInfo` reported by `DatastoreInfo::diff`.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub enum DatastoreChange {
+    ClusterDisplayName {
+        from: Option<String>,
+        to: Option<String>,
+    },
+    ClusterId {
+        from: String,
+        to: String,
+    },
+    Kind {
+        from: String,
+        to: String,
+    },
+    NodeId {
+        from: String,
+        to: String,
+    },
+    Version {
+        from: String,
+        to: String,
+    },
+}
+
+/// Normalised datastore kind, parsed case-insensitively from `DatastoreInfo::kind`.
+///
+/// Agents report the kind as a free string so the same datastore may be reported
+/// with different casing. Parse it into a `DatastoreKind` to compare kinds or
+/// to label metrics with the canonical `DatastoreKind::as_str` value.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub enum DatastoreKind {
+    Kafka,
+    MongoDB,
+    Zookeeper,
+
+    /// Any other datastore, with the kind normalised to lowercase.
+    Other(String),
+}
+
+impl DatastoreKind {
+    /// Canonical (lowercase) name of the datastore kind.
+    pub fn as_str(&self) -> &str {
+        match self {
+            DatastoreKind::Kafka => "kafka",
+            DatastoreKind::MongoDB => "mongodb",
+            DatastoreKind::Zookeeper => "zookeeper",
+            DatastoreKind::Other(kind) => kind,
+        }
+    }
+}
+
+impl fmt::Display for DatastoreKind {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.write_str(self.as_str())
+    }
+}
+
+impl From<&str> for DatastoreKind {
+    fn from(kind: &str) -> DatastoreKind {
+        let kind = kind.trim().to_lowercase();
+        match kind.as_str() {
+            "kafka" => DatastoreKind::Kafka,
+            "mongodb" => DatastoreKind::MongoDB,
+            "zookeeper" => DatastoreKind::Zookeeper,
+            _ => DatastoreKind::Other(kind),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use serde_json;
-
+    use super::DatastoreChange;
     use super::DatastoreInfo;
+    use super::DatastoreKind;
+    use crate::test_support::assert_binary_round_trip;
+
+    #[test]
+    fn diff_display_name() {
+        let old = DatastoreInfo::new("id", "DB", "Name", "1.2.3", None);
+        let new = DatastoreInfo::new("id", "DB", "Name", "1.2.3", Some("display".into()));
+        let changes = old.diff(&new);
+        assert_eq!(
+            changes,
+            vec![DatastoreChange::ClusterDisplayName {
+                from: None,
+                to: Some("display".into()),
+            }]
+        );
+    }
+
+    #[test]
+    fn diff_identical() {
+        let old = DatastoreInfo::new("id", "DB", "Name", "1.2.3", None);
+        let new = old.clone();
+        assert!(old.diff(&new).is_empty());
+    }
+
+    #[test]
+    fn diff_version() {
+        let old = DatastoreInfo::new("id", "DB", "Name", "1.2.3", None);
+        let new = DatastoreInfo::new("id", "DB", "Name", "1.3.0", None);
+        let changes = old.diff(&new);
+        assert_eq!(
+            changes,
+            vec![DatastoreChange::Version {
+                from: "1.2.3".into(),
+                to: "1.3.0".into(),
+            }]
+        );
+    }
+
+    #[test]
+    fn datastore_kind_ignores_case() {
+        for kind in ["MongoDB", "mongodb", "MONGODB"] {
+            let info = DatastoreInfo::new("id", kind, "Name", "1.2.3", None);
+            assert_eq!(info.datastore_kind(), DatastoreKind::MongoDB);
+            assert_eq!(info.datastore_kind().as_str(), "mongodb");
+            assert_eq!(info.kind, kind);
+        }
+    }
+
+    #[test]
+    fn datastore_kind_other() {
+        let info = DatastoreInfo::new("id", "DB", "Name", "1.2.3", None);
+        assert_eq!(info.datastore_kind(), DatastoreKind::Other("db".into()));
+        assert_eq!(info.datastore_kind().to_string(), "db");
+    }
+
+    #[test]
+    fn display_name_absent() {
+        let info = DatastoreInfo::new("id", "DB", "Name", "1.2.3", None);
+        assert_eq!(info.display_name(), "id");
+    }
+
+    #[test]
+    fn display_name_empty() {
+        let info = DatastoreInfo::new("id", "DB", "Name", "1.2.3", Some("".into()));
+        assert_eq!(info.display_name(), "id");
+    }
+
+    #[test]
+    fn display_name_present() {
+        let info = DatastoreInfo::new("id", "DB", "Name", "1.2.3", Some("display name".into()));
+        assert_eq!(info.display_name(), "display name");
+    }
 
     #[test]
     fn from_json() {
@@ -84,4 +282,12 @@ mod tests {
         );
         assert_eq!(payload, expected);
     }
+
+    #[test]
+    fn binary_round_trip() {
+        let info = DatastoreInfo::new("id", "DB", "Name", "1.2.3", None);
+        assert_binary_round_trip(&info);
+        let info = DatastoreInfo::new("id", "DB", "Name", "1.2.3", Some("display".into()));
+        assert_binary_round_trip(&info);
+    }
 }
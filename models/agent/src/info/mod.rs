@@ -1,12 +1,16 @@
 mod agent;
 mod datastore;
 mod shard;
+mod snapshot;
 
 pub use self::agent::AgentInfo;
 pub use self::agent::AgentVersion;
+pub use self::datastore::DatastoreChange;
 pub use self::datastore::DatastoreInfo;
+pub use self::datastore::DatastoreKind;
 pub use self::shard::CommitOffset;
 pub use self::shard::CommitUnit;
 pub use self::shard::Shard;
 pub use self::shard::ShardRole;
 pub use self::shard::Shards;
+pub use self::snapshot::Snapshot;
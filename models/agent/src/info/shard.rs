@@ -1,8 +1,17 @@
 use serde_derive::Deserialize;
 use serde_derive::Serialize;
 
+use replicante_models_api::HealthStatus;
+
 /// Information about the current commit offset of a shard or replication lag.
+///
+/// Offsets with a `seconds` unit also accept a duration string as their value when
+/// deserialized, either in human form (`"12345s"`, `"1h30m"`) or as an ISO-8601
+/// duration (`"PT1H30M"`).
+/// Values are always normalised to, and serialized as, an integer number of seconds.
 #[derive(Clone, Eq, PartialEq, Hash, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(try_from = "RawCommitOffset")]
 pub struct CommitOffset {
     pub unit: CommitUnit,
     pub value: i64,
@@ -22,8 +31,83 @@ impl CommitOffset {
     }
 }
 
+/// Wire format of `CommitOffset` values accepted during deserialization.
+#[derive(Deserialize)]
+struct RawCommitOffset {
+    unit: CommitUnit,
+    value: RawOffsetValue,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawOffsetValue {
+    Integer(i64),
+    Duration(String),
+}
+
+impl TryFrom<RawCommitOffset> for CommitOffset {
+    type Error = String;
+
+    fn try_from(raw: RawCommitOffset) -> Result<CommitOffset, String> {
+        let value = match (raw.value, &raw.unit) {
+            (RawOffsetValue::Integer(value), _) => value,
+            (RawOffsetValue::Duration(duration), CommitUnit::Seconds) => {
+                parse_duration_seconds(&duration)
+                    .ok_or_else(|| format!("invalid duration '{}'", duration))?
+            }
+            (RawOffsetValue::Duration(duration), CommitUnit::Unit(unit)) => {
+                return Err(format!(
+                    "duration '{}' is only valid for offsets in seconds, not '{}'",
+                    duration, unit
+                ))
+            }
+        };
+        Ok(CommitOffset::new(value, raw.unit))
+    }
+}
+
+/// Parse a human (`1h30m`) or ISO-8601 (`PT1H30M`) duration string into seconds.
+fn parse_duration_seconds(duration: &str) -> Option<i64> {
+    let duration = duration.trim();
+    let (human, iso) = match duration.strip_prefix('P') {
+        Some(iso) => (iso.replacen('T', "", 1).to_ascii_lowercase(), true),
+        None => (duration.to_string(), false),
+    };
+    // ISO-8601 durations use `M` for months before the `T` separator: reject them.
+    if iso && duration.split('T').next().unwrap_or_default().contains('M') {
+        return None;
+    }
+    if human.is_empty() {
+        return None;
+    }
+
+    let mut total: i64 = 0;
+    let mut number = String::new();
+    for c in human.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let scale = match c {
+            'd' => 86_400,
+            'h' => 3_600,
+            'm' => 60,
+            's' => 1,
+            _ => return None,
+        };
+        let value: i64 = number.parse().ok()?;
+        total = total.checked_add(value.checked_mul(scale)?)?;
+        number.clear();
+    }
+    if !number.is_empty() {
+        return None;
+    }
+    Some(total)
+}
+
 /// Unit of commit offsets or replica lags.
 #[derive(Clone, Eq, PartialEq, Hash, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum CommitUnit {
     #[serde(rename = "seconds")]
     Seconds,
@@ -44,6 +128,7 @@ impl CommitUnit {
 
 /// Information about a shard on a node.
 #[derive(Clone, Eq, PartialEq, Hash, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Shard {
     pub commit_offset: Option<CommitOffset>,
     pub id: String,
@@ -72,6 +157,7 @@ impl Shard {
 
 /// Information about shards on a node.
 #[derive(Clone, Eq, PartialEq, Hash, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Shards {
     pub shards: Vec<Shard>,
 }
@@ -80,10 +166,50 @@ impl Shards {
     pub fn new(shards: Vec<Shard>) -> Shards {
         Shards { shards }
     }
+
+    /// Copy of the shards sorted by shard ID.
+    ///
+    /// Agents may report shards in any order so normalise shards before comparing
+    /// them to avoid reporting changes when only the order differs.
+    pub fn normalized(&self) -> Shards {
+        let mut shards = self.shards.clone();
+        shards.sort_by(|left, right| left.id.cmp(&right.id));
+        Shards { shards }
+    }
+
+    /// Summarise the health of the node from its shards.
+    ///
+    /// The node is `Failed` if none of its shards is a primary and `Degraded` if any
+    /// secondary lags behind by more than `lag_threshold`.
+    /// Lags are only compared to the threshold when they are expressed in the same unit.
+    pub fn health(&self, lag_threshold: &CommitOffset) -> HealthStatus {
+        let has_primary = self
+            .shards
+            .iter()
+            .any(|shard| shard.role == ShardRole::Primary);
+        if !has_primary {
+            return HealthStatus::Failed("no primary shard found".into());
+        }
+        let lagging = self.shards.iter().find(|shard| {
+            shard.role == ShardRole::Secondary
+                && shard
+                    .lag
+                    .iter()
+                    .any(|lag| lag.unit == lag_threshold.unit && lag.value > lag_threshold.value)
+        });
+        match lagging {
+            None => HealthStatus::Healthy,
+            Some(shard) => HealthStatus::Degraded(format!(
+                "secondary shard '{}' lags behind by more than {}",
+                shard.id, lag_threshold.value,
+            )),
+        }
+    }
 }
 
 /// Possible shard roles.
 #[derive(Clone, Eq, PartialEq, Hash, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum ShardRole {
     #[serde(rename = "primary")]
     Primary,
@@ -97,11 +223,13 @@ pub enum ShardRole {
 
 #[cfg(test)]
 mod tests {
-    use serde_json;
+    use replicante_models_api::HealthStatus;
 
     use super::CommitOffset;
     use super::Shard;
     use super::ShardRole;
+    use super::Shards;
+    use crate::test_support::assert_binary_round_trip;
 
     #[test]
     fn primary_from_json() {
@@ -262,4 +390,137 @@ mod tests {
         );
         assert_eq!(payload, expected);
     }
+
+    #[test]
+    fn seconds_from_integer_or_duration() {
+        let integer: CommitOffset =
+            serde_json::from_str(r#"{"unit":"seconds","value":12345}"#).unwrap();
+        let duration: CommitOffset =
+            serde_json::from_str(r#"{"unit":"seconds","value":"12345s"}"#).unwrap();
+        assert_eq!(integer, CommitOffset::seconds(12345));
+        assert_eq!(duration, integer);
+        let payload = serde_json::to_string(&duration).unwrap();
+        assert_eq!(payload, r#"{"unit":"seconds","value":12345}"#);
+    }
+
+    #[test]
+    fn seconds_from_human_and_iso_durations() {
+        let cases = [
+            ("1h30m", 5400),
+            ("2d1s", 172_801),
+            ("PT1H30M", 5400),
+            ("P1DT2S", 86_402),
+        ];
+        for (duration, expected) in cases {
+            let payload = format!(r#"{{"unit":"seconds","value":"{}"}}"#, duration);
+            let offset: CommitOffset = serde_json::from_str(&payload).unwrap();
+            assert_eq!(offset, CommitOffset::seconds(expected), "{}", duration);
+        }
+    }
+
+    #[test]
+    fn invalid_durations_are_rejected() {
+        for duration in ["", "12", "12x", "P1M", "PT"] {
+            let payload = format!(r#"{{"unit":"seconds","value":"{}"}}"#, duration);
+            let result: Result<CommitOffset, _> = serde_json::from_str(&payload);
+            assert!(result.is_err(), "{}", duration);
+        }
+    }
+
+    #[test]
+    fn duration_requires_seconds_unit() {
+        let payload = r#"{"unit":{"unit":"ops"},"value":"12s"}"#;
+        let result: Result<CommitOffset, _> = serde_json::from_str(payload);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn binary_round_trip() {
+        let shards = Shards::new(vec![
+            Shard::new(
+                "shard-1",
+                ShardRole::Primary,
+                Some(CommitOffset::seconds(12345)),
+                None,
+            ),
+            Shard::new(
+                "shard-2",
+                ShardRole::Secondary,
+                Some(CommitOffset::unit(-42, "ops")),
+                Some(CommitOffset::seconds(3)),
+            ),
+            Shard::new("shard-3", ShardRole::Unknown("arbiter".into()), None, None),
+        ]);
+        assert_binary_round_trip(&shards);
+    }
+
+    fn health_shards(lag: i64) -> Shards {
+        Shards::new(vec![
+            Shard::new("shard-1", ShardRole::Primary, None, None),
+            Shard::new(
+                "shard-2",
+                ShardRole::Secondary,
+                None,
+                Some(CommitOffset::seconds(lag)),
+            ),
+            Shard::new(
+                "shard-3",
+                ShardRole::Secondary,
+                None,
+                Some(CommitOffset::unit(1000, "ops")),
+            ),
+        ])
+    }
+
+    #[test]
+    fn health_all_healthy() {
+        let shards = health_shards(5);
+        assert_eq!(
+            shards.health(&CommitOffset::seconds(10)),
+            HealthStatus::Healthy
+        );
+    }
+
+    #[test]
+    fn health_lagging_secondary() {
+        let shards = health_shards(15);
+        match shards.health(&CommitOffset::seconds(10)) {
+            HealthStatus::Degraded(details) => assert!(details.contains("shard-2")),
+            status => panic!("unexpected health status {:?}", status),
+        }
+    }
+
+    #[test]
+    fn health_without_primary() {
+        let shards = Shards::new(vec![Shard::new(
+            "shard-1",
+            ShardRole::Secondary,
+            None,
+            Some(CommitOffset::seconds(0)),
+        )]);
+        match shards.health(&CommitOffset::seconds(10)) {
+            HealthStatus::Failed(_) => (),
+            status => panic!("unexpected health status {:?}", status),
+        }
+    }
+
+    #[test]
+    fn normalized_ignores_order() {
+        let first = Shard::new("a", ShardRole::Primary, None, None);
+        let second = Shard::new("b", ShardRole::Secondary, None, None);
+        let third = Shard::new("c", ShardRole::Secondary, None, None);
+        let one = Shards::new(vec![third.clone(), first.clone(), second.clone()]);
+        let two = Shards::new(vec![second.clone(), third.clone(), first.clone()]);
+        assert_ne!(one, two);
+        assert_eq!(one.normalized(), two.normalized());
+
+        let normalized = one.normalized();
+        let ids: Vec<&str> = normalized
+            .shards
+            .iter()
+            .map(|shard| shard.id.as_str())
+            .collect();
+        assert_eq!(ids, vec!["a", "b", "c"]);
+        assert_eq!(normalized.normalized(), normalized);
+    }
 }
@@ -0,0 +1,122 @@
+use chrono::DateTime;
+use chrono::Duration;
+use chrono::Utc;
+use serde_derive::Deserialize;
+use serde_derive::Serialize;
+
+use crate::clock::Clock;
+use crate::clock::SystemClock;
+
+/// Model data along with the time it was observed at.
+///
+/// Snapshots serialize as the wrapped model with an additional `observed_ts` attribute.
+/// The attribute is optional for compatibility with agents that don't report it.
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Snapshot<T> {
+    /// The observed model data.
+    #[serde(flatten)]
+    pub data: T,
+
+    /// Time the data was observed at, if known.
+    #[serde(default)]
+    pub observed_ts: Option<DateTime<Utc>>,
+}
+
+impl<T> Snapshot<T> {
+    /// Wrap data observed now.
+    pub fn new(data: T) -> Snapshot<T> {
+        Snapshot::new_with_clock(data, &SystemClock)
+    }
+
+    /// Wrap data observed at the current time of the given `Clock`.
+    pub fn new_with_clock(data: T, clock: &dyn Clock) -> Snapshot<T> {
+        Snapshot {
+            data,
+            observed_ts: Some(clock.now()),
+        }
+    }
+
+    /// True if the data was observed more than `max_age` ago.
+    ///
+    /// Snapshots without an `observed_ts` are always stale since their age is unknown.
+    pub fn is_stale(&self, max_age: Duration) -> bool {
+        self.is_stale_with_clock(max_age, &SystemClock)
+    }
+
+    /// Same as `Snapshot::is_stale` but the current time is provided by the given `Clock`.
+    pub fn is_stale_with_clock(&self, max_age: Duration, clock: &dyn Clock) -> bool {
+        match self.observed_ts {
+            None => true,
+            Some(observed_ts) => clock.now() - observed_ts > max_age,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use chrono::TimeZone;
+    use chrono::Utc;
+
+    use super::Snapshot;
+    use crate::clock::FixedClock;
+    use crate::info::CommitOffset;
+    use crate::info::Shard;
+    use crate::info::ShardRole;
+    use crate::info::Shards;
+    use crate::test_support::assert_binary_round_trip;
+
+    fn shards() -> Shards {
+        let shard = Shard::new("shard-1", ShardRole::Primary, None, None);
+        Shards::new(vec![shard])
+    }
+
+    #[test]
+    fn fresh_snapshot() {
+        let observed = Utc.with_ymd_and_hms(2022, 9, 12, 10, 20, 30).unwrap();
+        let snapshot = Snapshot::new_with_clock(shards(), &FixedClock(observed));
+        let now = FixedClock(observed + Duration::seconds(10));
+        assert!(!snapshot.is_stale_with_clock(Duration::seconds(30), &now));
+    }
+
+    #[test]
+    fn old_snapshot() {
+        let observed = Utc.with_ymd_and_hms(2022, 9, 12, 10, 20, 30).unwrap();
+        let snapshot = Snapshot::new_with_clock(shards(), &FixedClock(observed));
+        let now = FixedClock(observed + Duration::seconds(60));
+        assert!(snapshot.is_stale_with_clock(Duration::seconds(30), &now));
+    }
+
+    #[test]
+    fn from_json_without_timestamp() {
+        let payload =
+            r#"{"shards":[{"commit_offset":null,"id":"shard-1","lag":null,"role":"primary"}]}"#;
+        let snapshot: Snapshot<Shards> = serde_json::from_str(payload).unwrap();
+        assert_eq!(snapshot.data, shards());
+        assert_eq!(snapshot.observed_ts, None);
+        assert!(snapshot.is_stale(Duration::days(365)));
+    }
+
+    #[test]
+    fn to_json() {
+        let observed = Utc.with_ymd_and_hms(2022, 9, 12, 10, 20, 30).unwrap();
+        let mut data = shards();
+        data.shards[0].lag = Some(CommitOffset::seconds(2));
+        let snapshot = Snapshot::new_with_clock(data, &FixedClock(observed));
+        let payload = serde_json::to_string(&snapshot).unwrap();
+        let expected = concat!(
+            r#"{"shards":[{"commit_offset":null,"id":"shard-1","#,
+            r#""lag":{"unit":"seconds","value":2},"role":"primary"}],"#,
+            r#""observed_ts":"2022-09-12T10:20:30Z"}"#,
+        );
+        assert_eq!(payload, expected);
+    }
+
+    #[test]
+    fn binary_round_trip() {
+        let observed = Utc.with_ymd_and_hms(2022, 9, 12, 10, 20, 30).unwrap();
+        let snapshot = Snapshot::new_with_clock(shards(), &FixedClock(observed));
+        assert_binary_round_trip(&snapshot);
+    }
+}
@@ -0,0 +1,66 @@
+//! Structured logging context for node models.
+//!
+//! Models implement `slog::KV` so node details are logged with consistent keys:
+//!
+//! ```ignore
+//! info!(logger, "Datastore version changed"; &datastore);
+//! ```
+use slog::Record;
+use slog::Result;
+use slog::Serializer;
+use slog::KV;
+
+use crate::info::AgentVersion;
+use crate::info::DatastoreInfo;
+
+impl KV for AgentVersion {
+    fn serialize(&self, _: &Record, serializer: &mut dyn Serializer) -> Result {
+        serializer.emit_str("agent_checkout", &self.checkout)?;
+        serializer.emit_str("agent_taint", &self.taint)?;
+        serializer.emit_str("agent_version", &self.number)
+    }
+}
+
+impl KV for DatastoreInfo {
+    fn serialize(&self, _: &Record, serializer: &mut dyn Serializer) -> Result {
+        serializer.emit_str("cluster_display_name", self.display_name())?;
+        serializer.emit_str("cluster_id", &self.cluster_id)?;
+        serializer.emit_str("datastore_kind", &self.kind)?;
+        serializer.emit_str("datastore_version", &self.version)?;
+        serializer.emit_str("node_id", &self.node_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use slog::info;
+
+    use replicante_logging::buffer_logger;
+
+    use crate::info::AgentVersion;
+    use crate::info::DatastoreInfo;
+
+    #[test]
+    fn agent_version_keys() {
+        let (logger, sink) = buffer_logger();
+        let version = AgentVersion::new("abcdef", "1.2.3", "not tainted");
+        info!(logger, "test"; &version);
+        let fields = &sink.records()[0].fields;
+        assert_eq!(fields["agent_checkout"], "abcdef");
+        assert_eq!(fields["agent_taint"], "not tainted");
+        assert_eq!(fields["agent_version"], "1.2.3");
+    }
+
+    #[test]
+    fn datastore_info_keys() {
+        let (logger, sink) = buffer_logger();
+        let info = DatastoreInfo::new("id", "DB", "node", "4.5.6", None);
+        info!(logger, "test"; &info);
+        let fields = &sink.records()[0].fields;
+        assert_eq!(fields["cluster_display_name"], "id");
+        assert_eq!(fields["cluster_id"], "id");
+        assert_eq!(fields["datastore_kind"], "DB");
+        assert_eq!(fields["datastore_version"], "4.5.6");
+        assert_eq!(fields["node_id"], "node");
+    }
+}
@@ -1,2 +1,23 @@
+#[macro_use]
+mod macros;
+
 pub mod actions;
+pub mod clock;
 pub mod info;
+#[cfg(feature = "with_slog")]
+mod kv;
+mod parse;
+#[cfg(feature = "schema")]
+pub mod schema;
+#[cfg(test)]
+mod test_support;
+mod versioned;
+
+pub use self::parse::parse_model;
+pub use self::parse::parse_model_strict;
+pub use self::parse::ModelParseError;
+pub use self::versioned::negotiate_version;
+pub use self::versioned::serialize_for_version;
+pub use self::versioned::Versioned;
+pub use self::versioned::VersionedModel;
+pub use self::versioned::SCHEMA_VERSION;
@@ -0,0 +1,110 @@
+/// Define an enum of string values that accepts unknown values for forward compatibility.
+///
+/// Newer versions of agents or core may introduce values older versions don't know about.
+/// Enums defined with this macro capture these values in an `Unknown(String)` variant
+/// instead of failing to deserialize the entire payload.
+///
+/// The generated enum serializes to and deserializes from plain strings.
+/// Parsing with `FromStr` or `TryFrom<&str>` is strict instead and rejects unknown values,
+/// which is useful to validate user input such as query parameters.
+macro_rules! forward_compatible_enum {
+    (
+        $(#[$meta:meta])*
+        pub enum $name:ident {
+            $(
+                $(#[$variant_meta:meta])*
+                $variant:ident => $value:literal,
+            )+
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Clone, Eq, PartialEq, Hash, Debug)]
+        pub enum $name {
+            $(
+                $(#[$variant_meta])*
+                $variant,
+            )+
+
+            /// A value not known to this version of the models.
+            Unknown(String),
+        }
+
+        impl $name {
+            /// String representation of the value.
+            pub fn as_str(&self) -> &str {
+                match self {
+                    $($name::$variant => $value,)+
+                    $name::Unknown(value) => value,
+                }
+            }
+        }
+
+        impl $name {
+            /// Lenient conversion used when deserializing, keeping unknown values.
+            fn from_wire(value: &str) -> $name {
+                match value {
+                    $($value => $name::$variant,)+
+                    value => $name::Unknown(value.to_string()),
+                }
+            }
+        }
+
+        impl ::std::convert::TryFrom<&str> for $name {
+            type Error = $crate::actions::UnknownEnumValue;
+
+            fn try_from(value: &str) -> Result<$name, Self::Error> {
+                value.parse()
+            }
+        }
+
+        impl ::std::str::FromStr for $name {
+            type Err = $crate::actions::UnknownEnumValue;
+
+            fn from_str(value: &str) -> Result<$name, Self::Err> {
+                match value {
+                    $($value => Ok($name::$variant),)+
+                    value => Err($crate::actions::UnknownEnumValue::new(stringify!($name), value)),
+                }
+            }
+        }
+
+        impl ::serde::Serialize for $name {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: ::serde::Serializer,
+            {
+                serializer.serialize_str(self.as_str())
+            }
+        }
+
+        impl<'de> ::serde::Deserialize<'de> for $name {
+            fn deserialize<D>(deserializer: D) -> Result<$name, D::Error>
+            where
+                D: ::serde::Deserializer<'de>,
+            {
+                let value = <String as ::serde::Deserialize>::deserialize(deserializer)?;
+                Ok($name::from_wire(value.as_str()))
+            }
+        }
+
+        #[cfg(feature = "schema")]
+        impl ::schemars::JsonSchema for $name {
+            fn schema_name() -> String {
+                stringify!($name).to_string()
+            }
+
+            fn json_schema(_: &mut ::schemars::gen::SchemaGenerator) -> ::schemars::schema::Schema {
+                // Unknown values are allowed so known values are listed as examples only.
+                let schema = ::schemars::schema::SchemaObject {
+                    instance_type: Some(::schemars::schema::InstanceType::String.into()),
+                    metadata: Some(Box::new(::schemars::schema::Metadata {
+                        examples: vec![$(::serde_json::Value::from($value),)+],
+                        ..Default::default()
+                    })),
+                    ..Default::default()
+                };
+                schema.into()
+            }
+        }
+    };
+}
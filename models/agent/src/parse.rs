@@ -0,0 +1,189 @@
+use serde::de::DeserializeOwned;
+use serde::de::Error as _;
+use thiserror::Error;
+
+/// Error returned by `parse_model` when a payload does not match the expected model.
+///
+/// The error names the model and the path to the offending field (`.` for the payload
+/// itself) while the underlying serde error, available as the source, reports the
+/// problem and its line and column.
+///
+/// Errors from `parse_model_strict` also list all unknown fields in the payload.
+#[derive(Error, Debug)]
+#[error("invalid {model} payload at '{path}'")]
+pub struct ModelParseError {
+    model: &'static str,
+    path: String,
+    #[source]
+    source: serde_json::Error,
+    unknown_fields: Vec<String>,
+}
+
+impl ModelParseError {
+    /// Column in the payload where the error was detected.
+    pub fn column(&self) -> usize {
+        self.source.column()
+    }
+
+    /// Line in the payload where the error was detected.
+    pub fn line(&self) -> usize {
+        self.source.line()
+    }
+
+    /// Name of the model the payload was parsed as.
+    pub fn model(&self) -> &'static str {
+        self.model
+    }
+
+    /// Path to the field that could not be parsed (`.` for the payload itself).
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Paths to fields in the payload that are not part of the model.
+    ///
+    /// Only `parse_model_strict` reports unknown fields, `parse_model` ignores them.
+    pub fn unknown_fields(&self) -> &[String] {
+        &self.unknown_fields
+    }
+}
+
+/// Parse a JSON payload into a model, reporting the model and field on errors.
+pub fn parse_model<T: DeserializeOwned>(payload: &[u8]) -> Result<T, ModelParseError> {
+    let mut deserializer = serde_json::Deserializer::from_slice(payload);
+    let model = serde_path_to_error::deserialize(&mut deserializer)
+        .map_err(|failure| parse_error::<T>(failure.path().to_string(), failure.into_inner()))?;
+    deserializer
+        .end()
+        .map_err(|source| parse_error::<T>(".".to_string(), source))?;
+    Ok(model)
+}
+
+/// Parse a JSON payload into a model, rejecting fields the model does not know about.
+///
+/// Models ignore unknown fields so agents and core can evolve independently but this
+/// also hides fields that are renamed or dropped by mistake. Use this function in tests
+/// and CI checks to catch schema drift while production code uses `parse_model`.
+///
+/// The returned error points at the first unknown field and lists all of them.
+pub fn parse_model_strict<T: DeserializeOwned>(payload: &[u8]) -> Result<T, ModelParseError> {
+    let mut unknown_fields = Vec::new();
+    let mut deserializer = serde_json::Deserializer::from_slice(payload);
+    // Paths are reported in the same format as `serde_path_to_error` (without `?` for options).
+    let mut track_unknown = |path: serde_ignored::Path| {
+        let path = path.to_string();
+        let path: Vec<&str> = path.split('.').filter(|part| *part != "?").collect();
+        unknown_fields.push(path.join("."));
+    };
+    let tracker = serde_ignored::Deserializer::new(&mut deserializer, &mut track_unknown);
+    let model = serde_path_to_error::deserialize(tracker)
+        .map_err(|failure| parse_error::<T>(failure.path().to_string(), failure.into_inner()))?;
+    deserializer
+        .end()
+        .map_err(|source| parse_error::<T>(".".to_string(), source))?;
+    if unknown_fields.is_empty() {
+        return Ok(model);
+    }
+    let source =
+        serde_json::Error::custom(format!("unknown fields: {}", unknown_fields.join(", ")));
+    let mut error = parse_error::<T>(unknown_fields[0].clone(), source);
+    error.unknown_fields = unknown_fields;
+    Err(error)
+}
+
+/// Build a `ModelParseError` for model `T`.
+fn parse_error<T>(path: String, source: serde_json::Error) -> ModelParseError {
+    ModelParseError {
+        model: model_name::<T>(),
+        path,
+        source,
+        unknown_fields: Vec::new(),
+    }
+}
+
+/// Name of the model type without module paths or generic parameters.
+fn model_name<T>() -> &'static str {
+    let name = std::any::type_name::<T>();
+    let name = name.split('<').next().unwrap_or(name);
+    name.rsplit("::").next().unwrap_or(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_model;
+    use super::parse_model_strict;
+    use crate::actions::ActionModel;
+    use crate::info::Shard;
+    use crate::info::ShardRole;
+
+    #[test]
+    fn parse_valid_shard() {
+        let payload = br#"{"commit_offset":null,"id":"shard-1","lag":null,"role":"primary"}"#;
+        let shard: Shard = parse_model(payload).unwrap();
+        assert_eq!(shard, Shard::new("shard-1", ShardRole::Primary, None, None));
+    }
+
+    #[test]
+    fn invalid_shard_names_field() {
+        let payload = concat!(
+            "{\n",
+            r#"  "commit_offset": {"unit": "seconds", "value": "soon"},"#,
+            "\n",
+            r#"  "id": "shard-1", "lag": null, "role": "primary""#,
+            "\n}"
+        );
+        let error = parse_model::<Shard>(payload.as_bytes()).unwrap_err();
+        assert_eq!(error.model(), "Shard");
+        assert_eq!(error.path(), "commit_offset");
+        assert_eq!(error.line(), 2);
+        assert_eq!(
+            error.to_string(),
+            "invalid Shard payload at 'commit_offset'"
+        );
+    }
+
+    #[test]
+    fn invalid_action_names_nested_field() {
+        let payload = concat!(
+            r#"{"args":{},"created_ts":"2019-12-01T00:00:00Z","finished_ts":null,"#,
+            r#""headers":{"x-tenant":42},"id":"a8d1a3b6-0b5f-4e3f-8a57-3f2d2b3f3f3f","#,
+            r#""kind":"test","requester":"AGENT_API","scheduled_ts":"2019-12-01T00:00:00Z","#,
+            r#""state":"NEW","state_payload":null}"#
+        );
+        let error = parse_model::<ActionModel>(payload.as_bytes()).unwrap_err();
+        assert_eq!(error.model(), "ActionModel");
+        assert_eq!(error.path(), "headers.x-tenant");
+    }
+
+    #[test]
+    fn strict_parsing_rejects_unknown_fields() {
+        let payload = concat!(
+            r#"{"commit_offset":{"unit":"seconds","value":10,"precision":"ms"},"#,
+            r#""id":"shard-1","lag":null,"role":"primary","leader":true}"#
+        );
+        let shard: Shard = parse_model(payload.as_bytes()).unwrap();
+        assert_eq!(shard.id, "shard-1");
+
+        let error = parse_model_strict::<Shard>(payload.as_bytes()).unwrap_err();
+        assert_eq!(error.model(), "Shard");
+        assert_eq!(error.path(), "commit_offset.precision");
+        assert_eq!(
+            error.unknown_fields(),
+            ["commit_offset.precision", "leader"]
+        );
+    }
+
+    #[test]
+    fn strict_parsing_accepts_known_fields() {
+        let payload = br#"{"commit_offset":null,"id":"shard-1","lag":null,"role":"primary"}"#;
+        let shard: Shard = parse_model_strict(payload).unwrap();
+        assert_eq!(shard, Shard::new("shard-1", ShardRole::Primary, None, None));
+    }
+
+    #[test]
+    fn trailing_data_is_rejected() {
+        let payload = br#"{"commit_offset":null,"id":"shard-1","lag":null,"role":"primary"} {}"#;
+        let error = parse_model::<Shard>(payload).unwrap_err();
+        assert_eq!(error.path(), ".");
+    }
+}
@@ -0,0 +1,69 @@
+use std::collections::BTreeMap;
+
+use schemars::schema::RootSchema;
+use schemars::schema_for;
+
+use crate::actions::api::ActionInfoResponse;
+use crate::actions::api::ActionScheduleRequest;
+use crate::actions::ActionHistoryItem;
+use crate::actions::ActionListItem;
+use crate::actions::ActionModel;
+use crate::actions::ActionRequester;
+use crate::actions::ActionState;
+use crate::info::AgentInfo;
+use crate::info::AgentVersion;
+use crate::info::CommitOffset;
+use crate::info::CommitUnit;
+use crate::info::DatastoreInfo;
+use crate::info::Shard;
+use crate::info::ShardRole;
+use crate::info::Shards;
+use crate::info::Snapshot;
+
+/// Generate the JSON schemas of all agent models, keyed by model name.
+///
+/// Schemas describe the models as they appear on the wire so they can be
+/// used by clients that are not written in rust.
+pub fn schemas() -> BTreeMap<&'static str, RootSchema> {
+    let mut schemas = BTreeMap::new();
+    schemas.insert("ActionHistoryItem", schema_for!(ActionHistoryItem));
+    schemas.insert("ActionInfoResponse", schema_for!(ActionInfoResponse));
+    schemas.insert("ActionListItem", schema_for!(ActionListItem));
+    schemas.insert("ActionModel", schema_for!(ActionModel));
+    schemas.insert("ActionRequester", schema_for!(ActionRequester));
+    schemas.insert("ActionScheduleRequest", schema_for!(ActionScheduleRequest));
+    schemas.insert("ActionState", schema_for!(ActionState));
+    schemas.insert("AgentInfo", schema_for!(AgentInfo));
+    schemas.insert("AgentVersion", schema_for!(AgentVersion));
+    schemas.insert("CommitOffset", schema_for!(CommitOffset));
+    schemas.insert("CommitUnit", schema_for!(CommitUnit));
+    schemas.insert("DatastoreInfo", schema_for!(DatastoreInfo));
+    schemas.insert(
+        "DatastoreInfoSnapshot",
+        schema_for!(Snapshot<DatastoreInfo>),
+    );
+    schemas.insert("Shard", schema_for!(Shard));
+    schemas.insert("ShardRole", schema_for!(ShardRole));
+    schemas.insert("Shards", schema_for!(Shards));
+    schemas.insert("ShardsSnapshot", schema_for!(Snapshot<Shards>));
+    schemas
+}
+
+#[cfg(test)]
+mod tests {
+    use super::schemas;
+
+    #[test]
+    fn action_state_values() {
+        let schemas = schemas();
+        let schema = serde_json::to_value(&schemas["ActionState"]).unwrap();
+        assert_eq!(schema["type"], "string");
+        let values: Vec<&str> = schema["examples"]
+            .as_array()
+            .expect("ActionState variants to be listed")
+            .iter()
+            .map(|variant| variant.as_str().unwrap())
+            .collect();
+        assert_eq!(values, vec!["DONE", "FAILED", "NEW", "RUNNING"]);
+    }
+}
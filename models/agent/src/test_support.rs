@@ -0,0 +1,28 @@
+use std::fmt::Debug;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Assert the value round-trips through the binary formats used for agent/core traffic.
+///
+/// MessagePack is checked with both the named (map) and the compact (array) struct encodings.
+pub fn assert_binary_round_trip<T>(value: &T)
+where
+    T: Debug + PartialEq + Serialize + DeserializeOwned,
+{
+    let mut cbor = Vec::new();
+    ciborium::ser::into_writer(value, &mut cbor).expect("CBOR encoding failed");
+    let decoded: T = ciborium::de::from_reader(cbor.as_slice()).expect("CBOR decoding failed");
+    assert_eq!(&decoded, value, "CBOR round trip changed the value");
+
+    let msgpack = rmp_serde::to_vec_named(value).expect("MessagePack encoding failed");
+    let decoded: T = rmp_serde::from_slice(&msgpack).expect("MessagePack decoding failed");
+    assert_eq!(&decoded, value, "MessagePack round trip changed the value");
+
+    let msgpack = rmp_serde::to_vec(value).expect("compact MessagePack encoding failed");
+    let decoded: T = rmp_serde::from_slice(&msgpack).expect("compact MessagePack decoding failed");
+    assert_eq!(
+        &decoded, value,
+        "compact MessagePack round trip changed the value"
+    );
+}
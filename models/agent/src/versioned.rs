@@ -0,0 +1,185 @@
+use serde_derive::Deserialize;
+use serde_derive::Serialize;
+use serde_json::Value as Json;
+
+use crate::actions::ActionListItem;
+use crate::actions::ActionModel;
+use crate::info::AgentInfo;
+use crate::info::DatastoreInfo;
+use crate::info::Shards;
+
+/// Version of the models schema implemented by this crate.
+///
+/// The version is incremented every time a field is added to a top-level model.
+///
+///   * `1`: initial schema.
+///   * `2`: `ActionListItem` includes `created_ts` and `finished_ts`.
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// Envelope tagging a model with the schema version it was serialized for.
+///
+/// Use `serialize_for_version` to create envelopes peers running older
+/// versions of the models can understand.
+/// Peers that predate envelopes can not decode them: the `model` field holds
+/// a payload compatible with their model definitions but must be unwrapped first.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Versioned<T> {
+    pub model: T,
+    pub schema_version: u32,
+}
+
+impl<T> Versioned<T> {
+    /// Tag a model with the current `SCHEMA_VERSION`.
+    pub fn new(model: T) -> Versioned<T> {
+        Versioned {
+            model,
+            schema_version: SCHEMA_VERSION,
+        }
+    }
+}
+
+/// Top-level models exchanged between core and agents that can be downgraded.
+pub trait VersionedModel: serde::Serialize {
+    /// Schema version each top-level field was introduced with, for fields added after `1`.
+    const ADDED_FIELDS: &'static [(u32, &'static str)];
+}
+
+impl VersionedModel for ActionListItem {
+    const ADDED_FIELDS: &'static [(u32, &'static str)] = &[(2, "created_ts"), (2, "finished_ts")];
+}
+
+impl VersionedModel for ActionModel {
+    const ADDED_FIELDS: &'static [(u32, &'static str)] = &[];
+}
+
+impl VersionedModel for AgentInfo {
+    const ADDED_FIELDS: &'static [(u32, &'static str)] = &[];
+}
+
+impl VersionedModel for DatastoreInfo {
+    const ADDED_FIELDS: &'static [(u32, &'static str)] = &[];
+}
+
+impl VersionedModel for Shards {
+    const ADDED_FIELDS: &'static [(u32, &'static str)] = &[];
+}
+
+/// Schema version to exchange models in with a peer supporting up to `peer` version.
+pub fn negotiate_version(peer: u32) -> u32 {
+    peer.min(SCHEMA_VERSION)
+}
+
+/// Serialize a model in a `Versioned` envelope a peer supporting `version` can understand.
+///
+/// Fields added after the negotiated version are dropped from the serialized model.
+pub fn serialize_for_version<T>(model: &T, version: u32) -> serde_json::Result<Json>
+where
+    T: VersionedModel,
+{
+    let version = negotiate_version(version);
+    let mut model = serde_json::to_value(model)?;
+    if let Json::Object(fields) = &mut model {
+        for (added, field) in T::ADDED_FIELDS {
+            if *added > version {
+                fields.remove(*field);
+            }
+        }
+    }
+    serde_json::to_value(Versioned {
+        model,
+        schema_version: version,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_derive::Deserialize;
+    use serde_json::json;
+    use uuid::Uuid;
+
+    use super::negotiate_version;
+    use super::serialize_for_version;
+    use super::Versioned;
+    use super::SCHEMA_VERSION;
+    use crate::actions::ActionListItem;
+    use crate::actions::ActionState;
+    use crate::info::AgentInfo;
+    use crate::info::AgentVersion;
+
+    fn item() -> ActionListItem {
+        serde_json::from_value(json!({
+            "created_ts": "2019-07-04T10:11:12Z",
+            "finished_ts": null,
+            "id": "8ded0ea6-4a3d-4a4d-8cd0-d2e6e3f6c3c4",
+            "kind": "test",
+            "state": "RUNNING",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn downgrade_drops_newer_fields() {
+        let payload = serialize_for_version(&item(), 1).unwrap();
+        let expected = json!({
+            "model": {
+                "id": "8ded0ea6-4a3d-4a4d-8cd0-d2e6e3f6c3c4",
+                "kind": "test",
+                "state": "RUNNING",
+            },
+            "schema_version": 1,
+        });
+        assert_eq!(payload, expected);
+
+        // Peers on the current version decode the downgraded model.
+        let decoded: Versioned<ActionListItem> = serde_json::from_value(payload).unwrap();
+        assert_eq!(decoded.schema_version, 1);
+        assert_eq!(decoded.model.state, ActionState::Running);
+    }
+
+    /// `ActionListItem` as defined before schema versions were introduced.
+    ///
+    /// Unknown fields are rejected to check newer fields are dropped from the payload.
+    #[derive(Debug, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct ActionListItemV1 {
+        id: Uuid,
+        kind: String,
+        state: ActionState,
+    }
+
+    #[test]
+    fn downgraded_model_decodes_with_v1_definition() {
+        let payload = serialize_for_version(&item(), 1).unwrap();
+        let model: ActionListItemV1 = serde_json::from_value(payload["model"].clone()).unwrap();
+        assert_eq!(model.id.to_string(), "8ded0ea6-4a3d-4a4d-8cd0-d2e6e3f6c3c4");
+        assert_eq!(model.kind, "test");
+        assert_eq!(model.state, ActionState::Running);
+
+        // The envelope itself must be unwrapped by the receiving peer.
+        let envelope = serde_json::from_value::<ActionListItemV1>(payload);
+        assert!(envelope.is_err());
+
+        // Current payloads include fields the v1 definition does not know about.
+        let current = serialize_for_version(&item(), SCHEMA_VERSION).unwrap();
+        let model = serde_json::from_value::<ActionListItemV1>(current["model"].clone());
+        assert!(model.is_err());
+    }
+
+    #[test]
+    fn negotiate_newer_peer() {
+        assert_eq!(negotiate_version(1), 1);
+        assert_eq!(negotiate_version(SCHEMA_VERSION + 1), SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn round_trip_same_version() {
+        let payload = serialize_for_version(&item(), SCHEMA_VERSION).unwrap();
+        let decoded: Versioned<ActionListItem> = serde_json::from_value(payload).unwrap();
+        assert_eq!(decoded, Versioned::new(item()));
+
+        let agent = AgentInfo::new(AgentVersion::new("abc123", "1.2.3", "tainted"));
+        let payload = serialize_for_version(&agent, SCHEMA_VERSION).unwrap();
+        let decoded: Versioned<AgentInfo> = serde_json::from_value(payload).unwrap();
+        assert_eq!(decoded, Versioned::new(agent));
+    }
+}
@@ -17,3 +17,18 @@ pub enum HealthStatus {
     #[serde(rename = "FAILED")]
     Failed(String),
 }
+
+/// Result of a health check along with information about the checked component.
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Serialize, Deserialize)]
+pub struct HealthCheckResult {
+    /// Failure of critical components fails the process, others only degrade it.
+    pub critical: bool,
+
+    /// Optional type of the checked component (such as `db`, `kafka`, `disk`).
+    #[serde(default, rename = "type", skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+
+    /// Result of the health check.
+    #[serde(flatten)]
+    pub status: HealthStatus,
+}
@@ -0,0 +1,204 @@
+use std::convert::TryFrom;
+use std::pin::Pin;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::task::Context;
+use std::task::Poll;
+
+use actix_web::body::BodySize;
+use actix_web::body::MessageBody;
+use actix_web::web::Bytes;
+
+/// Shared count of bytes streamed through one or more `CountingBody`s.
+#[derive(Clone, Debug, Default)]
+pub struct ByteCounter(Arc<AtomicU64>);
+
+impl ByteCounter {
+    pub fn new() -> ByteCounter {
+        ByteCounter::default()
+    }
+
+    /// Number of bytes counted so far.
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    fn add(&self, bytes: usize) {
+        let bytes = u64::try_from(bytes).unwrap_or(u64::MAX);
+        self.0.fetch_add(bytes, Ordering::Relaxed);
+    }
+}
+
+/// Body wrapper that counts the bytes streamed through it.
+///
+/// Chunks are counted as they are polled so bodies are never buffered.
+/// The count is available from the `ByteCounter` while (and after) the body is streamed,
+/// for example to observe request and response sizes once they are fully sent.
+///
+/// Actix Web bodies (`MessageBody`) are supported out of the box while `http_body::Body`
+/// is supported with the `with_tower` feature.
+pub struct CountingBody<B> {
+    body: Pin<Box<B>>,
+    counter: ByteCounter,
+}
+
+impl<B> CountingBody<B> {
+    /// Wrap the body with a new counter.
+    pub fn new(body: B) -> (CountingBody<B>, ByteCounter) {
+        let counter = ByteCounter::new();
+        let body = CountingBody::with_counter(body, counter.clone());
+        (body, counter)
+    }
+
+    /// Wrap the body and add the streamed bytes to the given counter.
+    pub fn with_counter(body: B, counter: ByteCounter) -> CountingBody<B> {
+        CountingBody {
+            body: Box::pin(body),
+            counter,
+        }
+    }
+}
+
+impl<B: MessageBody> MessageBody for CountingBody<B> {
+    type Error = B::Error;
+
+    fn size(&self) -> BodySize {
+        self.body.size()
+    }
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        let chunk = self.body.as_mut().poll_next(cx);
+        if let Poll::Ready(Some(Ok(bytes))) = &chunk {
+            self.counter.add(bytes.len());
+        }
+        chunk
+    }
+}
+
+#[cfg(feature = "with_tower")]
+impl<B: http_body::Body> http_body::Body for CountingBody<B> {
+    type Data = B::Data;
+    type Error = B::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<http_body::Frame<Self::Data>, Self::Error>>> {
+        let frame = self.body.as_mut().poll_frame(cx);
+        if let Poll::Ready(Some(Ok(frame))) = &frame {
+            if let Some(data) = frame.data_ref() {
+                self.counter.add(bytes::Buf::remaining(data));
+            }
+        }
+        frame
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.body.is_end_stream()
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.body.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::convert::Infallible;
+    use std::pin::Pin;
+    use std::task::Context;
+    use std::task::Poll;
+
+    use actix_web::body::to_bytes;
+    use actix_web::body::BodySize;
+    use actix_web::body::MessageBody;
+    use actix_web::web::Bytes;
+
+    use super::ByteCounter;
+    use super::CountingBody;
+
+    /// Body streaming a known sequence of chunks.
+    struct Chunks(VecDeque<Bytes>);
+
+    impl Chunks {
+        fn new(sizes: &[usize]) -> Chunks {
+            let chunks = sizes.iter().map(|size| Bytes::from(vec![b'x'; *size]));
+            Chunks(chunks.collect())
+        }
+    }
+
+    impl MessageBody for Chunks {
+        type Error = Infallible;
+
+        fn size(&self) -> BodySize {
+            BodySize::Stream
+        }
+
+        fn poll_next(
+            mut self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+        ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+            Poll::Ready(self.0.pop_front().map(Ok))
+        }
+    }
+
+    #[cfg(feature = "with_tower")]
+    impl http_body::Body for Chunks {
+        type Data = Bytes;
+        type Error = Infallible;
+
+        fn poll_frame(
+            mut self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+        ) -> Poll<Option<Result<http_body::Frame<Bytes>, Infallible>>> {
+            Poll::Ready(
+                self.0
+                    .pop_front()
+                    .map(|chunk| Ok(http_body::Frame::data(chunk))),
+            )
+        }
+    }
+
+    #[actix_rt::test]
+    async fn count_sized_body() {
+        let (body, counter) = CountingBody::new("a known body");
+        assert_eq!(body.size(), BodySize::Sized(12));
+        let bytes = to_bytes(body).await.unwrap();
+        assert_eq!(bytes.len(), 12);
+        assert_eq!(counter.get(), 12);
+    }
+
+    #[actix_rt::test]
+    async fn count_streamed_chunks() {
+        let counter = ByteCounter::new();
+        let body = CountingBody::with_counter(Chunks::new(&[1024, 512, 0, 7]), counter.clone());
+        to_bytes(body).await.unwrap();
+        assert_eq!(counter.get(), 1543);
+
+        // Counters can be shared across bodies.
+        let body = CountingBody::with_counter(Chunks::new(&[10]), counter.clone());
+        to_bytes(body).await.unwrap();
+        assert_eq!(counter.get(), 1553);
+    }
+
+    #[cfg(feature = "with_tower")]
+    #[actix_rt::test]
+    async fn count_http_body_frames() {
+        use http_body::Body;
+
+        let (mut body, counter) = CountingBody::new(Chunks::new(&[100, 20, 3]));
+        let mut frames = 0;
+        while let Some(frame) = std::future::poll_fn(|cx| Pin::new(&mut body).poll_frame(cx)).await
+        {
+            frame.unwrap();
+            frames += 1;
+        }
+        assert_eq!(frames, 3);
+        assert_eq!(counter.get(), 123);
+    }
+}
@@ -0,0 +1,422 @@
+use std::future::ready;
+use std::future::Ready;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+use actix_web::dev::forward_ready;
+use actix_web::dev::Service;
+use actix_web::dev::ServiceRequest;
+use actix_web::dev::ServiceResponse;
+use actix_web::dev::Transform;
+use actix_web::Error;
+use actix_web::HttpMessage;
+use actix_web::HttpRequest;
+use sentry::Hub;
+use sentry::Scope;
+use slog::error;
+use slog::Logger;
+
+use crate::tracing::SpanIds;
+use crate::RequestLogger;
+use crate::TrustedProxies;
+
+/// Request extension marking requests processed with a `sentry-actix` request hub.
+#[derive(Clone)]
+struct SentryRequest;
+
+/// Configure the sentry scope of the request, if `SentryMiddleware` processed it.
+///
+/// Requests processed by `SentryMiddleware` run with the `Hub` created for them by
+/// `sentry-actix`, so anything set on the scope is included in the events captured
+/// for the request: by `SentryMiddleware` and by `sentry::capture_*` calls in handlers.
+/// This lets handlers enrich error reports with business context.
+///
+/// Must be called while the request is handled (not from spawned tasks or threads).
+pub fn with_request_sentry_scope<F>(request: &HttpRequest, configure: F)
+where
+    F: FnOnce(&mut Scope),
+{
+    if request.extensions().get::<SentryRequest>().is_some() {
+        Hub::current().configure_scope(configure);
+    }
+}
+
+/// Actix Web middleware to report responses with error status codes to sentry.
+///
+/// Every matching response is logged but, when sampling is enabled, only a limited
+/// number of events is sent to sentry in each time window.
+/// The first matching response in each window is always captured.
+///
+/// Each request is given its own sentry `Hub` by the `sentry-actix` middleware,
+/// which handlers can configure with `with_request_sentry_scope`.
+/// Request hubs are created from the hub current when the service is built.
+///
+/// When the request is traced by `TracingMiddleware` (wrapping this middleware)
+/// events are tagged with the `trace_id` and `span_id` of the request span
+/// and failures are logged with the request's trace enriched logger.
+pub struct SentryMiddleware {
+    level: u16,
+    logger: Logger,
+    proxies: TrustedProxies,
+    sampler: Arc<Sampler>,
+}
+
+impl SentryMiddleware {
+    /// Capture an event for every response with a status code of `level` or above.
+    pub fn new(logger: Logger, level: u16) -> SentryMiddleware {
+        SentryMiddleware {
+            level,
+            logger,
+            proxies: TrustedProxies::default(),
+            sampler: Arc::new(Sampler::new(None, Duration::from_secs(0))),
+        }
+    }
+
+    /// Trust the given proxies to report the original request URL and client address.
+    pub fn trusted_proxies(mut self, proxies: TrustedProxies) -> SentryMiddleware {
+        self.proxies = proxies;
+        self
+    }
+
+    /// Capture at most `limit` events in each `window`.
+    ///
+    /// A `limit` of `0` is treated as `1` so at least one event is always captured.
+    pub fn sampling(mut self, limit: u32, window: Duration) -> SentryMiddleware {
+        self.sampler = Arc::new(Sampler::new(Some(limit.max(1)), window));
+        self
+    }
+
+    /// Wrap a service without going through `Transform::new_transform`.
+    pub(crate) fn wrap_service<S, B>(&self, service: S) -> SentryService<S>
+    where
+        S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+        S::Future: 'static,
+        B: 'static,
+    {
+        let service = MiddlewareService {
+            level: self.level,
+            logger: self.logger.clone(),
+            proxies: self.proxies.clone(),
+            sampler: Arc::clone(&self.sampler),
+            service,
+        };
+        // Events are captured (and sampled) by this middleware, not by sentry-actix.
+        sentry_actix::Sentry::builder()
+            .capture_server_errors(false)
+            .with_hub(Hub::current())
+            .finish()
+            .new_transform(service)
+            .into_inner()
+            .expect("the sentry-actix middleware never fails to build")
+    }
+}
+
+/// Type of the service generated by `SentryMiddleware`.
+pub(crate) type SentryService<S> = sentry_actix::SentryMiddleware<MiddlewareService<S>>;
+
+// `S` - type of the next service
+// `B` - type of response's body
+impl<S, B> Transform<S, ServiceRequest> for SentryMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = SentryService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(self.wrap_service(service)))
+    }
+}
+
+/// Inner middleware to process requests on behalf of `SentryMiddleware`.
+///
+/// Runs within the request hub bound by the `sentry-actix` middleware.
+pub struct MiddlewareService<S> {
+    level: u16,
+    logger: Logger,
+    proxies: TrustedProxies,
+    sampler: Arc<Sampler>,
+    service: S,
+}
+
+impl<S, B> Service<ServiceRequest> for MiddlewareService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = crate::LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let level = self.level;
+        // Log failures with the trace IDs when the request is traced.
+        let logger = req
+            .extensions()
+            .get::<RequestLogger>()
+            .map(|logger| logger.logger().clone())
+            .unwrap_or_else(|| self.logger.clone());
+        let sampler = Arc::clone(&self.sampler);
+        let method = req.method().to_string();
+        let path = req.path().to_string();
+        let origin = self.proxies.origin(req.request());
+        let url = origin.url(req.uri());
+        let client = origin.client.map(|client| client.to_string());
+        let span_ids = req.extensions().get::<SpanIds>().cloned();
+        req.extensions_mut().insert(SentryRequest);
+        let response = self.service.call(req);
+        Box::pin(async move {
+            // Errors are turned into responses by Actix Web so report them as well.
+            let response = response.await;
+            let status = match response.as_ref() {
+                Ok(response) => response.response().status(),
+                Err(error) => error.as_response_error().status_code(),
+            };
+            if status.as_u16() < level {
+                return response;
+            }
+
+            let captured = sampler.sample();
+            if captured {
+                let hub = Hub::current();
+                let message = format!("{} {} responded with {}", method, path, status);
+                hub.with_scope(
+                    |scope| {
+                        scope.set_tag("http.method", &method);
+                        scope.set_tag("http.path", &path);
+                        scope.set_tag("http.status", status.as_u16());
+                        scope.set_tag("http.url", &url);
+                        if let Some(client) = client.as_ref() {
+                            scope.set_tag("client.ip", client);
+                        }
+                        if let Some(ids) = span_ids.as_ref() {
+                            scope.set_tag("trace_id", &ids.trace_id);
+                            scope.set_tag("span_id", &ids.span_id);
+                        }
+                    },
+                    || hub.capture_message(&message, sentry::Level::Error),
+                );
+            }
+            error!(
+                logger,
+                "Request failed with error status";
+                "client" => client,
+                "method" => method,
+                "path" => path,
+                "scheme" => origin.scheme,
+                "sentry_captured" => captured,
+                "status" => %status,
+            );
+            response
+        })
+    }
+}
+
+/// Decide which matching responses are captured by sentry.
+struct Sampler {
+    limit: Option<u32>,
+    state: Mutex<SamplerWindow>,
+    window: Duration,
+}
+
+/// Events captured in the current sampling window.
+struct SamplerWindow {
+    captured: u32,
+    start: Instant,
+}
+
+impl Sampler {
+    fn new(limit: Option<u32>, window: Duration) -> Sampler {
+        let state = SamplerWindow {
+            captured: 0,
+            start: Instant::now(),
+        };
+        Sampler {
+            limit,
+            state: Mutex::new(state),
+            window,
+        }
+    }
+
+    /// Check if the current event should be captured.
+    fn sample(&self) -> bool {
+        let limit = match self.limit {
+            None => return true,
+            Some(limit) => limit,
+        };
+        let mut state = self
+            .state
+            .lock()
+            .expect("SentryMiddleware sampler lock poisoned");
+        let now = Instant::now();
+        if now.duration_since(state.start) >= self.window {
+            state.captured = 0;
+            state.start = now;
+        }
+        if state.captured >= limit {
+            return false;
+        }
+        state.captured += 1;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use actix_web::dev::fn_service;
+    use actix_web::dev::Service;
+    use actix_web::dev::ServiceRequest;
+    use actix_web::dev::ServiceResponse;
+    use actix_web::dev::Transform;
+    use actix_web::error::ErrorServiceUnavailable;
+    use actix_web::test::call_service;
+    use actix_web::test::init_service;
+    use actix_web::test::TestRequest;
+    use actix_web::web;
+    use actix_web::App;
+    use actix_web::HttpRequest;
+    use actix_web::HttpResponse;
+    use replicante_logging::buffer_logger;
+    use sentry::test::with_captured_events;
+    use slog::o;
+    use slog::Logger;
+
+    use super::with_request_sentry_scope;
+    use super::SentryMiddleware;
+
+    fn send_requests(middleware: SentryMiddleware, count: usize) {
+        actix_rt::System::new().block_on(async move {
+            let app = App::new()
+                .wrap(middleware)
+                .route("/ok", web::get().to(HttpResponse::Ok))
+                .route("/fail", web::get().to(HttpResponse::InternalServerError));
+            let app = init_service(app).await;
+            let req = TestRequest::get().uri("/ok").to_request();
+            call_service(&app, req).await;
+            for _ in 0..count {
+                let req = TestRequest::get().uri("/fail").to_request();
+                call_service(&app, req).await;
+            }
+        });
+    }
+
+    #[test]
+    fn capture_all_errors() {
+        let (logger, sink) = buffer_logger();
+        let events = with_captured_events(|| {
+            send_requests(SentryMiddleware::new(logger, 500), 3);
+        });
+        assert_eq!(events.len(), 3);
+        assert_eq!(sink.records().len(), 3);
+    }
+
+    async fn fail_with_context(request: HttpRequest) -> HttpResponse {
+        let cluster = request.match_info().get("cluster").unwrap_or_default();
+        with_request_sentry_scope(&request, |scope| scope.set_tag("cluster.id", cluster));
+        HttpResponse::InternalServerError().finish()
+    }
+
+    #[test]
+    fn handler_tags_captured_events() {
+        let logger = Logger::root(slog::Discard, o!());
+        let events = with_captured_events(|| {
+            actix_rt::System::new().block_on(async move {
+                let app = App::new()
+                    .wrap(SentryMiddleware::new(logger, 500))
+                    .route("/fail", web::get().to(HttpResponse::InternalServerError))
+                    .route("/{cluster}/fail", web::get().to(fail_with_context));
+                let app = init_service(app).await;
+                let req = TestRequest::get().uri("/prod/fail").to_request();
+                call_service(&app, req).await;
+                let req = TestRequest::get().uri("/fail").to_request();
+                call_service(&app, req).await;
+            });
+        });
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].tags.get("cluster.id").unwrap(), "prod");
+        assert_eq!(events[0].tags.get("http.path").unwrap(), "/prod/fail");
+        // Scopes are not shared across requests.
+        assert!(!events[1].tags.contains_key("cluster.id"));
+    }
+
+    async fn capture_in_handler(request: HttpRequest) -> HttpResponse {
+        with_request_sentry_scope(&request, |scope| scope.set_tag("cluster.id", "prod"));
+        sentry::capture_message("handler event", sentry::Level::Warning);
+        HttpResponse::Ok().finish()
+    }
+
+    #[test]
+    fn handler_events_use_request_scope() {
+        let logger = Logger::root(slog::Discard, o!());
+        let events = with_captured_events(|| {
+            actix_rt::System::new().block_on(async move {
+                let app = App::new()
+                    .wrap(SentryMiddleware::new(logger, 500))
+                    .route("/", web::get().to(capture_in_handler));
+                let app = init_service(app).await;
+                let req = TestRequest::get().uri("/").to_request();
+                call_service(&app, req).await;
+            });
+            sentry::capture_message("outside request", sentry::Level::Warning);
+        });
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].message.as_deref(), Some("handler event"));
+        assert_eq!(events[0].tags.get("cluster.id").unwrap(), "prod");
+        assert!(!events[1].tags.contains_key("cluster.id"));
+    }
+
+    #[test]
+    fn capture_service_errors() {
+        let (logger, sink) = buffer_logger();
+        let events = with_captured_events(|| {
+            actix_rt::System::new().block_on(async move {
+                let service = fn_service(|_: ServiceRequest| async {
+                    Err::<ServiceResponse, _>(ErrorServiceUnavailable("backend down"))
+                });
+                let service = SentryMiddleware::new(logger, 500)
+                    .new_transform(service)
+                    .await
+                    .unwrap();
+                let req = TestRequest::get().uri("/down").to_srv_request();
+                let error = service.call(req).await.expect_err("error was not returned");
+                assert_eq!(error.as_response_error().status_code().as_u16(), 503);
+            });
+        });
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].tags.get("http.status").unwrap(), "503");
+        assert_eq!(sink.records().len(), 1);
+    }
+
+    #[test]
+    fn sampling_drops_events_but_logs() {
+        let (logger, sink) = buffer_logger();
+        let middleware = SentryMiddleware::new(logger, 500).sampling(2, Duration::from_secs(60));
+        let events = with_captured_events(|| {
+            send_requests(middleware, 5);
+        });
+        assert_eq!(events.len(), 2);
+        assert_eq!(sink.records().len(), 5);
+    }
+
+    #[test]
+    fn sampling_captures_first_in_each_window() {
+        let logger = Logger::root(slog::Discard, o!());
+        let middleware = SentryMiddleware::new(logger, 500).sampling(0, Duration::from_secs(0));
+        let events = with_captured_events(|| {
+            send_requests(middleware, 3);
+        });
+        assert_eq!(events.len(), 3);
+    }
+}
@@ -1,9 +1,25 @@
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::sync::Arc;
 
 use actix_web::dev::HttpServiceFactory;
+use actix_web::guard;
+use actix_web::http::Method;
+use actix_web::web;
 use actix_web::web::ServiceConfig;
+use actix_web::FromRequest;
+use actix_web::Handler;
+use actix_web::Responder;
 use actix_web::Scope;
+use prometheus::Registry;
+
+use crate::APIFlags;
+use crate::HealthChecks;
+use crate::HealthHandler;
+use crate::IntrospectionRoot;
+use crate::MetricsExporter;
+use crate::RootDescriptor;
+use crate::RootsIndex;
 
 /// Type alias for AppConfig functions to improve code readability.
 type AppConfigFn<T> = Arc<dyn Fn(&mut AppConfigContext<T>) + Send + Sync>;
@@ -41,6 +57,53 @@ impl<T> AppConfig<T> {
     {
         self.configs.push(Arc::new(config));
     }
+
+    /// Register a `RootsIndex` at `/` to list enabled roots and their prefixes.
+    pub fn with_index(&mut self, index: RootsIndex) {
+        self.register(move |conf| {
+            let index = web::resource("/").route(web::get().to(index.clone()));
+            conf.app.service(index);
+        });
+    }
+
+    /// Register the standard process introspection endpoints under the `IntrospectionRoot`.
+    ///
+    /// The following endpoints are registered if the root is enabled by the flags:
+    ///
+    ///   * `/metrics`: prometheus metrics exported from the given registry.
+    ///   * `/health`: results of the given liveness checks.
+    ///   * `/ready`: results of the given readiness checks.
+    ///
+    /// The metrics exporter registers its response size gauge with the registry
+    /// unless a gauge with the same name is already registered.
+    pub fn with_introspection(
+        &mut self,
+        flags: &APIFlags,
+        registry: Registry,
+        liveness: HealthChecks,
+        readiness: HealthChecks,
+    ) {
+        IntrospectionRoot.and_then(flags, |root| {
+            let exporter = MetricsExporter::with_registry(registry.clone());
+            // Another exporter may have registered the size gauge with the registry already.
+            let _ = exporter.register_metrics(&registry);
+            let health = HealthHandler::liveness(liveness);
+            let ready = HealthHandler::readiness(readiness);
+            let metrics_path = format!("{}/metrics", root.prefix());
+            let health_path = format!("{}/health", root.prefix());
+            let ready_path = format!("{}/ready", root.prefix());
+            self.register(move |conf| {
+                let metrics =
+                    web::resource(metrics_path.as_str()).route(web::get().to(exporter.clone()));
+                let health =
+                    web::resource(health_path.as_str()).route(web::get().to(health.clone()));
+                let ready = web::resource(ready_path.as_str()).route(web::get().to(ready.clone()));
+                conf.app.service(metrics);
+                conf.app.service(health);
+                conf.app.service(ready);
+            });
+        });
+    }
 }
 
 impl<T> Default for AppConfig<T> {
@@ -81,10 +144,11 @@ impl<'context, T> AppConfigContext<'context, T> {
     /// As stated there, routes are matched in order of registstration in their parent Scope/App.
     /// To ensure order is consistent across application restarts and order of callbacks invokation:
     ///
-    ///   * Scopes are sorted by `path`, alphabetically.
+    ///   * Scopes are sorted by `path` segments, alphabetically.
     ///   * Scopes are reversed to support prefixes.
     ///
-    /// Prefixes are scopes with a `path` starting with the `path` used by another scope:
+    /// Paths are compared segment by segment, so `/apix` is not considered prefixed by `/api`.
+    /// Prefixes are scopes with a `path` starting with all the segments of another scope:
     /// For example:
     ///
     ///   * `/api`.
@@ -118,19 +182,58 @@ impl<'context, T> AppConfigContext<'context, T> {
         let scope = scope.service(factory);
         self.scopes.map.insert(key, scope);
     }
+
+    /// Register a named route handling `method` requests to `path` in the scope for `prefix`.
+    ///
+    /// This is a convenience wrapper around `AppConfigContext::scoped_service` used by
+    /// the `routes!` macro to reduce the boilerplate of registering many routes.
+    /// Each route is its own resource guarded by `method`, so several routes
+    /// can share the same `path` with different methods.
+    ///
+    /// # Panic
+    /// Route names must be unique across all routes registered with this method.
+    /// Attempting to register two routes with the same name panics.
+    pub fn scoped_route<F, Args>(
+        &mut self,
+        prefix: &str,
+        method: Method,
+        path: &str,
+        name: &str,
+        handler: F,
+    ) where
+        F: Handler<Args>,
+        Args: FromRequest + 'static,
+        F::Output: Responder + 'static,
+    {
+        if !self.scopes.names.insert(name.to_string()) {
+            panic!("a route named '{}' is already registered", name);
+        }
+        let resource = web::resource(path)
+            .name(name)
+            .guard(guard::Method(method))
+            .to(handler);
+        self.scoped_service(prefix, resource);
+    }
 }
 
 /// Container for `actix_web::Scope`s shared among configuration callbacks.
 #[derive(Default)]
 struct AppConfigScopes {
     map: HashMap<String, Scope>,
+    names: HashSet<String>,
 }
 
 impl AppConfigScopes {
     /// Consume this object and configure all known scopes as services.
     fn configure(self, app: &mut ServiceConfig) {
         let mut scopes: Vec<(String, Scope)> = self.map.into_iter().collect();
-        scopes.sort_by(|a, b| a.0.cmp(&b.0));
+        // Compare paths by segment and fall back to the full path so order is deterministic
+        // even for paths with the same segments (such as `/api` and `/api/`).
+        scopes.sort_by(|a, b| {
+            path_segments(&a.0)
+                .cmp(path_segments(&b.0))
+                .then_with(|| a.0.cmp(&b.0))
+        });
         scopes.reverse();
         for (_, scope) in scopes.into_iter() {
             app.service(scope);
@@ -138,6 +241,11 @@ impl AppConfigScopes {
     }
 }
 
+/// Iterate over the non-empty segments of a path.
+fn path_segments(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|segment| !segment.is_empty())
+}
+
 #[cfg(test)]
 mod tests {
     use actix_web::test::call_service;
@@ -147,8 +255,37 @@ mod tests {
     use actix_web::App;
     use actix_web::HttpResponse;
     use actix_web::Responder;
+    use prometheus::Registry;
+    use replicante_models_api::HealthStatus;
+
+    use actix_web::test::read_body;
+    use actix_web::test::read_body_json;
+    use serde_json::json;
+    use serde_json::Value;
 
     use super::AppConfig;
+    use crate::APIFlags;
+    use crate::HealthChecks;
+    use crate::RootDescriptor;
+    use crate::RootsIndex;
+
+    enum TestRoot {
+        Enabled,
+        Disabled,
+    }
+
+    impl RootDescriptor for TestRoot {
+        fn enabled(&self, _: &APIFlags) -> bool {
+            matches!(self, TestRoot::Enabled)
+        }
+
+        fn prefix(&self) -> &'static str {
+            match self {
+                TestRoot::Enabled => "/api/enabled",
+                TestRoot::Disabled => "/api/disabled",
+            }
+        }
+    }
 
     async fn static_200() -> impl Responder {
         "static 200".to_string()
@@ -177,19 +314,79 @@ mod tests {
         let app = App::new()
             .configure(|app| conf.configure(app, &()))
             .service(resource);
-        let mut app = init_service(app).await;
+        let app = init_service(app).await;
 
         let req = TestRequest::get().uri("/res1").to_request();
-        let res = call_service(&mut app, req).await;
+        let res = call_service(&app, req).await;
         assert_eq!(res.status().as_u16(), 200);
         let req = TestRequest::get().uri("/res2").to_request();
-        let res = call_service(&mut app, req).await;
+        let res = call_service(&app, req).await;
         assert_eq!(res.status().as_u16(), 200);
         let req = TestRequest::get().uri("/res3").to_request();
-        let res = call_service(&mut app, req).await;
+        let res = call_service(&app, req).await;
         assert_eq!(res.status().as_u16(), 200);
     }
 
+    #[actix_rt::test]
+    async fn index() {
+        let flags = APIFlags::new();
+        let index = RootsIndex::default()
+            .root("enabled", &TestRoot::Enabled, &flags)
+            .root("disabled", &TestRoot::Disabled, &flags);
+        let mut conf = AppConfig::default();
+        conf.with_index(index);
+        let app = App::new().configure(|app| conf.configure(app, &()));
+        let app = init_service(app).await;
+
+        let req = TestRequest::get().uri("/").to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status().as_u16(), 200);
+        let body: Value = read_body_json(res).await;
+        assert_eq!(body, json!({"enabled": "/api/enabled"}));
+    }
+
+    #[actix_rt::test]
+    async fn introspection() {
+        let mut checks = HealthChecks::default();
+        checks.register("test", || HealthStatus::Healthy);
+        let mut conf = AppConfig::default();
+        conf.with_introspection(&APIFlags::new(), Registry::new(), checks.clone(), checks);
+        let app = App::new().configure(|app| conf.configure(app, &()));
+        let app = init_service(app).await;
+
+        let req = TestRequest::get().uri("/metrics").to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status().as_u16(), 200);
+        let req = TestRequest::get().uri("/health").to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status().as_u16(), 200);
+        let req = TestRequest::get().uri("/ready").to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status().as_u16(), 200);
+    }
+
+    #[actix_rt::test]
+    async fn introspection_disabled() {
+        let mut flags = APIFlags::new();
+        flags.insert("introspection", false);
+        let mut conf = AppConfig::default();
+        conf.with_introspection(
+            &flags,
+            Registry::new(),
+            HealthChecks::default(),
+            HealthChecks::default(),
+        );
+        let app = App::new().configure(|app| conf.configure(app, &()));
+        let app = init_service(app).await;
+
+        let req = TestRequest::get().uri("/metrics").to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status().as_u16(), 404);
+        let req = TestRequest::get().uri("/health").to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status().as_u16(), 404);
+    }
+
     #[actix_rt::test]
     async fn scopes() {
         let mut conf = AppConfig::default();
@@ -206,16 +403,16 @@ mod tests {
             conf.scoped_service("/scope2", resource);
         });
         let app = App::new().configure(|app| conf.configure(app, &()));
-        let mut app = init_service(app).await;
+        let app = init_service(app).await;
 
         let req = TestRequest::get().uri("/scope1/res1").to_request();
-        let res = call_service(&mut app, req).await;
+        let res = call_service(&app, req).await;
         assert_eq!(res.status().as_u16(), 200);
         let req = TestRequest::get().uri("/scope1/res2").to_request();
-        let res = call_service(&mut app, req).await;
+        let res = call_service(&app, req).await;
         assert_eq!(res.status().as_u16(), 200);
         let req = TestRequest::get().uri("/scope2/res3").to_request();
-        let res = call_service(&mut app, req).await;
+        let res = call_service(&app, req).await;
         assert_eq!(res.status().as_u16(), 200);
     }
 
@@ -235,23 +432,45 @@ mod tests {
             conf.scoped_service("/scope/prefix/overlap", resource);
         });
         let app = App::new().configure(|app| conf.configure(app, &()));
-        let mut app = init_service(app).await;
+        let app = init_service(app).await;
 
         let req = TestRequest::get().uri("/scope/prefix/res").to_request();
-        let res = call_service(&mut app, req).await;
+        let res = call_service(&app, req).await;
         assert_eq!(res.status().as_u16(), 200);
         let req = TestRequest::get()
             .uri("/scope/prefix/overlap/res")
             .to_request();
-        let res = call_service(&mut app, req).await;
+        let res = call_service(&app, req).await;
         assert_eq!(res.status().as_u16(), 400);
         let req = TestRequest::get()
             .uri("/scope/prefix/overlap/variable")
             .to_request();
-        let res = call_service(&mut app, req).await;
+        let res = call_service(&app, req).await;
         assert_eq!(res.status().as_u16(), 500);
     }
 
+    #[actix_rt::test]
+    async fn scopes_with_similar_names() {
+        let mut conf = AppConfig::default();
+        for prefix in ["/apix", "/api/v1", "/api"] {
+            conf.register(move |conf| {
+                let resource = web::resource("/res").to(move || async move { prefix });
+                conf.scoped_service(prefix, resource);
+            });
+        }
+        let app = App::new().configure(|app| conf.configure(app, &()));
+        let app = init_service(app).await;
+
+        for prefix in ["/api", "/apix", "/api/v1"] {
+            let req = TestRequest::get()
+                .uri(&format!("{}/res", prefix))
+                .to_request();
+            let res = call_service(&app, req).await;
+            assert_eq!(res.status().as_u16(), 200);
+            assert_eq!(read_body(res).await, prefix);
+        }
+    }
+
     #[test]
     #[should_panic(expected = "path variables are not suppored in scoped_service")]
     fn scopes_should_not_allow_variable() {
@@ -0,0 +1,161 @@
+use std::future::ready;
+use std::future::Ready;
+
+use actix_web::body::EitherBody;
+use actix_web::dev::forward_ready;
+use actix_web::dev::Service;
+use actix_web::dev::ServiceRequest;
+use actix_web::dev::ServiceResponse;
+use actix_web::dev::Transform;
+use actix_web::http::header;
+use actix_web::Error;
+use actix_web::HttpMessage;
+
+use crate::errors::api_error_response;
+use crate::errors::error_body;
+
+/// Actix Web middleware to reject requests with non-JSON bodies.
+///
+/// Requests that carry a body without a `Content-Type: application/json` header
+/// are rejected with a `415 Unsupported Media Type` response.
+/// The response body follows the JSON error format used across Replicante APIs.
+///
+/// Requests without a body (such as most `GET` and `DELETE` requests) are always allowed.
+#[derive(Clone, Debug, Default)]
+pub struct RequireJsonMiddleware;
+
+// `S` - type of the next service
+// `B` - type of response's body
+impl<S, B> Transform<S, ServiceRequest> for RequireJsonMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = MiddlewareService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(MiddlewareService { service }))
+    }
+}
+
+/// Inner middleware to process requests on behalf of `RequireJsonMiddleware`.
+pub struct MiddlewareService<S> {
+    service: S,
+}
+
+impl<S, B> Service<ServiceRequest> for MiddlewareService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = crate::LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        if has_body(&req) && !is_json(&req) {
+            let message = "request body must be JSON (Content-Type: application/json)";
+            let response = api_error_response(&error_body("UnsupportedMediaType", message))
+                .map_into_right_body();
+            let response = req.into_response(response);
+            return Box::pin(async move { Ok(response) });
+        }
+
+        let response = self.service.call(req);
+        Box::pin(async move {
+            let response = response.await?;
+            Ok(response.map_into_left_body())
+        })
+    }
+}
+
+/// Check if the request declares a (non-empty) body.
+fn has_body(req: &ServiceRequest) -> bool {
+    let headers = req.headers();
+    if headers.contains_key(header::TRANSFER_ENCODING) {
+        return true;
+    }
+    headers
+        .get(header::CONTENT_LENGTH)
+        .and_then(|length| length.to_str().ok())
+        .and_then(|length| length.parse::<u64>().ok())
+        .map(|length| length > 0)
+        .unwrap_or(false)
+}
+
+/// Check if the request declares a JSON body.
+fn is_json(req: &ServiceRequest) -> bool {
+    let content_type = req.content_type();
+    content_type.eq_ignore_ascii_case("application/json")
+        || (content_type.starts_with("application/") && content_type.ends_with("+json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::header::ContentType;
+    use actix_web::http::StatusCode;
+    use actix_web::test::call_service;
+    use actix_web::test::init_service;
+    use actix_web::test::read_body_json;
+    use actix_web::test::TestRequest;
+    use actix_web::web;
+    use actix_web::App;
+    use actix_web::HttpResponse;
+    use serde_json::Value;
+
+    use super::RequireJsonMiddleware;
+
+    macro_rules! app {
+        () => {
+            init_service(
+                App::new()
+                    .wrap(RequireJsonMiddleware)
+                    .route("/", web::get().to(HttpResponse::Ok))
+                    .route("/", web::post().to(HttpResponse::Ok)),
+            )
+            .await
+        };
+    }
+
+    #[actix_rt::test]
+    async fn get_passes() {
+        let app = app!();
+        let request = TestRequest::get().uri("/").to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[actix_rt::test]
+    async fn json_post_passes() {
+        let app = app!();
+        let request = TestRequest::post()
+            .uri("/")
+            .insert_header(ContentType::json())
+            .set_payload(r#"{"key":"value"}"#)
+            .to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[actix_rt::test]
+    async fn text_post_rejected() {
+        let app = app!();
+        let request = TestRequest::post()
+            .uri("/")
+            .insert_header(ContentType::plaintext())
+            .set_payload("key=value")
+            .to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        let body: Value = read_body_json(response).await;
+        assert_eq!(body["variant"], "UnsupportedMediaType");
+    }
+}
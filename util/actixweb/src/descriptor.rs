@@ -1,6 +1,10 @@
+use std::collections::BTreeMap;
 use std::collections::HashMap;
+use std::future::ready;
+use std::future::Ready;
 
 use actix_web::web;
+use actix_web::HttpResponse;
 use actix_web::Resource;
 
 /// Type of API enablement flags.
@@ -93,3 +97,61 @@ pub trait RootDescriptor {
         }
     }
 }
+
+/// Root for process introspection endpoints (metrics, health checks, ...).
+///
+/// The root is enabled unless the `introspection` flag is set to `false`.
+pub struct IntrospectionRoot;
+
+impl RootDescriptor for IntrospectionRoot {
+    fn enabled(&self, flags: &APIFlags) -> bool {
+        flags.get("introspection").copied().unwrap_or(true)
+    }
+
+    fn prefix(&self) -> &'static str {
+        ""
+    }
+}
+
+/// ActixWeb `Responder` listing enabled roots and their prefixes.
+///
+/// Responds with a JSON object mapping root names to their prefix.
+/// Roots are added to the index only if they are enabled by the given `APIFlags`.
+///
+/// ## Example
+/// ```
+/// use replicante_util_actixweb::APIFlags;
+/// use replicante_util_actixweb::IntrospectionRoot;
+/// use replicante_util_actixweb::RootsIndex;
+///
+/// let flags = APIFlags::new();
+/// let index = RootsIndex::default().root("introspection", &IntrospectionRoot, &flags);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct RootsIndex {
+    roots: BTreeMap<String, &'static str>,
+}
+
+impl RootsIndex {
+    /// Add a root to the index, if it is enabled.
+    pub fn root<R, S>(mut self, name: S, root: &R, flags: &APIFlags) -> RootsIndex
+    where
+        R: RootDescriptor,
+        S: Into<String>,
+    {
+        let name = name.into();
+        root.and_then(flags, |root| {
+            self.roots.insert(name, root.prefix());
+        });
+        self
+    }
+}
+
+impl actix_web::Handler<()> for RootsIndex {
+    type Output = HttpResponse;
+    type Future = Ready<Self::Output>;
+
+    fn call(&self, _: ()) -> Self::Future {
+        ready(HttpResponse::Ok().json(&self.roots))
+    }
+}
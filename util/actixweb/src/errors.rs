@@ -1,5 +1,183 @@
+use std::fmt;
+
+use actix_web::http::header::ContentType;
+use actix_web::http::StatusCode;
+use actix_web::HttpResponse;
+use actix_web::ResponseError;
+use replicante_util_tracing::ConfigError as TracingConfigError;
+use replicante_util_tracing::Error as TracingError;
+use serde::Deserialize;
+use serde::Serialize;
 use thiserror::Error;
 
+/// Environment variable to pretty-print JSON error responses in release builds.
+pub const PRETTY_ERRORS_ENV: &str = "REPLICANTE_PRETTY_ERRORS";
+
+/// Check if JSON error responses should be pretty-printed.
+///
+/// Errors are pretty-printed in debug builds or when the `REPLICANTE_PRETTY_ERRORS`
+/// environment variable is set to `1` or `true`, and are compact otherwise.
+pub fn pretty_errors() -> bool {
+    cfg!(debug_assertions) || env_flag(std::env::var(PRETTY_ERRORS_ENV).ok().as_deref())
+}
+
+/// Check if an environment variable value enables a flag (case insensitive).
+fn env_flag(value: Option<&str>) -> bool {
+    matches!(value.map(str::to_lowercase).as_deref(), Some("1" | "true"))
+}
+
+/// JSON body of error responses.
+///
+/// The format matches the `SerializableFail` errors of `replicante_util_failure`
+/// so error bodies from services using either crate can be decoded with this type.
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Serialize, Deserialize)]
+pub struct ErrorBody {
+    /// Error message.
+    pub error: String,
+
+    /// Layers of errors that ultimately caused this error.
+    pub layers: Vec<String>,
+
+    /// Optional formatted backtrace to aid debugging.
+    pub trace: Option<String>,
+
+    /// Identifier of the reported error variant.
+    #[serde(default)]
+    pub variant: Option<String>,
+}
+
+impl ErrorBody {
+    /// Convert the error into a JSON HTTP response with the given status.
+    ///
+    /// The JSON body is pretty-printed if `pretty_errors` says so.
+    /// Use `api_error_response` to select the status based on the error `variant` instead.
+    pub fn into_response(self, status: StatusCode) -> HttpResponse {
+        HttpResponse::build(status)
+            .content_type(ContentType::json())
+            .body(self.to_json(pretty_errors()))
+    }
+
+    /// Encode the error as compact or pretty-printed JSON.
+    pub fn to_json(&self, pretty: bool) -> String {
+        let encoded = match pretty {
+            true => serde_json::to_string_pretty(self),
+            false => serde_json::to_string(self),
+        };
+        encoded.expect("ErrorBody can always be encoded as JSON")
+    }
+}
+
+impl fmt::Display for ErrorBody {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.error)
+    }
+}
+
+/// `ErrorBody` for error responses generated by this crate.
+pub(crate) fn error_body(variant: &str, message: &str) -> ErrorBody {
+    ErrorBody {
+        error: message.to_string(),
+        layers: vec![message.to_string()],
+        trace: None,
+        variant: Some(variant.to_string()),
+    }
+}
+
+/// HTTP status of known error variants.
+///
+/// This is the single mapping from error variant to status: `ApiError` implementations
+/// look their variant up here, and so do `ErrorBody`s received from other services.
+///
+/// Tracing configuration errors are server misconfigurations and map to `500`.
+const VARIANT_STATUS: &[(&str, u16)] = &[
+    ("ApiVersionDisabled", 410),
+    ("ContextExtract", 400),
+    ("ContextInject", 500),
+    ("HandlerPanic", 500),
+    ("HeaderValueInvalid", 400),
+    ("MethodNotAllowed", 405),
+    ("MetricRegister", 500),
+    ("NotFound", 404),
+    ("QueryMalformed", 400),
+    ("QueryParamInvalid", 400),
+    ("QueryParamMissing", 400),
+    ("ThreadSpawn", 500),
+    ("TooManyRequests", 429),
+    ("TracingConfig", 500),
+    ("TracingEnvOverrideInvalid", 500),
+    ("TracingEnvOverrideMissing", 500),
+    ("TracingHeaderNameInvalid", 500),
+    ("TracingHeaderValueInvalid", 500),
+    ("TracingUrlInvalid", 500),
+    ("UnsupportedMediaType", 415),
+];
+
+/// HTTP status code for an error variant, `500` for unknown variants.
+pub fn variant_status(variant: &str) -> u16 {
+    VARIANT_STATUS
+        .iter()
+        .find(|(known, _)| *known == variant)
+        .map(|(_, status)| *status)
+        .unwrap_or(500)
+}
+
+/// Uniform mapping of errors to the HTTP status and variant of API error responses.
+///
+/// Errors in this crate implement this trait and use it in their `ResponseError`
+/// implementations so the status of each error variant is defined in one place.
+pub trait ApiError: std::fmt::Display {
+    /// HTTP status code to respond with, looked up with `variant_status` by default.
+    fn http_status(&self) -> u16 {
+        variant_status(self.variant())
+    }
+
+    /// Identifier of the error variant, reported in the `variant` field of responses.
+    fn variant(&self) -> &str;
+
+    /// Body of the error response.
+    fn error_body(&self) -> ErrorBody {
+        error_body(self.variant(), &self.to_string())
+    }
+}
+
+/// Build the JSON error response for an `ApiError`.
+///
+/// Invalid status codes are reported as `500 Internal Server Error`.
+pub fn api_error_response<E: ApiError + ?Sized>(error: &E) -> HttpResponse {
+    error.error_body().into_response(api_error_status(error))
+}
+
+/// HTTP status code of an `ApiError`.
+fn api_error_status<E: ApiError + ?Sized>(error: &E) -> StatusCode {
+    StatusCode::from_u16(error.http_status()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+/// Errors received from other services keep their variant, and status, in responses.
+impl ApiError for ErrorBody {
+    fn variant(&self) -> &str {
+        self.variant.as_deref().unwrap_or("Unknown")
+    }
+
+    fn error_body(&self) -> ErrorBody {
+        self.clone()
+    }
+}
+
+/// Implement `ResponseError` for `ApiError`s.
+macro_rules! api_response_error {
+    ($error:ty) => {
+        impl ResponseError for $error {
+            fn status_code(&self) -> StatusCode {
+                api_error_status(self)
+            }
+
+            fn error_response(&self) -> HttpResponse {
+                api_error_response(self)
+            }
+        }
+    };
+}
+
 /// Errors related to HTTP protocol logic.
 #[derive(Error, Debug)]
 pub enum HttpError {
@@ -15,6 +193,16 @@ impl HttpError {
     }
 }
 
+impl ApiError for HttpError {
+    fn variant(&self) -> &str {
+        match self {
+            HttpError::HeaderValueInvalid(_) => "HeaderValueInvalid",
+        }
+    }
+}
+
+api_response_error!(HttpError);
+
 /// Errors related to tracing contexts logic.
 #[derive(Error, Debug)]
 pub enum TracingContextError {
@@ -24,3 +212,275 @@ pub enum TracingContextError {
     #[error("failed to inject tracing context")]
     Inject,
 }
+
+impl ApiError for TracingContextError {
+    fn variant(&self) -> &str {
+        match self {
+            TracingContextError::Extract => "ContextExtract",
+            TracingContextError::Inject => "ContextInject",
+        }
+    }
+}
+
+api_response_error!(TracingContextError);
+
+/// Errors related to metrics logic.
+#[derive(Error, Debug)]
+pub enum MetricsError {
+    #[error("unable to register metric '{0}'")]
+    // (metric)
+    Register(String),
+}
+
+impl MetricsError {
+    /// Error indicating a metric could not be registered with a registry.
+    pub fn register<S: Into<String>>(metric: S) -> MetricsError {
+        MetricsError::Register(metric.into())
+    }
+}
+
+impl ApiError for MetricsError {
+    fn variant(&self) -> &str {
+        match self {
+            MetricsError::Register(_) => "MetricRegister",
+        }
+    }
+}
+
+api_response_error!(MetricsError);
+
+/// Error returned in place of the response of request handlers that panicked.
+///
+/// Responds with `500 Internal Server Error` and a JSON error body.
+#[derive(Error, Debug)]
+#[error("request handler panicked")]
+pub struct HandlerPanic;
+
+impl ApiError for HandlerPanic {
+    fn variant(&self) -> &str {
+        "HandlerPanic"
+    }
+}
+
+api_response_error!(HandlerPanic);
+
+/// Error returned for requests to API versions that are disabled.
+///
+/// Responds with the status configured on `ApiVersionMiddleware` and a JSON error body.
+#[derive(Error, Debug)]
+#[error("API version '{version}' is disabled")]
+pub struct ApiVersionDisabled {
+    status: StatusCode,
+    version: String,
+}
+
+impl ApiVersionDisabled {
+    /// Error indicating the given version is disabled, responding with `status`.
+    pub fn new<S: Into<String>>(version: S, status: StatusCode) -> ApiVersionDisabled {
+        ApiVersionDisabled {
+            status,
+            version: version.into(),
+        }
+    }
+}
+
+impl ApiError for ApiVersionDisabled {
+    fn http_status(&self) -> u16 {
+        self.status.as_u16()
+    }
+
+    fn variant(&self) -> &str {
+        "ApiVersionDisabled"
+    }
+}
+
+api_response_error!(ApiVersionDisabled);
+
+/// Errors related to parsing request query parameters.
+///
+/// Responds with `400 Bad Request` and a JSON error body when returned by handlers.
+#[derive(Error, Debug)]
+pub enum QueryError {
+    #[error("invalid value '{value}' for query parameter '{name}', expected {expected}")]
+    InvalidValue {
+        expected: &'static str,
+        name: String,
+        value: String,
+    },
+
+    #[error("unable to parse query string: {0}")]
+    // (reason)
+    Malformed(String),
+
+    #[error("missing required query parameter '{0}'")]
+    // (name)
+    Missing(String),
+}
+
+impl QueryError {
+    /// Error indicating a query parameter was not of the expected type.
+    pub fn invalid_value<N, V>(name: N, value: V, expected: &'static str) -> QueryError
+    where
+        N: Into<String>,
+        V: Into<String>,
+    {
+        QueryError::InvalidValue {
+            expected,
+            name: name.into(),
+            value: value.into(),
+        }
+    }
+
+    /// Error indicating a required query parameter was not provided.
+    pub fn missing<S: Into<String>>(name: S) -> QueryError {
+        QueryError::Missing(name.into())
+    }
+}
+
+impl ApiError for QueryError {
+    fn variant(&self) -> &str {
+        match self {
+            QueryError::InvalidValue { .. } => "QueryParamInvalid",
+            QueryError::Malformed(_) => "QueryMalformed",
+            QueryError::Missing(_) => "QueryParamMissing",
+        }
+    }
+}
+
+api_response_error!(QueryError);
+
+impl ApiError for TracingConfigError {
+    fn variant(&self) -> &str {
+        match self {
+            TracingConfigError::InvalidEnvOverride { .. } => "TracingEnvOverrideInvalid",
+            TracingConfigError::InvalidHeaderName(_) => "TracingHeaderNameInvalid",
+            TracingConfigError::InvalidHeaderValue(_) => "TracingHeaderValueInvalid",
+            TracingConfigError::InvalidUrl(_) => "TracingUrlInvalid",
+            TracingConfigError::MissingEnvOverride(_) => "TracingEnvOverrideMissing",
+        }
+    }
+}
+
+impl ApiError for TracingError {
+    fn variant(&self) -> &str {
+        match self {
+            TracingError::Config(_) => "TracingConfig",
+            TracingError::ThreadSpawn(_) => "ThreadSpawn",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::body::to_bytes;
+    use actix_web::http::StatusCode;
+    use actix_web::ResponseError;
+    use serde_json::Value;
+
+    use super::api_error_response;
+    use super::env_flag;
+    use super::error_body;
+    use super::ApiError;
+    use super::ErrorBody;
+    use super::HandlerPanic;
+    use super::MetricsError;
+    use super::TracingConfigError;
+    use super::TracingContextError;
+    use super::TracingError;
+
+    #[test]
+    fn compact_error_body() {
+        let body = error_body("NotFound", "missing").to_json(false);
+        assert_eq!(
+            body,
+            r#"{"error":"missing","layers":["missing"],"trace":null,"variant":"NotFound"}"#
+        );
+    }
+
+    #[actix_rt::test]
+    async fn into_response() {
+        let error = error_body("NotFound", "not found");
+        let response = error.clone().into_response(StatusCode::NOT_FOUND);
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body = to_bytes(response.into_body()).await.unwrap();
+        let body: ErrorBody = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body, error);
+    }
+
+    #[test]
+    fn pretty_error_body() {
+        let error = error_body("NotFound", "not found");
+        let body = error.to_json(true);
+        assert!(body.starts_with("{\n  \"error\": \"not found\",\n"));
+        let body: ErrorBody = serde_json::from_str(&body).unwrap();
+        assert_eq!(body, error);
+    }
+
+    #[test]
+    fn pretty_errors_env_flag() {
+        assert!(env_flag(Some("1")));
+        assert!(env_flag(Some("TRUE")));
+        assert!(!env_flag(Some("0")));
+        assert!(!env_flag(Some("")));
+        assert!(!env_flag(None));
+    }
+
+    #[test]
+    fn context_extract_is_a_client_error() {
+        let error = TracingContextError::Extract;
+        assert_eq!(error.http_status(), 400);
+        assert_eq!(error.variant(), "ContextExtract");
+        assert_eq!(error.status_code(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn internal_errors_are_server_errors() {
+        let error = MetricsError::register("test");
+        assert_eq!(error.http_status(), 500);
+        assert_eq!(error.status_code(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(TracingContextError::Inject.http_status(), 500);
+    }
+
+    #[test]
+    fn tracing_errors() {
+        let error = TracingConfigError::InvalidUrl("nope".into());
+        assert_eq!(error.http_status(), 500);
+        assert_eq!(error.variant(), "TracingUrlInvalid");
+        let error = TracingConfigError::InvalidHeaderValue("nope".into());
+        assert_eq!(error.http_status(), 500);
+        assert_eq!(error.variant(), "TracingHeaderValueInvalid");
+        let error = TracingError::ThreadSpawn("span collector");
+        assert_eq!(error.http_status(), 500);
+        assert_eq!(error.variant(), "ThreadSpawn");
+    }
+
+    #[actix_rt::test]
+    async fn error_body_uses_variant_status() {
+        let error = ErrorBody {
+            error: "failed to extract tracing context".into(),
+            layers: vec!["failed to extract tracing context".into(), "cause".into()],
+            trace: None,
+            variant: Some("ContextExtract".into()),
+        };
+        let response = api_error_response(&error);
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = to_bytes(response.into_body()).await.unwrap();
+        let body: ErrorBody = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body, error);
+
+        let error = error_body("ApiVersionDisabled", "API version 'v0' is disabled");
+        assert_eq!(error.http_status(), 410);
+        let error = error_body("SomethingNew", "unknown variant");
+        assert_eq!(error.http_status(), 500);
+    }
+
+    #[actix_rt::test]
+    async fn response_uses_variant() {
+        let response = HandlerPanic.error_response();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let body = to_bytes(response.into_body()).await.unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["variant"], "HandlerPanic");
+        assert_eq!(body["error"], "request handler panicked");
+    }
+}
@@ -0,0 +1,138 @@
+use std::future::ready;
+use std::future::Ready;
+
+use actix_web::http::header::HeaderValue;
+use actix_web::http::header::ALLOW;
+use actix_web::http::Method;
+use actix_web::HttpResponse;
+
+use crate::errors::api_error_response;
+use crate::errors::error_body;
+
+/// ActixWeb `Responder` for requests that don't match any route.
+///
+/// Responds with `404 Not Found` and a JSON error body.
+/// Use it as the default service of an `App` or `Scope`:
+///
+/// ```
+/// use actix_web::web;
+/// use actix_web::App;
+/// use replicante_util_actixweb::NotFoundHandler;
+///
+/// let app = App::new().default_service(web::to(NotFoundHandler));
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NotFoundHandler;
+
+impl actix_web::Handler<()> for NotFoundHandler {
+    type Output = HttpResponse;
+    type Future = Ready<Self::Output>;
+
+    fn call(&self, _: ()) -> Self::Future {
+        let response = api_error_response(&error_body("NotFound", "requested resource not found"));
+        ready(response)
+    }
+}
+
+/// ActixWeb `Responder` for requests to a resource with an unsupported method.
+///
+/// Responds with `405 Method Not Allowed`, an `Allow` header listing the
+/// given methods, and a JSON error body.
+/// Use it as the default service of a `Resource`:
+///
+/// ```
+/// use actix_web::http::Method;
+/// use actix_web::web;
+/// use actix_web::HttpResponse;
+/// use replicante_util_actixweb::MethodNotAllowedHandler;
+///
+/// let resource = web::resource("/")
+///     .route(web::get().to(HttpResponse::Ok))
+///     .default_service(web::to(MethodNotAllowedHandler::new(vec![Method::GET])));
+/// ```
+#[derive(Clone, Debug)]
+pub struct MethodNotAllowedHandler {
+    allow: String,
+}
+
+impl MethodNotAllowedHandler {
+    pub fn new(allowed: Vec<Method>) -> MethodNotAllowedHandler {
+        let allow = allowed
+            .iter()
+            .map(Method::as_str)
+            .collect::<Vec<_>>()
+            .join(", ");
+        MethodNotAllowedHandler { allow }
+    }
+}
+
+impl actix_web::Handler<()> for MethodNotAllowedHandler {
+    type Output = HttpResponse;
+    type Future = Ready<Self::Output>;
+
+    fn call(&self, _: ()) -> Self::Future {
+        let error = error_body(
+            "MethodNotAllowed",
+            "method not allowed for the requested resource",
+        );
+        let mut response = api_error_response(&error);
+        let allow = HeaderValue::from_str(&self.allow).expect("HTTP methods are valid headers");
+        response.headers_mut().insert(ALLOW, allow);
+        ready(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::header::ALLOW;
+    use actix_web::http::Method;
+    use actix_web::http::StatusCode;
+    use actix_web::test::call_service;
+    use actix_web::test::init_service;
+    use actix_web::test::read_body_json;
+    use actix_web::test::TestRequest;
+    use actix_web::web;
+    use actix_web::App;
+    use actix_web::HttpResponse;
+    use serde_json::Value;
+
+    use super::MethodNotAllowedHandler;
+    use super::NotFoundHandler;
+
+    macro_rules! app {
+        () => {{
+            let resource = web::resource("/mapped")
+                .route(web::get().to(HttpResponse::Ok))
+                .route(web::put().to(HttpResponse::Ok))
+                .default_service(web::to(MethodNotAllowedHandler::new(vec![
+                    Method::GET,
+                    Method::PUT,
+                ])));
+            let app = App::new()
+                .service(resource)
+                .default_service(web::to(NotFoundHandler));
+            init_service(app).await
+        }};
+    }
+
+    #[actix_rt::test]
+    async fn method_not_allowed() {
+        let app = app!();
+        let request = TestRequest::post().uri("/mapped").to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(response.headers().get(ALLOW).unwrap(), "GET, PUT");
+        let body: Value = read_body_json(response).await;
+        assert_eq!(body["variant"], "MethodNotAllowed");
+    }
+
+    #[actix_rt::test]
+    async fn not_found() {
+        let app = app!();
+        let request = TestRequest::get().uri("/unmapped").to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body: Value = read_body_json(response).await;
+        assert_eq!(body["variant"], "NotFound");
+    }
+}
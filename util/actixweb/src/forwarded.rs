@@ -0,0 +1,301 @@
+use std::net::IpAddr;
+use std::net::SocketAddr;
+
+use actix_web::http::header::HOST;
+use actix_web::http::Uri;
+use actix_web::HttpRequest;
+
+/// Header set by proxies to report the original client address.
+const X_FORWARDED_FOR: &str = "x-forwarded-for";
+
+/// Header set by proxies to report the original `Host` header.
+const X_FORWARDED_HOST: &str = "x-forwarded-host";
+
+/// Header set by proxies to report the original request scheme.
+const X_FORWARDED_PROTO: &str = "x-forwarded-proto";
+
+/// Client address, scheme and host of a request as seen before any proxy.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RequestOrigin {
+    /// Address of the client that sent the request, if known.
+    pub client: Option<IpAddr>,
+
+    /// Host the client sent the request to, if known.
+    pub host: Option<String>,
+
+    /// Scheme (`http` or `https`) used by the client.
+    pub scheme: String,
+}
+
+impl RequestOrigin {
+    /// Full URL requested by the client for the given request URI.
+    pub fn url(&self, uri: &Uri) -> String {
+        let path = uri
+            .path_and_query()
+            .map(|path| path.as_str())
+            .unwrap_or("/");
+        match self.host.as_ref() {
+            None => path.to_string(),
+            Some(host) => format!("{}://{}{}", self.scheme, host, path),
+        }
+    }
+}
+
+/// Proxies trusted to report the original client address and scheme of requests.
+///
+/// Proxies report details about the original request with the standard `Forwarded`
+/// header or the `X-Forwarded-For`, `X-Forwarded-Host` and `X-Forwarded-Proto` headers.
+/// These headers are only honoured when the request comes directly from a trusted proxy
+/// as any client can set them to arbitrary values.
+///
+/// Clients can also send forwarding headers that proxies then extend, so the client
+/// address is the first hop that is not a trusted proxy, looking from the most recent
+/// hop (the rightmost) to the oldest one.
+///
+/// By default no proxy is trusted and the connection details are used.
+#[derive(Clone, Debug, Default)]
+pub struct TrustedProxies {
+    proxies: Vec<IpAddr>,
+}
+
+impl TrustedProxies {
+    /// Trust forwarding headers from the given proxy addresses.
+    pub fn new<I>(proxies: I) -> TrustedProxies
+    where
+        I: IntoIterator<Item = IpAddr>,
+    {
+        let proxies = proxies.into_iter().collect();
+        TrustedProxies { proxies }
+    }
+
+    /// Check if the given address belongs to a trusted proxy.
+    pub fn is_trusted(&self, addr: IpAddr) -> bool {
+        self.proxies.contains(&addr)
+    }
+
+    /// Determine the client address, host and scheme of the request.
+    ///
+    /// The host is taken from the request URI authority (set by HTTP/2 requests)
+    /// or the `Host` header, falling back to the configured server host.
+    pub fn origin(&self, request: &HttpRequest) -> RequestOrigin {
+        let peer = request.peer_addr().map(|addr| addr.ip());
+        let scheme = if request.app_config().secure() {
+            "https"
+        } else {
+            "http"
+        };
+        let host = request
+            .uri()
+            .authority()
+            .map(|authority| authority.to_string())
+            .or_else(|| header(request, HOST.as_str()).map(String::from))
+            .or_else(|| Some(request.app_config().host().to_string()));
+        let mut origin = RequestOrigin {
+            client: peer,
+            host,
+            scheme: scheme.to_string(),
+        };
+        match peer {
+            Some(peer) if self.is_trusted(peer) => (),
+            _ => return origin,
+        };
+
+        // Trusted proxy: prefer the standard header and fall back to de-facto ones.
+        if let Some(forwarded) = header(request, "forwarded") {
+            let elements: Vec<&str> = forwarded.split(',').collect();
+            let element = self.client_hop(&elements, |element| {
+                element.split(';').find_map(|pair| {
+                    let mut parts = pair.trim().splitn(2, '=');
+                    let key = parts.next().unwrap_or("");
+                    let value = parts.next().unwrap_or("").trim_matches('"');
+                    if key.eq_ignore_ascii_case("for") {
+                        parse_node(value)
+                    } else {
+                        None
+                    }
+                })
+            });
+            for pair in element.split(';') {
+                let mut parts = pair.trim().splitn(2, '=');
+                let key = parts.next().unwrap_or("").to_ascii_lowercase();
+                let value = parts.next().unwrap_or("").trim_matches('"');
+                match key.as_str() {
+                    "for" => origin.client = parse_node(value).or(origin.client),
+                    "host" if !value.is_empty() => origin.host = Some(value.to_string()),
+                    "proto" if !value.is_empty() => origin.scheme = value.to_ascii_lowercase(),
+                    _ => (),
+                }
+            }
+            return origin;
+        }
+        if let Some(clients) = header(request, X_FORWARDED_FOR) {
+            let clients: Vec<&str> = clients.split(',').collect();
+            let client = self.client_hop(&clients, parse_node);
+            origin.client = parse_node(client).or(origin.client);
+        }
+        if let Some(host) = header(request, X_FORWARDED_HOST) {
+            origin.host = Some(host.trim().to_string());
+        }
+        if let Some(proto) = header(request, X_FORWARDED_PROTO) {
+            origin.scheme = proto.trim().to_ascii_lowercase();
+        }
+        origin
+    }
+
+    /// Find the hop of the original client in a list of forwarding entries.
+    ///
+    /// Entries are walked from the rightmost, skipping those from trusted proxies.
+    /// If all entries are from trusted proxies the leftmost one is returned.
+    fn client_hop<'a, F>(&self, hops: &[&'a str], address: F) -> &'a str
+    where
+        F: Fn(&str) -> Option<IpAddr>,
+    {
+        hops.iter()
+            .rev()
+            .find(|hop| match address(hop) {
+                Some(addr) => !self.is_trusted(addr),
+                None => true,
+            })
+            .or_else(|| hops.first())
+            .copied()
+            .unwrap_or("")
+    }
+}
+
+/// Read a header as a string, ignoring invalid values.
+fn header<'a>(request: &'a HttpRequest, name: &str) -> Option<&'a str> {
+    request
+        .headers()
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+}
+
+/// Parse an IP address with an optional port, as found in forwarding headers.
+fn parse_node(node: &str) -> Option<IpAddr> {
+    let node = node.trim();
+    if let Ok(addr) = node.parse::<IpAddr>() {
+        return Some(addr);
+    }
+    if let Ok(addr) = node.parse::<SocketAddr>() {
+        return Some(addr.ip());
+    }
+    // IPv6 addresses without a port are quoted and bracketed in the `Forwarded` header.
+    node.trim_start_matches('[')
+        .trim_end_matches(']')
+        .parse::<IpAddr>()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::IpAddr;
+    use std::net::SocketAddr;
+
+    use actix_web::test::TestRequest;
+
+    use super::TrustedProxies;
+
+    fn proxies() -> TrustedProxies {
+        TrustedProxies::new(vec![
+            "10.0.0.1".parse::<IpAddr>().unwrap(),
+            "10.0.0.2".parse::<IpAddr>().unwrap(),
+        ])
+    }
+
+    fn request(peer: &str) -> TestRequest {
+        let peer: SocketAddr = peer.parse().unwrap();
+        TestRequest::get()
+            .uri("/path?query=1")
+            .insert_header(("Host", "internal:8080"))
+            .peer_addr(peer)
+    }
+
+    #[test]
+    fn connection_details_by_default() {
+        let request = request("10.0.0.1:1234").to_http_request();
+        let origin = TrustedProxies::default().origin(&request);
+        assert_eq!(origin.client, Some("10.0.0.1".parse().unwrap()));
+        assert_eq!(origin.scheme, "http");
+        assert_eq!(
+            origin.url(request.uri()),
+            "http://internal:8080/path?query=1"
+        );
+    }
+
+    #[test]
+    fn forwarded_from_trusted_proxy() {
+        let request = request("10.0.0.1:1234")
+            .insert_header((
+                "Forwarded",
+                "for=\"192.0.2.60:4711\";proto=https;host=example.com, for=10.0.0.2",
+            ))
+            .to_http_request();
+        let origin = proxies().origin(&request);
+        assert_eq!(origin.client, Some("192.0.2.60".parse().unwrap()));
+        assert_eq!(origin.scheme, "https");
+        assert_eq!(
+            origin.url(request.uri()),
+            "https://example.com/path?query=1"
+        );
+    }
+
+    #[test]
+    fn x_forwarded_from_trusted_proxy() {
+        let request = request("10.0.0.1:1234")
+            .insert_header(("X-Forwarded-For", "192.0.2.60, 10.0.0.2"))
+            .insert_header(("X-Forwarded-Proto", "https"))
+            .to_http_request();
+        let origin = proxies().origin(&request);
+        assert_eq!(origin.client, Some("192.0.2.60".parse().unwrap()));
+        assert_eq!(origin.scheme, "https");
+        assert_eq!(
+            origin.url(request.uri()),
+            "https://internal:8080/path?query=1"
+        );
+    }
+
+    #[test]
+    fn spoofed_leftmost_entry_is_ignored() {
+        let spoofed = request("10.0.0.1:1234")
+            .insert_header(("X-Forwarded-For", "1.2.3.4, 192.0.2.60, 10.0.0.2"))
+            .to_http_request();
+        let origin = proxies().origin(&spoofed);
+        assert_eq!(origin.client, Some("192.0.2.60".parse().unwrap()));
+
+        let spoofed = request("10.0.0.1:1234")
+            .insert_header((
+                "Forwarded",
+                "for=1.2.3.4;host=evil.com, for=192.0.2.60;host=example.com, for=10.0.0.2",
+            ))
+            .to_http_request();
+        let origin = proxies().origin(&spoofed);
+        assert_eq!(origin.client, Some("192.0.2.60".parse().unwrap()));
+        assert_eq!(origin.host.as_deref(), Some("example.com"));
+    }
+
+    #[test]
+    fn http2_authority_without_host_header() {
+        let peer: SocketAddr = "192.0.2.99:1234".parse().unwrap();
+        let request = TestRequest::get()
+            .uri("http://example.com:8443/path?query=1")
+            .peer_addr(peer)
+            .to_http_request();
+        assert!(request.headers().get("host").is_none());
+        let origin = TrustedProxies::default().origin(&request);
+        assert_eq!(
+            origin.url(request.uri()),
+            "http://example.com:8443/path?query=1"
+        );
+    }
+
+    #[test]
+    fn x_forwarded_from_untrusted_source() {
+        let request = request("192.0.2.99:1234")
+            .insert_header(("X-Forwarded-For", "192.0.2.60"))
+            .insert_header(("X-Forwarded-Proto", "https"))
+            .to_http_request();
+        let origin = proxies().origin(&request);
+        assert_eq!(origin.client, Some("192.0.2.99".parse().unwrap()));
+        assert_eq!(origin.scheme, "http");
+    }
+}
@@ -0,0 +1,366 @@
+use std::collections::BTreeMap;
+use std::future::ready;
+use std::future::Ready;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix_web::HttpResponse;
+use replicante_models_api::HealthCheckResult;
+use replicante_models_api::HealthStatus;
+use replicante_util_upkeep::Upkeep;
+
+/// Type alias for health check functions to improve code readability.
+type HealthCheckFn = Arc<dyn Fn() -> HealthStatus + Send + Sync>;
+
+/// Name of the synthetic check reported by readiness handlers during the lame duck period.
+const SHUTDOWN_CHECK: &str = "shutdown";
+
+/// Set of named health checks for the components and dependencies of a process.
+///
+/// Processes register separate sets for liveness and readiness: checks of dependencies
+/// that the process can recover from belong in the readiness set only so the process
+/// stops receiving traffic without being restarted while they are unavailable.
+#[derive(Clone, Default)]
+pub struct HealthChecks {
+    checks: BTreeMap<String, HealthCheck>,
+    lame_duck: Arc<AtomicBool>,
+}
+
+impl HealthChecks {
+    /// Aggregate check results into the overall health of the process.
+    ///
+    ///   * The process is `Failed` if any critical check is `Failed`.
+    ///   * The process is `Degraded` if any check is `Degraded` or a non-critical check is `Failed`.
+    ///   * The process is `Healthy` otherwise.
+    ///
+    /// Details of unhealthy results are reported as `name: details` pairs.
+    pub fn aggregate(results: &BTreeMap<String, HealthCheckResult>) -> HealthStatus {
+        let mut degraded = Vec::new();
+        let mut failed = Vec::new();
+        for (name, result) in results {
+            match &result.status {
+                HealthStatus::Healthy => (),
+                HealthStatus::Failed(details) if result.critical => {
+                    failed.push(format!("{}: {}", name, details))
+                }
+                HealthStatus::Degraded(details) | HealthStatus::Failed(details) => {
+                    degraded.push(format!("{}: {}", name, details))
+                }
+            }
+        }
+        if !failed.is_empty() {
+            return HealthStatus::Failed(failed.join("; "));
+        }
+        if !degraded.is_empty() {
+            return HealthStatus::Degraded(degraded.join("; "));
+        }
+        HealthStatus::Healthy
+    }
+
+    /// Run all registered checks and collect the results by name.
+    pub fn check(&self) -> BTreeMap<String, HealthCheckResult> {
+        self.checks
+            .iter()
+            .map(|(name, check)| {
+                let result = HealthCheckResult {
+                    critical: check.critical,
+                    kind: check.kind.clone(),
+                    status: (check.check)(),
+                };
+                (name.clone(), result)
+            })
+            .collect()
+    }
+
+    /// Report the process as not ready for `delay` when the `Upkeep` shutdown flow begins.
+    ///
+    /// Readiness handlers respond with `503 Service Unavailable` as soon as the shutdown
+    /// flow begins, then the flow is paused for `delay` so load balancers can stop sending
+    /// traffic to the process before threads are stopped and connections are closed.
+    /// Liveness handlers are not affected.
+    ///
+    /// The lame duck state is shared by all clones of these checks.
+    pub fn lame_duck_on_shutdown(&self, upkeep: &mut Upkeep, delay: Duration) {
+        let lame_duck = Arc::clone(&self.lame_duck);
+        upkeep.on_shutdown_begin(move || {
+            lame_duck.store(true, Ordering::SeqCst);
+            std::thread::sleep(delay);
+        });
+    }
+
+    /// Register a critical health check function with the given name.
+    ///
+    /// Registering a check with the same name as an existing check replaces it.
+    pub fn register<F, S>(&mut self, name: S, check: F)
+    where
+        F: Fn() -> HealthStatus + Send + Sync + 'static,
+        S: Into<String>,
+    {
+        let check = HealthCheck {
+            check: Arc::new(check),
+            critical: true,
+            kind: None,
+        };
+        self.checks.insert(name.into(), check);
+    }
+
+    /// Register a health check function for a component of the given type.
+    ///
+    /// Failures of non-critical components only degrade the process.
+    /// Registering a check with the same name as an existing check replaces it.
+    pub fn register_component<F, K, S>(&mut self, name: S, kind: K, critical: bool, check: F)
+    where
+        F: Fn() -> HealthStatus + Send + Sync + 'static,
+        K: Into<String>,
+        S: Into<String>,
+    {
+        let check = HealthCheck {
+            check: Arc::new(check),
+            critical,
+            kind: Some(kind.into()),
+        };
+        self.checks.insert(name.into(), check);
+    }
+}
+
+/// Registered health check function and information about the checked component.
+#[derive(Clone)]
+struct HealthCheck {
+    check: HealthCheckFn,
+    critical: bool,
+    kind: Option<String>,
+}
+
+/// ActixWeb `Responder` to report the result of a set of health checks.
+///
+/// Handlers respond with `503 Service Unavailable` if the checks aggregate to `Failed`
+/// (any critical check is `Failed`, see `HealthChecks::aggregate`) and `200 OK` otherwise.
+/// Liveness and readiness handlers report on their own set of checks.
+///
+/// Readiness handlers also fail during the lame duck period configured with
+/// `HealthChecks::lame_duck_on_shutdown`.
+#[derive(Clone)]
+pub struct HealthHandler {
+    checks: HealthChecks,
+    mode: HealthMode,
+}
+
+impl HealthHandler {
+    /// Report the readiness of the process (same as `HealthHandler::readiness`).
+    pub fn new(checks: HealthChecks) -> HealthHandler {
+        HealthHandler::readiness(checks)
+    }
+
+    /// Report the liveness of the process from the given checks.
+    pub fn liveness(checks: HealthChecks) -> HealthHandler {
+        let mode = HealthMode::Liveness;
+        HealthHandler { checks, mode }
+    }
+
+    /// Report the readiness of the process from the given checks.
+    pub fn readiness(checks: HealthChecks) -> HealthHandler {
+        let mode = HealthMode::Readiness;
+        HealthHandler { checks, mode }
+    }
+}
+
+impl actix_web::Handler<()> for HealthHandler {
+    type Output = HttpResponse;
+    type Future = Ready<Self::Output>;
+
+    fn call(&self, _: ()) -> Self::Future {
+        let mut results = self.checks.check();
+        if let HealthMode::Readiness = self.mode {
+            if self.checks.lame_duck.load(Ordering::SeqCst) {
+                let shutdown = HealthCheckResult {
+                    critical: true,
+                    kind: None,
+                    status: HealthStatus::Failed("process is shutting down".into()),
+                };
+                results.insert(SHUTDOWN_CHECK.to_string(), shutdown);
+            }
+        }
+        let failed = matches!(HealthChecks::aggregate(&results), HealthStatus::Failed(_));
+        let mut response = if failed {
+            HttpResponse::ServiceUnavailable()
+        } else {
+            HttpResponse::Ok()
+        };
+        ready(response.json(results))
+    }
+}
+
+/// Kind of health reported by a `HealthHandler`.
+#[derive(Clone, Copy, Debug)]
+enum HealthMode {
+    Liveness,
+    Readiness,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use std::time::Instant;
+
+    use actix_web::http::StatusCode;
+    use actix_web::test::call_service;
+    use actix_web::test::init_service;
+    use actix_web::test::read_body_json;
+    use actix_web::test::TestRequest;
+    use actix_web::web;
+    use actix_web::App;
+    use replicante_models_api::HealthCheckResult;
+    use replicante_models_api::HealthStatus;
+    use replicante_util_upkeep::Upkeep;
+    use serde_json::json;
+    use serde_json::Value;
+
+    use super::HealthChecks;
+    use super::HealthHandler;
+
+    #[actix_rt::test]
+    async fn healthy() {
+        let mut checks = HealthChecks::default();
+        checks.register("db", || HealthStatus::Healthy);
+        checks.register("cache", || HealthStatus::Degraded("slow".into()));
+        let service = web::resource("/").to(HealthHandler::new(checks));
+        let app = init_service(App::new().service(service)).await;
+        let request = TestRequest::with_uri("/").to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: Value = read_body_json(response).await;
+        assert_eq!(
+            body,
+            json!({
+                "cache": {"critical": true, "status": "DEGRADED", "details": "slow"},
+                "db": {"critical": true, "status": "HEALTHY"},
+            })
+        );
+    }
+
+    #[actix_rt::test]
+    async fn failed() {
+        let mut checks = HealthChecks::default();
+        checks.register("db", || HealthStatus::Failed("down".into()));
+        let service = web::resource("/").to(HealthHandler::new(checks));
+        let app = init_service(App::new().service(service)).await;
+        let request = TestRequest::with_uri("/").to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[actix_rt::test]
+    async fn liveness_and_readiness_diverge() {
+        let mut liveness = HealthChecks::default();
+        liveness.register("process", || HealthStatus::Healthy);
+        let mut readiness = liveness.clone();
+        readiness.register("db", || HealthStatus::Failed("down".into()));
+        let liveness = web::resource("/health").to(HealthHandler::liveness(liveness));
+        let readiness = web::resource("/ready").to(HealthHandler::readiness(readiness));
+        let app = init_service(App::new().service(liveness).service(readiness)).await;
+
+        let request = TestRequest::with_uri("/health").to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: Value = read_body_json(response).await;
+        assert!(body.get("db").is_none());
+        let request = TestRequest::with_uri("/ready").to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[actix_rt::test]
+    async fn liveness_failed() {
+        let mut checks = HealthChecks::default();
+        checks.register("process", || HealthStatus::Failed("stuck".into()));
+        checks.register_component("cache", "cache", false, || HealthStatus::Healthy);
+        let service = web::resource("/").to(HealthHandler::liveness(checks));
+        let app = init_service(App::new().service(service)).await;
+        let request = TestRequest::with_uri("/").to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[actix_rt::test]
+    async fn lame_duck_on_shutdown() {
+        let mut checks = HealthChecks::default();
+        checks.register("process", || HealthStatus::Healthy);
+        let mut upkeep = Upkeep::new();
+        checks.lame_duck_on_shutdown(&mut upkeep, Duration::from_secs(2));
+        let liveness = web::resource("/health").to(HealthHandler::liveness(checks.clone()));
+        let readiness = web::resource("/ready").to(HealthHandler::readiness(checks));
+        let app = init_service(App::new().service(liveness).service(readiness)).await;
+
+        let request = TestRequest::with_uri("/ready").to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let (keepalive, shutdown) = upkeep.spawn_keepalive();
+        shutdown.shutdown();
+        let deadline = Instant::now() + Duration::from_secs(1);
+        let response = loop {
+            let request = TestRequest::with_uri("/ready").to_request();
+            let response = call_service(&app, request).await;
+            if response.status() != StatusCode::OK || Instant::now() > deadline {
+                break response;
+            }
+            actix_rt::time::sleep(Duration::from_millis(10)).await;
+        };
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body: Value = read_body_json(response).await;
+        assert_eq!(body["shutdown"]["status"], "FAILED");
+
+        let request = TestRequest::with_uri("/health").to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(!keepalive.is_finished());
+        assert!(keepalive.join().unwrap());
+    }
+
+    #[test]
+    fn aggregate_critical_failure() {
+        let mut checks = HealthChecks::default();
+        checks.register_component("cache", "cache", false, || HealthStatus::Healthy);
+        checks.register_component("db", "db", true, || HealthStatus::Failed("down".into()));
+        let overall = HealthChecks::aggregate(&checks.check());
+        assert_eq!(overall, HealthStatus::Failed("db: down".into()));
+    }
+
+    #[test]
+    fn aggregate_non_critical_failure() {
+        let mut checks = HealthChecks::default();
+        checks.register_component("cache", "cache", false, || {
+            HealthStatus::Failed("down".into())
+        });
+        checks.register_component("db", "db", true, || HealthStatus::Healthy);
+        let overall = HealthChecks::aggregate(&checks.check());
+        assert_eq!(overall, HealthStatus::Degraded("cache: down".into()));
+    }
+
+    #[actix_rt::test]
+    async fn component_tags() {
+        let mut checks = HealthChecks::default();
+        checks.register_component("cache", "redis", false, || {
+            HealthStatus::Failed("down".into())
+        });
+        checks.register_component("primary", "db", true, || HealthStatus::Healthy);
+        let service = web::resource("/").to(HealthHandler::readiness(checks));
+        let app = init_service(App::new().service(service)).await;
+        let request = TestRequest::with_uri("/").to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: Value = read_body_json(response).await;
+        assert_eq!(
+            body,
+            json!({
+                "cache": {"critical": false, "type": "redis", "status": "FAILED", "details": "down"},
+                "primary": {"critical": true, "type": "db", "status": "HEALTHY"},
+            })
+        );
+        let cache: HealthCheckResult = serde_json::from_value(body["cache"].clone()).unwrap();
+        assert_eq!(cache.kind.as_deref(), Some("redis"));
+        assert_eq!(cache.status, HealthStatus::Failed("down".into()));
+    }
+}
@@ -0,0 +1,116 @@
+use std::task::Context;
+use std::task::Poll;
+
+use http::Request;
+use http::Response;
+use slog::info;
+use slog::Logger;
+use tower::Layer;
+use tower::Service;
+
+use super::BoxFuture;
+
+/// Tower layer to log requests, like `LoggingMiddleware` does for Actix Web.
+#[derive(Clone)]
+pub struct LoggingLayer {
+    logger: Logger,
+}
+
+impl LoggingLayer {
+    pub fn new(logger: Logger) -> LoggingLayer {
+        LoggingLayer { logger }
+    }
+}
+
+impl<S> Layer<S> for LoggingLayer {
+    type Service = LoggingService<S>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        LoggingService {
+            logger: self.logger.clone(),
+            service,
+        }
+    }
+}
+
+/// Inner service to process requests on behalf of `LoggingLayer`.
+#[derive(Clone)]
+pub struct LoggingService<S> {
+    logger: Logger,
+    service: S,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for LoggingService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+    S::Future: Send + 'static,
+{
+    type Response = Response<ResBody>;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        let logger = self.logger.clone();
+        let method = req.method().clone();
+        let path = req.uri().path().to_string();
+        let response = self.service.call(req);
+        Box::pin(async move {
+            let response = response.await?;
+            let status = response.status();
+            let error = status.is_server_error() || status.is_client_error();
+            info!(
+                logger,
+                "Request handled";
+                "success" => !error,
+                "method" => %method,
+                "path" => path,
+                "status" => %status,
+            );
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use http::Request;
+    use http::Response;
+    use http::StatusCode;
+    use tower::service_fn;
+    use tower::Layer;
+    use tower::ServiceExt;
+
+    use replicante_logging::buffer_logger;
+
+    use super::LoggingLayer;
+
+    #[actix_rt::test]
+    async fn log_line_per_request() {
+        let (logger, sink) = buffer_logger();
+        let service = LoggingLayer::new(logger).layer(service_fn(|_: Request<()>| async {
+            let response = Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .body(())
+                .unwrap();
+            Ok::<_, Infallible>(response)
+        }));
+        let request = Request::get("/missing").body(()).unwrap();
+        let response = service.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let records = sink.records();
+        assert_eq!(records.len(), 1);
+        let record = &records[0];
+        assert_eq!(record.message, "Request handled");
+        assert_eq!(record.fields["method"], "GET");
+        assert_eq!(record.fields["path"], "/missing");
+        assert_eq!(record.fields["status"], "404 Not Found");
+        assert_eq!(record.fields["success"], "false");
+    }
+}
@@ -0,0 +1,203 @@
+use std::sync::Arc;
+use std::task::Context;
+use std::task::Poll;
+
+use http::Extensions;
+use http::Request;
+use http::Response;
+use http::Uri;
+use tower::Layer;
+use tower::Service;
+
+use super::BoxFuture;
+use crate::metrics::DurationTimer;
+use crate::metrics::PATH_UNMATCHED;
+use crate::MetricsCollector;
+
+/// Status label used for requests that failed without a response.
+const STATUS_ERROR: &str = "error";
+
+type PathLabel = Arc<dyn Fn(&Uri, &Extensions) -> Option<String> + Send + Sync>;
+
+/// Tower layer to capture request metrics, like `MetricsMiddleware` does for Actix Web.
+///
+/// Tower has no notion of routes so requests are labelled as `<unmatched>` by default,
+/// as labelling them with the requested path would create a time series per URL.
+/// Use `MetricsLayer::path_label` to label requests by route instead, such as with
+/// the route pattern recorded by the framework in the request extensions.
+#[derive(Clone)]
+pub struct MetricsLayer {
+    metrics: MetricsCollector,
+    path_label: PathLabel,
+}
+
+impl MetricsLayer {
+    pub fn new(metrics: MetricsCollector) -> MetricsLayer {
+        MetricsLayer {
+            metrics,
+            path_label: Arc::new(|_, _| None),
+        }
+    }
+
+    /// Compute the path label of requests with the given function.
+    ///
+    /// Requests for which the function returns `None` are labelled as `<unmatched>`.
+    pub fn path_label<F>(mut self, label: F) -> MetricsLayer
+    where
+        F: Fn(&Uri, &Extensions) -> Option<String> + Send + Sync + 'static,
+    {
+        self.path_label = Arc::new(label);
+        self
+    }
+}
+
+impl<S> Layer<S> for MetricsLayer {
+    type Service = MetricsService<S>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        MetricsService {
+            metrics: self.metrics.clone(),
+            path_label: Arc::clone(&self.path_label),
+            service,
+        }
+    }
+}
+
+/// Inner service to process requests on behalf of `MetricsLayer`.
+#[derive(Clone)]
+pub struct MetricsService<S> {
+    metrics: MetricsCollector,
+    path_label: PathLabel,
+    service: S,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for MetricsService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+    S::Future: Send + 'static,
+{
+    type Response = Response<ResBody>;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        let metrics = self.metrics.clone();
+        let path = (self.path_label)(req.uri(), req.extensions())
+            .unwrap_or_else(|| PATH_UNMATCHED.to_string());
+        let mut timer = DurationTimer::with_labels(&metrics.duration, req.method().as_str(), path);
+        let response = self.service.call(req);
+        Box::pin(async move {
+            let response = match response.await {
+                Ok(response) => response,
+                Err(error) => {
+                    timer.observe(STATUS_ERROR);
+                    return Err(error);
+                }
+            };
+            let status = response.status();
+            timer.observe(status.as_str());
+            if status.is_server_error() || status.is_client_error() {
+                metrics
+                    .errors
+                    .with_label_values(&[&timer.method, &timer.path, status.as_str()])
+                    .inc();
+            }
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use http::Request;
+    use http::Response;
+    use http::StatusCode;
+    use tower::service_fn;
+    use tower::Layer;
+    use tower::ServiceExt;
+
+    use super::MetricsLayer;
+    use crate::MetricsCollector;
+
+    async fn handler(request: Request<()>) -> Result<Response<()>, Infallible> {
+        let status = match request.uri().path() {
+            "/fail" => StatusCode::INTERNAL_SERVER_ERROR,
+            _ => StatusCode::OK,
+        };
+        Ok(Response::builder().status(status).body(()).unwrap())
+    }
+
+    #[actix_rt::test]
+    async fn metric_sample_per_request() {
+        let metrics = MetricsCollector::new("test");
+        let layer =
+            MetricsLayer::new(metrics.clone()).path_label(|uri, _| Some(uri.path().to_string()));
+        for path in ["/ok", "/fail"] {
+            let request = Request::get(path).body(()).unwrap();
+            layer
+                .layer(service_fn(handler))
+                .oneshot(request)
+                .await
+                .unwrap();
+        }
+
+        let ok = metrics.duration.with_label_values(&["GET", "/ok", "200"]);
+        assert_eq!(ok.get_sample_count(), 1);
+        let fail = metrics.duration.with_label_values(&["GET", "/fail", "500"]);
+        assert_eq!(fail.get_sample_count(), 1);
+        let errors = metrics.errors.with_label_values(&["GET", "/fail", "500"]);
+        assert_eq!(errors.get() as u64, 1);
+    }
+
+    #[actix_rt::test]
+    async fn custom_path_label() {
+        let metrics = MetricsCollector::new("test");
+        let layer = MetricsLayer::new(metrics.clone()).path_label(|uri, _| {
+            uri.path()
+                .starts_with("/actions/")
+                .then(|| "/actions/{id}".to_string())
+        });
+        for path in ["/actions/1", "/actions/2", "/other"] {
+            let request = Request::get(path).body(()).unwrap();
+            layer
+                .layer(service_fn(handler))
+                .oneshot(request)
+                .await
+                .unwrap();
+        }
+
+        let actions = metrics
+            .duration
+            .with_label_values(&["GET", "/actions/{id}", "200"]);
+        assert_eq!(actions.get_sample_count(), 2);
+        let other = metrics
+            .duration
+            .with_label_values(&["GET", "<unmatched>", "200"]);
+        assert_eq!(other.get_sample_count(), 1);
+    }
+
+    #[actix_rt::test]
+    async fn unmatched_path_by_default() {
+        let metrics = MetricsCollector::new("test");
+        let layer = MetricsLayer::new(metrics.clone());
+        for path in ["/actions/1", "/actions/2"] {
+            let request = Request::get(path).body(()).unwrap();
+            layer
+                .layer(service_fn(handler))
+                .oneshot(request)
+                .await
+                .unwrap();
+        }
+
+        let unmatched = metrics
+            .duration
+            .with_label_values(&["GET", "<unmatched>", "200"]);
+        assert_eq!(unmatched.get_sample_count(), 2);
+    }
+}
@@ -0,0 +1,21 @@
+//! Tower layers mirroring the Actix Web observability middlewares.
+//!
+//! These are intended for services built on `tower` (such as `axum` apps) that need
+//! the same logs, metrics and traces as the Actix Web services using this crate.
+use std::future::Future;
+use std::pin::Pin;
+
+mod logging;
+mod metrics;
+mod tracing;
+
+pub use self::logging::LoggingLayer;
+pub use self::logging::LoggingService;
+pub use self::metrics::MetricsLayer;
+pub use self::metrics::MetricsService;
+pub use self::tracing::RequestSpan;
+pub use self::tracing::TracingLayer;
+pub use self::tracing::TracingService;
+
+/// Type alias for futures returned by tower services.
+type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
@@ -0,0 +1,329 @@
+use std::borrow::Cow;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::task::Context;
+use std::task::Poll;
+
+use actix_web::http::header::HeaderMap as ActixHeaderMap;
+use actix_web::http::header::HeaderName as ActixHeaderName;
+use actix_web::http::header::HeaderValue as ActixHeaderValue;
+use http::header::HeaderName;
+use http::header::HeaderValue;
+use http::HeaderMap;
+use http::Request;
+use http::Response;
+use opentracingrust::Span;
+use opentracingrust::Tracer;
+use slog::error;
+use slog::Logger;
+use tower::Layer;
+use tower::Service;
+
+use super::BoxFuture;
+use crate::span_logger;
+use crate::HeaderFilter;
+use crate::HeadersCarrier;
+use crate::RequestLogger;
+use crate::TagLimits;
+
+/// Request extension to access the span created by `TracingLayer`.
+///
+/// The span is finished once the response is returned so it is only available
+/// to handlers while the request is processed.
+#[derive(Clone)]
+pub struct RequestSpan(Arc<Mutex<Option<Span>>>);
+
+impl RequestSpan {
+    /// Access the request's tracing span.
+    pub fn with_span<B, R>(&self, block: B) -> R
+    where
+        B: FnOnce(Option<&mut Span>) -> R,
+    {
+        let mut span = self.0.lock().expect("RequestSpan lock poisoned");
+        block(span.as_mut())
+    }
+
+    /// Take the span out of the extension so it can be finished.
+    fn take(&self) -> Option<Span> {
+        self.0.lock().expect("RequestSpan lock poisoned").take()
+    }
+}
+
+/// Tower layer to inject an `opentracingrust::Span` on each request.
+///
+/// Behaves like `TracingMiddleware` does for Actix Web: trace contexts are extracted
+/// from request headers and injected into response headers with `HeadersCarrier`.
+/// Handlers can access the span with the `RequestSpan` request extension
+/// and a `Logger` enriched with the span's trace IDs with the `RequestLogger` extension.
+/// Request attributes tagged onto spans are limited in size as `TracingMiddleware` does.
+#[derive(Clone)]
+pub struct TracingLayer {
+    filter: HeaderFilter,
+    logger: Logger,
+    name: Option<String>,
+    tag_limits: TagLimits,
+    tracer: Arc<Tracer>,
+}
+
+impl TracingLayer {
+    /// Inject spans using the request path as the name.
+    pub fn new(logger: Logger, tracer: Arc<Tracer>) -> TracingLayer {
+        TracingLayer {
+            filter: HeaderFilter::default(),
+            logger,
+            name: None,
+            tag_limits: TagLimits::default(),
+            tracer,
+        }
+    }
+
+    /// Inject spans using the given name.
+    pub fn with_name<S>(logger: Logger, tracer: Arc<Tracer>, name: S) -> TracingLayer
+    where
+        S: Into<String>,
+    {
+        TracingLayer {
+            filter: HeaderFilter::default(),
+            logger,
+            name: Some(name.into()),
+            tag_limits: TagLimits::default(),
+            tracer,
+        }
+    }
+
+    /// Limit the headers used to extract and inject trace contexts.
+    pub fn header_filter(mut self, filter: HeaderFilter) -> TracingLayer {
+        self.filter = filter;
+        self
+    }
+
+    /// Limit the size of request attributes tagged onto spans.
+    ///
+    /// By default values are truncated after 1024 bytes and skipped after 64 KiB.
+    pub fn tag_limits(mut self, limits: TagLimits) -> TracingLayer {
+        self.tag_limits = limits;
+        self
+    }
+}
+
+impl<S> Layer<S> for TracingLayer {
+    type Service = TracingService<S>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        TracingService {
+            filter: self.filter.clone(),
+            logger: self.logger.clone(),
+            name: self.name.clone(),
+            service,
+            tag_limits: self.tag_limits,
+            tracer: Arc::clone(&self.tracer),
+        }
+    }
+}
+
+/// Inner service to process requests on behalf of `TracingLayer`.
+#[derive(Clone)]
+pub struct TracingService<S> {
+    filter: HeaderFilter,
+    logger: Logger,
+    name: Option<String>,
+    service: S,
+    tag_limits: TagLimits,
+    tracer: Arc<Tracer>,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for TracingService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+    S::Future: Send + 'static,
+{
+    type Response = Response<ResBody>;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<ReqBody>) -> Self::Future {
+        let logger = self.logger.clone();
+        let limits = self.tag_limits;
+        let name = match self.name.as_ref() {
+            None => limits.truncate(req.uri().path()),
+            Some(name) => Cow::Borrowed(name.as_str()),
+        };
+        let mut span = self.tracer.span(&name);
+
+        // Extend the span with a parent and some request attributes.
+        let mut headers = to_actix_headers(req.headers());
+        match HeadersCarrier::extract_filtered(&mut headers, &self.tracer, self.filter.clone()) {
+            Ok(Some(context)) => span.child_of(context),
+            Ok(None) => (),
+            Err(error) => {
+                let error = anyhow::anyhow!(error);
+                sentry::integrations::anyhow::capture_anyhow(&error);
+                error!(
+                    logger,
+                    "Unable to extract trace context from request headers";
+                    "error" => %error,
+                );
+            }
+        };
+        span.tag("http.route.method", req.method().as_str());
+        limits.tag(&mut span, "http.route.uri", &req.uri().to_string());
+
+        // Send the request and handle the span on response.
+        let filter = self.filter.clone();
+        let tracer = Arc::clone(&self.tracer);
+        let request_logger = span_logger(&self.logger, &span, &self.tracer);
+        req.extensions_mut()
+            .insert(RequestLogger::new(request_logger));
+        let request_span = RequestSpan(Arc::new(Mutex::new(Some(span))));
+        req.extensions_mut().insert(request_span.clone());
+        let response = self.service.call(req);
+        Box::pin(async move {
+            let mut response = response.await?;
+            if let Some(span) = request_span.take() {
+                let mut headers = ActixHeaderMap::new();
+                let result =
+                    HeadersCarrier::inject_filtered(span.context(), &mut headers, &tracer, filter);
+                match result {
+                    Ok(()) => copy_actix_headers(&headers, response.headers_mut()),
+                    Err(error) => {
+                        let error = anyhow::anyhow!(error);
+                        sentry::integrations::anyhow::capture_anyhow(&error);
+                        error!(
+                            logger,
+                            "Failed to inject trace context into response headers";
+                            "error" => %error,
+                        );
+                    }
+                }
+
+                if let Err(error) = span.finish() {
+                    let error = anyhow::anyhow!(error.to_string());
+                    sentry::integrations::anyhow::capture_anyhow(&error);
+                    error!(
+                        logger,
+                        "Failed to finish request tracing span";
+                        "error" => %error,
+                    );
+                }
+            }
+            Ok(response)
+        })
+    }
+}
+
+/// Copy `http` headers into an Actix Web `HeaderMap` for use with `HeadersCarrier`.
+fn to_actix_headers(headers: &HeaderMap) -> ActixHeaderMap {
+    let mut actix = ActixHeaderMap::new();
+    for (name, value) in headers {
+        let name = ActixHeaderName::from_bytes(name.as_str().as_bytes());
+        let value = ActixHeaderValue::from_bytes(value.as_bytes());
+        if let (Ok(name), Ok(value)) = (name, value) {
+            actix.append(name, value);
+        }
+    }
+    actix
+}
+
+/// Copy headers set by `HeadersCarrier` into `http` headers.
+fn copy_actix_headers(actix: &ActixHeaderMap, headers: &mut HeaderMap) {
+    for (name, value) in actix.iter() {
+        let name = HeaderName::from_bytes(name.as_str().as_bytes());
+        let value = HeaderValue::from_bytes(value.as_bytes());
+        if let (Ok(name), Ok(value)) = (name, value) {
+            headers.insert(name, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+    use std::sync::Arc;
+
+    use http::Request;
+    use http::Response;
+    use opentracingrust::tracers::NoopTracer;
+    use opentracingrust::TagValue;
+    use opentracingrust_zipkin::ZipkinTracer;
+    use replicante_logging::buffer_logger;
+    use slog::info;
+    use slog::o;
+    use slog::Discard;
+    use slog::Logger;
+    use tower::service_fn;
+    use tower::Layer;
+    use tower::ServiceExt;
+
+    use super::RequestSpan;
+    use super::TracingLayer;
+    use crate::RequestLogger;
+    use crate::TagLimits;
+
+    #[actix_rt::test]
+    async fn span_per_request() {
+        let logger = Logger::root(Discard, o!());
+        let (tracer, receiver) = NoopTracer::new();
+        let layer = TracingLayer::new(logger, Arc::new(tracer));
+        let service = layer.layer(service_fn(|request: Request<()>| async move {
+            let span = request.extensions().get::<RequestSpan>().unwrap();
+            span.with_span(|span| span.unwrap().tag("handled", true));
+            Ok::<_, Infallible>(Response::new(()))
+        }));
+        let request = Request::get("/test").body(()).unwrap();
+        let response = service.oneshot(request).await.unwrap();
+        assert!(response.status().is_success());
+
+        let span = receiver.try_recv().expect("request span was not finished");
+        assert_eq!(span.name(), "/test");
+        assert!(span.tags().get("handled").is_some());
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[actix_rt::test]
+    async fn oversized_tags_are_truncated() {
+        let logger = Logger::root(Discard, o!());
+        let (tracer, receiver) = NoopTracer::new();
+        let layer =
+            TracingLayer::new(logger, Arc::new(tracer)).tag_limits(TagLimits::new(16, 4096));
+        let service = layer.layer(service_fn(|_: Request<()>| async move {
+            Ok::<_, Infallible>(Response::new(()))
+        }));
+        let uri = format!("/{}?query={}", "k".repeat(20), "q".repeat(100));
+        let request = Request::get(&uri).body(()).unwrap();
+        service.oneshot(request).await.unwrap();
+
+        let span = receiver.try_recv().expect("request span was not finished");
+        assert_eq!(span.name(), &format!("{}...", &uri[..16]));
+        match span.tags().get("http.route.uri") {
+            Some(TagValue::String(value)) => assert_eq!(value, &format!("{}...", &uri[..16])),
+            other => panic!("unexpected http.route.uri tag: {:?}", other),
+        }
+    }
+
+    #[actix_rt::test]
+    async fn request_logs_carry_trace_id() {
+        let (logger, sink) = buffer_logger();
+        let (tracer, _receiver) = ZipkinTracer::new();
+        let layer = TracingLayer::new(logger, Arc::new(tracer));
+        let service = layer.layer(service_fn(|request: Request<()>| async move {
+            let logger = request.extensions().get::<RequestLogger>().unwrap();
+            info!(logger.logger(), "Handling request");
+            Ok::<_, Infallible>(Response::new(()))
+        }));
+        let request = Request::get("/test").body(()).unwrap();
+        let response = service.oneshot(request).await.unwrap();
+
+        let trace_id = response.headers().get("x-b3-traceid").unwrap();
+        let records = sink.records();
+        let record = records
+            .iter()
+            .find(|record| record.message == "Handling request")
+            .expect("handler did not log");
+        assert_eq!(record.fields["trace_id"], trace_id.to_str().unwrap());
+        assert!(record.fields.contains_key("span_id"));
+    }
+}
@@ -1,24 +1,100 @@
 use std::future::Future;
 use std::pin::Pin;
 
+mod body;
+mod capture;
 mod config;
+mod content_type;
 mod descriptor;
+mod fallback;
+mod forwarded;
+mod health;
+#[cfg(feature = "with_tower")]
+mod layers;
 mod logging;
 mod metrics;
+mod panic;
+mod query;
+mod rate_limit;
+mod request_id;
+mod routes;
+mod server;
+mod stack;
+#[cfg(any(test, feature = "with_test_support"))]
+mod test_support;
 mod tracing;
+mod version;
 
 pub mod errors;
 
+pub use self::body::ByteCounter;
+pub use self::body::CountingBody;
+pub use self::capture::with_request_sentry_scope;
+pub use self::capture::SentryMiddleware;
 pub use self::config::AppConfig;
 pub use self::config::AppConfigContext;
+pub use self::content_type::RequireJsonMiddleware;
 pub use self::descriptor::APIFlags;
+pub use self::descriptor::IntrospectionRoot;
 pub use self::descriptor::RootDescriptor;
+pub use self::descriptor::RootsIndex;
+pub use self::fallback::MethodNotAllowedHandler;
+pub use self::fallback::NotFoundHandler;
+pub use self::forwarded::RequestOrigin;
+pub use self::forwarded::TrustedProxies;
+pub use self::health::HealthChecks;
+pub use self::health::HealthHandler;
+#[cfg(feature = "with_tower")]
+pub use self::layers::LoggingLayer;
+#[cfg(feature = "with_tower")]
+pub use self::layers::LoggingService;
+#[cfg(feature = "with_tower")]
+pub use self::layers::MetricsLayer;
+#[cfg(feature = "with_tower")]
+pub use self::layers::MetricsService;
+#[cfg(feature = "with_tower")]
+pub use self::layers::RequestSpan;
+#[cfg(feature = "with_tower")]
+pub use self::layers::TracingLayer;
+#[cfg(feature = "with_tower")]
+pub use self::layers::TracingService;
 pub use self::logging::LoggingMiddleware;
+pub use self::metrics::gather_registries;
+pub use self::metrics::registry_with_labels;
 pub use self::metrics::MetricsCollector;
+pub use self::metrics::MetricsCollectorBuilder;
 pub use self::metrics::MetricsExporter;
 pub use self::metrics::MetricsMiddleware;
+pub use self::panic::CatchPanicMiddleware;
+pub use self::query::query_params;
+pub use self::query::QueryMap;
+pub use self::rate_limit::RateLimitMiddleware;
+pub use self::request_id::request_id;
+pub use self::request_id::RequestId;
+pub use self::request_id::RequestIdMiddleware;
+#[doc(hidden)]
+pub use self::routes::has_duplicate_route_names;
+pub use self::server::run_with_upkeep;
+pub use self::stack::StandardMiddleware;
+#[cfg(any(test, feature = "with_test_support"))]
+pub use self::test_support::capture_request;
+#[cfg(any(test, feature = "with_test_support"))]
+pub use self::test_support::CapturedRequest;
+#[cfg(any(test, feature = "with_test_support"))]
+pub use self::test_support::TEST_METRICS_PREFIX;
+pub use self::tracing::request_logger;
+pub use self::tracing::set_baggage;
+pub use self::tracing::span_logger;
 pub use self::tracing::with_request_span;
+pub use self::tracing::CarrierFormat;
+pub use self::tracing::HeaderFilter;
+pub use self::tracing::HeadersCarrier;
+pub use self::tracing::RequestLogger;
+pub use self::tracing::TagLimits;
 pub use self::tracing::TracingMiddleware;
+pub use self::version::api_version;
+pub use self::version::ApiVersion;
+pub use self::version::ApiVersionMiddleware;
 
 /// Type alias for futures returned by middleware.
 // (from futures_util but I did not want the whole crate)
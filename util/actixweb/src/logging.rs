@@ -7,17 +7,41 @@ use actix_web::dev::ServiceRequest;
 use actix_web::dev::ServiceResponse;
 use actix_web::dev::Transform;
 use actix_web::Error;
+use actix_web::HttpMessage;
 use slog::info;
 use slog::Logger;
 
+use crate::ApiVersion;
+use crate::RequestId;
+use crate::TrustedProxies;
+
 /// Actix Web middleware to log requests.
 pub struct LoggingMiddleware {
     logger: Logger,
+    proxies: TrustedProxies,
 }
 
 impl LoggingMiddleware {
     pub fn new(logger: Logger) -> LoggingMiddleware {
-        LoggingMiddleware { logger }
+        LoggingMiddleware {
+            logger,
+            proxies: TrustedProxies::default(),
+        }
+    }
+
+    /// Trust the given proxies to report the original client address and scheme.
+    pub fn trusted_proxies(mut self, proxies: TrustedProxies) -> LoggingMiddleware {
+        self.proxies = proxies;
+        self
+    }
+
+    /// Wrap a service without going through `Transform::new_transform`.
+    pub(crate) fn wrap_service<S>(&self, service: S) -> MiddlewareService<S> {
+        MiddlewareService {
+            logger: self.logger.clone(),
+            proxies: self.proxies.clone(),
+            service,
+        }
     }
 }
 
@@ -36,16 +60,14 @@ where
     type Future = Ready<Result<Self::Transform, Self::InitError>>;
 
     fn new_transform(&self, service: S) -> Self::Future {
-        ready(Ok(MiddlewareService {
-            logger: self.logger.clone(),
-            service,
-        }))
+        ready(Ok(self.wrap_service(service)))
     }
 }
 
 /// Inner middleware to process requests on behalf of `LoggingMiddleware`.
 pub struct MiddlewareService<S> {
     logger: Logger,
+    proxies: TrustedProxies,
     service: S,
 }
 
@@ -63,19 +85,36 @@ where
 
     fn call(&self, req: ServiceRequest) -> Self::Future {
         let logger = self.logger.clone();
+        let proxies = self.proxies.clone();
         let response = self.service.call(req);
         Box::pin(async move {
             let response = response.await?;
             let method = response.request().method();
             let path = response.request().path();
             let status = response.response().status();
+            let origin = proxies.origin(response.request());
+            let client = origin.client.map(|client| client.to_string());
+            let request_id = response
+                .request()
+                .extensions()
+                .get::<RequestId>()
+                .map(ToString::to_string);
+            let api_version = response
+                .request()
+                .extensions()
+                .get::<ApiVersion>()
+                .map(ToString::to_string);
             let error = status.is_server_error() || status.is_client_error();
             info!(
                 logger,
                 "Request handled";
                 "success" => !error,
+                "api_version" => api_version,
+                "client" => client,
                 "method" => %method,
                 "path" => path,
+                "request_id" => request_id,
+                "scheme" => origin.scheme,
                 "status" => %status,
             );
             Ok(response)
@@ -1,5 +1,9 @@
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::future::ready;
 use std::future::Ready;
+use std::io::Write;
 use std::time::Duration;
 use std::time::Instant;
 
@@ -8,23 +12,35 @@ use actix_web::dev::Service;
 use actix_web::dev::ServiceRequest;
 use actix_web::dev::ServiceResponse;
 use actix_web::dev::Transform;
+use actix_web::http::header::ACCEPT_ENCODING;
+use actix_web::http::header::CONTENT_ENCODING;
+use actix_web::http::header::CONTENT_TYPE;
 use actix_web::Error;
+use actix_web::HttpRequest;
 use actix_web::HttpResponse;
+use anyhow::Result;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use prometheus::core::Collector;
+use prometheus::proto::MetricFamily;
 use prometheus::CounterVec;
 use prometheus::Encoder;
 use prometheus::HistogramOpts;
 use prometheus::HistogramVec;
+use prometheus::IntGauge;
 use prometheus::Opts;
 use prometheus::Registry;
 use prometheus::TextEncoder;
-use slog::debug;
+use slog::warn;
 use slog::Logger;
 
+use crate::errors::MetricsError;
+
 /// Set of metrics tracked by the `MetricsMiddleware` for actix web.
 #[derive(Clone)]
 pub struct MetricsCollector {
-    duration: HistogramVec,
-    errors: CounterVec,
+    pub(crate) duration: HistogramVec,
+    pub(crate) errors: CounterVec,
 }
 
 impl MetricsCollector {
@@ -33,66 +49,304 @@ impl MetricsCollector {
     where
         S: AsRef<str>,
     {
-        let prefix = prefix.as_ref();
+        MetricsCollector::builder(prefix).build()
+    }
+
+    /// Create a new set of metrics with the given prefix and constant labels.
+    ///
+    /// Constant labels distinguish metrics from multiple components sharing the same registry.
+    ///
+    /// # Panics
+    /// Label names used to partition requests (`method`, `path`, `status`) are reserved.
+    pub fn with_const_labels<S, I, N, V>(prefix: S, labels: I) -> MetricsCollector
+    where
+        S: AsRef<str>,
+        I: IntoIterator<Item = (N, V)>,
+        N: Into<String>,
+        V: Into<String>,
+    {
+        labels
+            .into_iter()
+            .fold(
+                MetricsCollector::builder(prefix),
+                |builder, (name, value)| builder.const_label(name, value),
+            )
+            .build()
+    }
+
+    /// Configure a new set of metrics with the given prefix and additional options.
+    pub fn builder<S>(prefix: S) -> MetricsCollectorBuilder
+    where
+        S: AsRef<str>,
+    {
+        MetricsCollectorBuilder {
+            const_labels: HashMap::new(),
+            prefix: prefix.as_ref().to_string(),
+        }
+    }
+
+    /// Register this set of metrics with the registry.
+    ///
+    /// Registration is attempted for all metrics, even if some of them fail,
+    /// so that as many metrics as possible are exported.
+    /// Failures are logged and the first error encountered is returned.
+    ///
+    /// A failed registration usually means a different collector with the same name
+    /// is already registered: the middleware will keep recording requests but
+    /// the values will NOT be exported by the registry.
+    pub fn register(&self, logger: &Logger, registry: &Registry) -> Result<()> {
+        let mut result = Ok(());
+        let metrics: Vec<Box<dyn Collector>> = vec![
+            Box::new(self.duration.clone()),
+            Box::new(self.errors.clone()),
+        ];
+        for metric in metrics {
+            let name = metric
+                .desc()
+                .first()
+                .map(|desc| desc.fq_name.clone())
+                .unwrap_or_default();
+            if let Err(error) = registry.register(metric) {
+                warn!(
+                    logger,
+                    "Failed to register MetricsMiddleware metric";
+                    "metric" => &name,
+                    "error" => ?error,
+                );
+                if result.is_ok() {
+                    result = Err(anyhow::Error::new(error).context(MetricsError::register(name)));
+                }
+            }
+        }
+        result
+    }
+}
+
+/// Label names used by `MetricsCollector` metrics to partition requests.
+const REQUEST_LABELS: [&str; 3] = ["method", "path", "status"];
+
+/// Builder for `MetricsCollector`s with additional options.
+pub struct MetricsCollectorBuilder {
+    const_labels: HashMap<String, String>,
+    prefix: String,
+}
+
+impl MetricsCollectorBuilder {
+    /// Create the configured set of metrics.
+    pub fn build(self) -> MetricsCollector {
         let duration = HistogramVec::new(
             HistogramOpts::new(
-                format!("{}_endpoint_duration", prefix).as_str(),
+                format!("{}_endpoint_duration", self.prefix).as_str(),
                 "Duration (in seconds) of HTTP endpoints",
-            ),
-            &["method", "path", "status"],
+            )
+            .const_labels(self.const_labels.clone()),
+            &REQUEST_LABELS,
         )
         .expect("unable to configure API duration histogram");
         let errors = CounterVec::new(
             Opts::new(
-                format!("{}_endpoint_errors", prefix).as_str(),
+                format!("{}_endpoint_errors", self.prefix).as_str(),
                 "Number of errors encountered while handling requests",
-            ),
-            &["method", "path", "status"],
+            )
+            .const_labels(self.const_labels),
+            &REQUEST_LABELS,
         )
         .expect("unable to configure API errors counter");
         MetricsCollector { duration, errors }
     }
 
-    /// Register this set of metrics with the registry.
-    pub fn register(&self, logger: &Logger, registry: &Registry) {
-        if let Err(error) = registry.register(Box::new(self.duration.clone())) {
-            debug!(logger, "Failed to register MetricsMiddleware::duration"; "error" => ?error);
-        }
-        if let Err(error) = registry.register(Box::new(self.errors.clone())) {
-            debug!(logger, "Failed to register MetricsMiddleware::errors"; "error" => ?error);
+    /// Add a label with a constant value to all metrics.
+    ///
+    /// Useful to distinguish metrics from multiple components sharing the same registry.
+    ///
+    /// # Panics
+    /// Label names used to partition requests (`method`, `path`, `status`) are reserved.
+    pub fn const_label<N, V>(mut self, name: N, value: V) -> MetricsCollectorBuilder
+    where
+        N: Into<String>,
+        V: Into<String>,
+    {
+        let name = name.into();
+        if REQUEST_LABELS.contains(&name.as_str()) {
+            panic!("constant label name '{}' is reserved", name);
         }
+        self.const_labels.insert(name, value.into());
+        self
     }
 }
 
 /// ActixWeb `Responder` to export prometheus metrics.
+///
+/// The size of each response (before compression) is tracked in the
+/// `metrics_exporter_response_size_bytes` gauge, exported once registered
+/// with `MetricsExporter::register_metrics`.
+/// The gauge is also used to pre-allocate the buffer for the next response to reduce
+/// allocations when large registries are scraped frequently.
+///
+/// Responses are always sized so clients receive a `Content-Length` header.
+/// Use `MetricsExporter::gzip` to compress responses for clients that accept it.
 #[derive(Clone)]
 pub struct MetricsExporter {
-    registry: Registry,
+    gzip: bool,
+    registries: Vec<Registry>,
+    size: IntGauge,
 }
 
 impl MetricsExporter {
+    /// Export metrics from the given registry.
     pub fn with_registry(registry: Registry) -> MetricsExporter {
-        MetricsExporter { registry }
+        MetricsExporter::with_registries(vec![registry])
+    }
+
+    /// Export metrics from all the given registries in a single response.
+    ///
+    /// Metrics are merged with `gather_registries`.
+    pub fn with_registries(registries: Vec<Registry>) -> MetricsExporter {
+        let size = IntGauge::new(
+            "metrics_exporter_response_size_bytes",
+            "Size (in bytes) of the last metrics exposition response",
+        )
+        .expect("unable to configure metrics exporter size gauge");
+        MetricsExporter {
+            gzip: false,
+            registries,
+            size,
+        }
+    }
+
+    /// Compress responses with gzip when requests accept it (disabled by default).
+    pub fn gzip(mut self, enabled: bool) -> MetricsExporter {
+        self.gzip = enabled;
+        self
+    }
+
+    /// Register the response size gauge with the registry.
+    ///
+    /// Registration fails if the registry already has a gauge with the same name,
+    /// for example because another exporter registered with it.
+    pub fn register_metrics(&self, registry: &Registry) -> Result<()> {
+        registry
+            .register(Box::new(self.size.clone()))
+            .map_err(|error| {
+                anyhow::Error::new(error).context(MetricsError::register(
+                    "metrics_exporter_response_size_bytes",
+                ))
+            })
     }
 }
 
-impl actix_web::Handler<()> for MetricsExporter {
+impl actix_web::Handler<(HttpRequest,)> for MetricsExporter {
     type Output = HttpResponse;
     type Future = Ready<Self::Output>;
 
-    fn call(&self, _: ()) -> Self::Future {
-        let mut buffer = Vec::new();
+    fn call(&self, (request,): (HttpRequest,)) -> Self::Future {
+        let capacity = usize::try_from(self.size.get()).unwrap_or(0);
+        let mut buffer = Vec::with_capacity(capacity);
         let encoder = TextEncoder::new();
-        let metric_families = self.registry.gather();
+        let metric_families = gather_registries(&self.registries);
         encoder.encode(&metric_families, &mut buffer).unwrap();
-        let response = HttpResponse::Ok()
-            .append_header((actix_web::http::header::CONTENT_TYPE, encoder.format_type()))
-            .body(buffer);
-        ready(response)
+        self.size
+            .set(i64::try_from(buffer.len()).unwrap_or(i64::MAX));
+        let mut response = HttpResponse::Ok();
+        response.append_header((CONTENT_TYPE, encoder.format_type()));
+        if self.gzip && accepts_gzip(&request) {
+            let mut gzip =
+                GzEncoder::new(Vec::with_capacity(buffer.len() / 4), Compression::fast());
+            let compressed = gzip.write_all(&buffer).and_then(|_| gzip.finish());
+            // Fall back to uncompressed responses in the unlikely event compression fails.
+            if let Ok(compressed) = compressed {
+                buffer = compressed;
+                response.append_header((CONTENT_ENCODING, "gzip"));
+            }
+        }
+        ready(response.body(buffer))
+    }
+}
+
+/// Check if the request accepts gzip encoded responses.
+fn accepts_gzip(request: &HttpRequest) -> bool {
+    let accepted = request
+        .headers()
+        .get_all(ACCEPT_ENCODING)
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','));
+    for encoding in accepted {
+        let mut parts = encoding.split(';').map(str::trim);
+        let name = parts.next().unwrap_or_default();
+        if !name.eq_ignore_ascii_case("gzip") {
+            continue;
+        }
+        let disabled = parts.any(|param| {
+            param
+                .strip_prefix("q=")
+                .and_then(|q| q.parse::<f32>().ok())
+                .map(|q| q <= 0.0)
+                .unwrap_or(false)
+        });
+        return !disabled;
+    }
+    false
+}
+
+/// Create a [`Registry`] that attaches the given constant labels to every metric it gathers.
+///
+/// Use this to add process-wide dimensions (such as `version`) to all metrics
+/// without passing them to every component that registers metrics.
+/// Metrics registered with the returned registry must not define labels with the same names.
+///
+/// [`Registry`]: prometheus/struct.Registry.html
+pub fn registry_with_labels<I, N, V>(labels: I) -> Result<Registry>
+where
+    I: IntoIterator<Item = (N, V)>,
+    N: Into<String>,
+    V: Into<String>,
+{
+    let labels: HashMap<String, String> = labels
+        .into_iter()
+        .map(|(name, value)| (name.into(), value.into()))
+        .collect();
+    let registry = Registry::new_custom(None, Some(labels))?;
+    Ok(registry)
+}
+
+/// Gather metric families from multiple registries into a single set.
+///
+/// Families with the same name are merged as long as they have the same type.
+/// Metrics with the same name and labels as an already gathered metric are skipped,
+/// as are families with a type that conflicts with an already gathered family.
+pub fn gather_registries(registries: &[Registry]) -> Vec<MetricFamily> {
+    let mut merged: BTreeMap<String, MetricFamily> = BTreeMap::new();
+    for registry in registries {
+        for mut family in registry.gather() {
+            let existing = match merged.get_mut(family.get_name()) {
+                None => {
+                    merged.insert(family.get_name().to_string(), family);
+                    continue;
+                }
+                Some(existing) => existing,
+            };
+            if existing.get_field_type() != family.get_field_type() {
+                continue;
+            }
+            for metric in family.take_metric().into_iter() {
+                let duplicate = existing
+                    .get_metric()
+                    .iter()
+                    .any(|known| known.get_label() == metric.get_label());
+                if !duplicate {
+                    existing.mut_metric().push(metric);
+                }
+            }
+        }
     }
+    merged.into_values().collect()
 }
 
 /// Actix Web middleware to capture request metrics.
+///
+/// Requests are labelled with the pattern of the route they matched (for example
+/// `/actions/{id}`) rather than the requested path to keep the cardinality of metrics low.
+/// Requests that do not match any route are labelled as `<unmatched>`.
 pub struct MetricsMiddleware {
     metrics: MetricsCollector,
 }
@@ -101,6 +355,14 @@ impl MetricsMiddleware {
     pub fn new(metrics: MetricsCollector) -> MetricsMiddleware {
         MetricsMiddleware { metrics }
     }
+
+    /// Wrap a service without going through `Transform::new_transform`.
+    pub(crate) fn wrap_service<S>(&self, service: S) -> MiddlewareService<S> {
+        MiddlewareService {
+            metrics: self.metrics.clone(),
+            service,
+        }
+    }
 }
 
 // `S` - type of the next service
@@ -118,10 +380,7 @@ where
     type Future = Ready<Result<Self::Transform, Self::InitError>>;
 
     fn new_transform(&self, service: S) -> Self::Future {
-        ready(Ok(MiddlewareService {
-            metrics: self.metrics.clone(),
-            service,
-        }))
+        ready(Ok(self.wrap_service(service)))
     }
 }
 
@@ -145,22 +404,23 @@ where
 
     fn call(&self, req: ServiceRequest) -> Self::Future {
         let metrics = self.metrics.clone();
-        let request_start = Instant::now();
+        let mut timer = DurationTimer::new(&metrics.duration, &req);
         let response = self.service.call(req);
         Box::pin(async move {
-            let response = response.await?;
-            let duration = duration_to_seconds(request_start.elapsed());
-            let method = response.request().method().as_str();
-            let path = response.request().path();
+            let response = match response.await {
+                Ok(response) => response,
+                Err(error) => {
+                    let status = error.as_response_error().status_code();
+                    timer.observe(status.as_str());
+                    return Err(error);
+                }
+            };
             let status = response.response().status();
-            metrics
-                .duration
-                .with_label_values(&[method, path, status.as_str()])
-                .observe(duration);
+            timer.observe(status.as_str());
             if response.response().error().is_some() {
                 metrics
                     .errors
-                    .with_label_values(&[method, path, status.as_str()])
+                    .with_label_values(&[&timer.method, &timer.path, status.as_str()])
                     .inc();
             }
             Ok(response)
@@ -168,6 +428,65 @@ where
     }
 }
 
+/// Path label used for requests that did not match any route.
+pub(crate) const PATH_UNMATCHED: &str = "<unmatched>";
+
+/// Status label used for requests dropped before a response was returned.
+const STATUS_DROPPED: &str = "dropped";
+
+/// Request duration timer that observes the duration on drop if needed.
+///
+/// Request futures can be dropped before they complete (for example when clients disconnect).
+/// To avoid losing samples in these cases the duration is observed when the timer is dropped
+/// unless `DurationTimer::observe` was already called.
+pub(crate) struct DurationTimer {
+    histogram: HistogramVec,
+    pub(crate) method: String,
+    observed: bool,
+    pub(crate) path: String,
+    start: Instant,
+}
+
+impl DurationTimer {
+    fn new(histogram: &HistogramVec, request: &ServiceRequest) -> DurationTimer {
+        let path = request
+            .match_pattern()
+            .unwrap_or_else(|| PATH_UNMATCHED.to_string());
+        DurationTimer::with_labels(histogram, request.method().as_str(), path)
+    }
+
+    /// Start timing a request with the given method and path labels.
+    pub(crate) fn with_labels<S>(histogram: &HistogramVec, method: &str, path: S) -> DurationTimer
+    where
+        S: Into<String>,
+    {
+        DurationTimer {
+            histogram: histogram.clone(),
+            method: method.to_string(),
+            observed: false,
+            path: path.into(),
+            start: Instant::now(),
+        }
+    }
+
+    /// Observe the request duration with the given status label.
+    pub(crate) fn observe(&mut self, status: &str) {
+        let duration = duration_to_seconds(self.start.elapsed());
+        self.histogram
+            .with_label_values(&[&self.method, &self.path, status])
+            .observe(duration);
+        self.observed = true;
+    }
+}
+
+impl Drop for DurationTimer {
+    fn drop(&mut self) {
+        if !self.observed {
+            self.observe(STATUS_DROPPED);
+        }
+    }
+}
+
 /// Convert a [request] duration to seconds.
 fn duration_to_seconds(duration: Duration) -> f64 {
     let nanos = f64::from(duration.subsec_nanos()) / 1e9;
@@ -176,24 +495,308 @@ fn duration_to_seconds(duration: Duration) -> f64 {
 
 #[cfg(test)]
 mod tests {
+    use std::future::pending;
+    use std::io::Read;
+
+    use actix_web::dev::Service;
+    use actix_web::http::header::ACCEPT_ENCODING;
+    use actix_web::http::header::CONTENT_ENCODING;
+    use actix_web::http::header::CONTENT_TYPE;
     use actix_web::http::StatusCode;
     use actix_web::test::call_service;
     use actix_web::test::init_service;
+    use actix_web::test::read_body;
     use actix_web::test::TestRequest;
     use actix_web::web;
     use actix_web::App;
+    use actix_web::HttpRequest;
+    use actix_web::HttpResponse;
+    use flate2::read::GzDecoder;
+    use prometheus::core::Collector;
+    use prometheus::IntCounter;
     use prometheus::Registry;
+    use slog::o;
+    use slog::Discard;
+    use slog::Logger;
 
+    use super::gather_registries;
+    use super::registry_with_labels;
+    use super::MetricsCollector;
     use super::MetricsExporter;
+    use super::MetricsMiddleware;
+
+    #[test]
+    fn registry_with_labels_tags_all_metrics() {
+        let registry = registry_with_labels(vec![("version", "1.2.3")]).unwrap();
+        let counter = IntCounter::new("test_counter", "test counter").unwrap();
+        registry.register(Box::new(counter.clone())).unwrap();
+        let logger = Logger::root(Discard, o!());
+        MetricsCollector::new("test")
+            .register(&logger, &registry)
+            .unwrap();
+        counter.inc();
+
+        let families = registry.gather();
+        assert!(!families.is_empty());
+        for family in families {
+            for metric in family.get_metric() {
+                let version = metric
+                    .get_label()
+                    .iter()
+                    .find(|label| label.get_name() == "version")
+                    .map(|label| label.get_value());
+                assert_eq!(version, Some("1.2.3"), "in {}", family.get_name());
+            }
+        }
+    }
+
+    #[test]
+    fn metrics_collector_const_labels() {
+        let collector = MetricsCollector::builder("test")
+            .const_label("component", "agent")
+            .build();
+        let descs = collector
+            .duration
+            .desc()
+            .into_iter()
+            .chain(collector.errors.desc());
+        for desc in descs {
+            let labels: Vec<(&str, &str)> = desc
+                .const_label_pairs
+                .iter()
+                .map(|pair| (pair.get_name(), pair.get_value()))
+                .collect();
+            assert_eq!(labels, vec![("component", "agent")]);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "constant label name 'status' is reserved")]
+    fn metrics_collector_const_labels_reserved() {
+        MetricsCollector::builder("test").const_label("status", "200");
+    }
+
+    #[test]
+    fn metrics_collector_with_const_labels() {
+        let logger = Logger::root(Discard, o!());
+        let registry = Registry::new();
+        let agent = MetricsCollector::with_const_labels("test", vec![("component", "agent")]);
+        let core = MetricsCollector::with_const_labels("test", vec![("component", "core")]);
+        agent
+            .register(&logger, &registry)
+            .expect("agent metrics to be registered");
+        core.register(&logger, &registry)
+            .expect("core metrics to be registered");
+        agent
+            .duration
+            .with_label_values(&["GET", "/", "200"])
+            .observe(1.0);
+        core.duration
+            .with_label_values(&["GET", "/", "200"])
+            .observe(1.0);
+
+        let families = registry.gather();
+        let family = families
+            .iter()
+            .find(|family| family.get_name() == "test_endpoint_duration")
+            .expect("duration histogram to be gathered");
+        let mut components: Vec<&str> = family
+            .get_metric()
+            .iter()
+            .flat_map(|metric| metric.get_label())
+            .filter(|label| label.get_name() == "component")
+            .map(|label| label.get_value())
+            .collect();
+        components.sort_unstable();
+        assert_eq!(components, vec!["agent", "core"]);
+    }
+
+    #[test]
+    fn metrics_collector_register_twice_fails() {
+        let logger = Logger::root(Discard, o!());
+        let registry = Registry::new();
+        let collector = MetricsCollector::new("test");
+        collector
+            .register(&logger, &registry)
+            .expect("metrics to be registered");
+        let error = collector
+            .register(&logger, &registry)
+            .expect_err("duplicate registration to fail");
+        assert_eq!(
+            error.to_string(),
+            "unable to register metric 'test_endpoint_duration'"
+        );
+    }
 
     #[actix_rt::test]
     async fn metrics_exporter_returns_200() {
         let registry = Registry::new();
-        let exporter = MetricsExporter::with_registry(registry);
-        let service = web::resource("/").to(exporter);
-        let mut app = init_service(App::new().service(service)).await;
+        let exporter = MetricsExporter::with_registry(registry.clone());
+        exporter.register_metrics(&registry).unwrap();
+        let service = web::resource("/").to(exporter.clone());
+        let app = init_service(App::new().service(service)).await;
         let request = TestRequest::with_uri("https://server:1234/").to_request();
-        let response = call_service(&mut app, request).await;
+        let response = call_service(&app, request).await;
         assert_eq!(response.status(), StatusCode::OK);
+        assert!(exporter.size.get() > 0);
+        let exported = registry
+            .gather()
+            .iter()
+            .any(|family| family.get_name() == "metrics_exporter_response_size_bytes");
+        assert!(exported);
+    }
+
+    #[test]
+    fn metrics_exporter_registration_is_explicit() {
+        let registry = Registry::new();
+        let exporter = MetricsExporter::with_registry(registry.clone());
+        assert!(registry.gather().is_empty());
+
+        exporter.register_metrics(&registry).unwrap();
+        let other = MetricsExporter::with_registry(registry.clone());
+        let error = other
+            .register_metrics(&registry)
+            .expect_err("duplicate registration to fail");
+        assert_eq!(
+            error.to_string(),
+            "unable to register metric 'metrics_exporter_response_size_bytes'"
+        );
+    }
+
+    #[actix_rt::test]
+    async fn metrics_exporter_body() {
+        let registry = Registry::new();
+        let counter = IntCounter::new("test_counter", "Test counter").unwrap();
+        registry.register(Box::new(counter.clone())).unwrap();
+        counter.inc();
+        let exporter = MetricsExporter::with_registry(registry).gzip(true);
+        let service = web::resource("/").to(exporter.clone());
+        let app = init_service(App::new().service(service)).await;
+
+        let request = TestRequest::with_uri("/").to_request();
+        let response = call_service(&app, request).await;
+        let content_type = response.headers().get(CONTENT_TYPE).unwrap();
+        assert_eq!(content_type, "text/plain; version=0.0.4");
+        assert!(response.headers().get(CONTENT_ENCODING).is_none());
+        let body = read_body(response).await;
+        assert_eq!(body.len() as i64, exporter.size.get());
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(body.contains("test_counter 1\n"));
+
+        let request = TestRequest::with_uri("/")
+            .insert_header((ACCEPT_ENCODING, "br;q=1.0, gzip;q=0.8"))
+            .to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(response.headers().get(CONTENT_ENCODING).unwrap(), "gzip");
+        let body = read_body(response).await;
+        let mut decoded = String::new();
+        GzDecoder::new(&body[..])
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert!(decoded.contains("test_counter 1\n"));
+    }
+
+    #[actix_rt::test]
+    async fn metrics_exporter_gzip_refused() {
+        let exporter = MetricsExporter::with_registry(Registry::new()).gzip(true);
+        let service = web::resource("/").to(exporter);
+        let app = init_service(App::new().service(service)).await;
+        let request = TestRequest::with_uri("/")
+            .insert_header((ACCEPT_ENCODING, "gzip;q=0"))
+            .to_request();
+        let response = call_service(&app, request).await;
+        assert!(response.headers().get(CONTENT_ENCODING).is_none());
+    }
+
+    #[test]
+    fn merge_registries() {
+        let one = Registry::new();
+        let two = Registry::new();
+        let shared_one = IntCounter::new("shared", "Shared counter").unwrap();
+        let shared_two = IntCounter::new("shared", "Shared counter").unwrap();
+        let first = IntCounter::new("first", "First counter").unwrap();
+        let second = IntCounter::new("second", "Second counter").unwrap();
+        one.register(Box::new(shared_one)).unwrap();
+        one.register(Box::new(first)).unwrap();
+        two.register(Box::new(shared_two)).unwrap();
+        two.register(Box::new(second)).unwrap();
+
+        let families = gather_registries(&[one, two]);
+        let names: Vec<&str> = families.iter().map(|family| family.get_name()).collect();
+        assert_eq!(names, vec!["first", "second", "shared"]);
+        assert_eq!(families[2].get_metric().len(), 1);
+    }
+
+    #[actix_rt::test]
+    async fn requests_labelled_by_route() {
+        let collector = MetricsCollector::new("test");
+        let middleware = MetricsMiddleware::new(collector.clone());
+        let service = web::resource("/actions/{id}")
+            .to(|req: HttpRequest| async move { req.match_pattern().unwrap() });
+        let app = init_service(App::new().wrap(middleware).service(service)).await;
+        let request = TestRequest::with_uri("/actions/abc").to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = read_body(response).await;
+        assert_eq!(body, "/actions/{id}");
+        let request = TestRequest::with_uri("/unknown").to_request();
+        call_service(&app, request).await;
+
+        let count = collector
+            .duration
+            .with_label_values(&["GET", "/actions/{id}", "200"])
+            .get_sample_count();
+        assert_eq!(count, 1);
+        let count = collector
+            .duration
+            .with_label_values(&["GET", "<unmatched>", "404"])
+            .get_sample_count();
+        assert_eq!(count, 1);
+    }
+
+    #[actix_rt::test]
+    async fn route_parameters_share_series() {
+        let collector = MetricsCollector::new("test");
+        let registry = Registry::new();
+        let logger = Logger::root(Discard, o!());
+        collector.register(&logger, &registry).unwrap();
+        let middleware = MetricsMiddleware::new(collector);
+        let service = web::resource("/shards/{id}").to(HttpResponse::Ok);
+        let app = init_service(App::new().wrap(middleware).service(service)).await;
+        for uri in ["/shards/1", "/shards/2"] {
+            let request = TestRequest::with_uri(uri).to_request();
+            call_service(&app, request).await;
+        }
+
+        let families = registry.gather();
+        let family = families
+            .iter()
+            .find(|family| family.get_name() == "test_endpoint_duration")
+            .unwrap();
+        assert_eq!(family.get_metric().len(), 1);
+        let path = family.get_metric()[0]
+            .get_label()
+            .iter()
+            .find(|label| label.get_name() == "path")
+            .unwrap();
+        assert_eq!(path.get_value(), "/shards/{id}");
+        assert_eq!(family.get_metric()[0].get_histogram().get_sample_count(), 2);
+    }
+
+    #[actix_rt::test]
+    async fn dropped_request_is_observed() {
+        let collector = MetricsCollector::new("test");
+        let middleware = MetricsMiddleware::new(collector.clone());
+        let service = web::resource("/").to(pending::<HttpResponse>);
+        let app = init_service(App::new().wrap(middleware).service(service)).await;
+        let request = TestRequest::with_uri("/").to_request();
+        let response = app.call(request);
+        drop(response);
+
+        let count = collector
+            .duration
+            .with_label_values(&["GET", "/", "dropped"])
+            .get_sample_count();
+        assert_eq!(count, 1);
     }
 }
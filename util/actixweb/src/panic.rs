@@ -0,0 +1,194 @@
+use std::any::Any;
+use std::future::ready;
+use std::future::Future;
+use std::future::Ready;
+use std::panic::catch_unwind;
+use std::panic::AssertUnwindSafe;
+use std::pin::Pin;
+use std::task::Context;
+use std::task::Poll;
+
+use actix_web::dev::forward_ready;
+use actix_web::dev::Service;
+use actix_web::dev::ServiceRequest;
+use actix_web::dev::ServiceResponse;
+use actix_web::dev::Transform;
+use actix_web::Error;
+use slog::error;
+use slog::Logger;
+
+use crate::errors::HandlerPanic;
+
+/// Actix Web middleware to convert panics in request handlers into error responses.
+///
+/// Without this middleware a panicking handler unwinds the worker and clients see
+/// a dropped connection. Caught panics are instead logged, captured to sentry and
+/// returned as a `HandlerPanic` error (a 500 JSON response).
+pub struct CatchPanicMiddleware {
+    logger: Logger,
+}
+
+impl CatchPanicMiddleware {
+    pub fn new(logger: Logger) -> CatchPanicMiddleware {
+        CatchPanicMiddleware { logger }
+    }
+}
+
+// `S` - type of the next service
+// `B` - type of response's body
+impl<S, B> Transform<S, ServiceRequest> for CatchPanicMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = MiddlewareService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(MiddlewareService {
+            logger: self.logger.clone(),
+            service,
+        }))
+    }
+}
+
+/// Inner middleware to process requests on behalf of `CatchPanicMiddleware`.
+pub struct MiddlewareService<S> {
+    logger: Logger,
+    service: S,
+}
+
+impl<S, B> Service<ServiceRequest> for MiddlewareService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = crate::LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let logger = self.logger.clone();
+        let method = req.method().to_string();
+        let path = req.path().to_string();
+        let response = match catch_unwind(AssertUnwindSafe(|| self.service.call(req))) {
+            Ok(response) => CatchUnwind(Box::pin(response)),
+            Err(payload) => {
+                report_panic(&logger, &method, &path, payload);
+                return Box::pin(async { Err(HandlerPanic.into()) });
+            }
+        };
+        Box::pin(async move {
+            match response.await {
+                Ok(response) => response,
+                Err(payload) => {
+                    report_panic(&logger, &method, &path, payload);
+                    Err(HandlerPanic.into())
+                }
+            }
+        })
+    }
+}
+
+/// Future adapter returning panics raised while polling the inner future as errors.
+struct CatchUnwind<F>(Pin<Box<F>>);
+
+impl<F: Future> Future for CatchUnwind<F> {
+    type Output = Result<F::Output, Box<dyn Any + Send>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let inner = self.0.as_mut();
+        match catch_unwind(AssertUnwindSafe(|| inner.poll(cx))) {
+            Ok(Poll::Pending) => Poll::Pending,
+            Ok(Poll::Ready(output)) => Poll::Ready(Ok(output)),
+            Err(payload) => Poll::Ready(Err(payload)),
+        }
+    }
+}
+
+/// Log a caught panic and capture it to sentry.
+fn report_panic(logger: &Logger, method: &str, path: &str, payload: Box<dyn Any + Send>) {
+    let message = payload
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string());
+    sentry::with_scope(
+        |scope| {
+            scope.set_tag("http.method", method);
+            scope.set_tag("http.path", path);
+        },
+        || {
+            let event = format!("{} {} handler panicked: {}", method, path, message);
+            sentry::capture_message(&event, sentry::Level::Error)
+        },
+    );
+    error!(
+        logger,
+        "Request handler panicked";
+        "method" => method,
+        "path" => path,
+        "panic" => &message,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::body::to_bytes;
+    use actix_web::http::StatusCode;
+    use actix_web::test::call_service;
+    use actix_web::test::init_service;
+    use actix_web::test::try_call_service;
+    use actix_web::test::TestRequest;
+    use actix_web::web;
+    use actix_web::App;
+    use actix_web::HttpResponse;
+    use sentry::test::with_captured_events;
+    use serde_json::Value;
+    use slog::o;
+    use slog::Discard;
+    use slog::Logger;
+
+    use super::CatchPanicMiddleware;
+
+    async fn panics() -> HttpResponse {
+        panic!("handler bug");
+    }
+
+    #[test]
+    fn panic_returns_json_error() {
+        let events = with_captured_events(|| {
+            actix_rt::System::new().block_on(async {
+                let logger = Logger::root(Discard, o!());
+                let app = App::new()
+                    .wrap(CatchPanicMiddleware::new(logger))
+                    .route("/ok", web::get().to(HttpResponse::Ok))
+                    .route("/panic", web::get().to(panics));
+                let app = init_service(app).await;
+
+                // Actix Web converts errors into responses before they are sent.
+                let request = TestRequest::get().uri("/panic").to_request();
+                let error = try_call_service(&app, request).await.unwrap_err();
+                let response = error.error_response();
+                assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+                let body = to_bytes(response.into_body()).await.unwrap();
+                let body: Value = serde_json::from_slice(&body).unwrap();
+                assert_eq!(body["variant"], "HandlerPanic");
+
+                let request = TestRequest::get().uri("/ok").to_request();
+                let response = call_service(&app, request).await;
+                assert_eq!(response.status(), StatusCode::OK);
+            });
+        });
+        assert_eq!(events.len(), 1);
+        let message = events[0].message.as_deref().unwrap_or_default();
+        assert_eq!(message, "GET /panic handler panicked: handler bug");
+    }
+}
@@ -0,0 +1,160 @@
+use std::collections::HashMap;
+use std::future::ready;
+use std::future::Ready;
+
+use actix_web::dev::Payload;
+use actix_web::web::Query;
+use actix_web::FromRequest;
+use actix_web::HttpRequest;
+
+use crate::errors::QueryError;
+
+/// Parse the query string of a request into a `QueryMap`.
+pub fn query_params(request: &HttpRequest) -> Result<QueryMap, QueryError> {
+    QueryMap::parse(request.query_string())
+}
+
+/// Query parameters of a request with typed and validated access.
+///
+/// Errors returned by the getters respond with `400 Bad Request` and a JSON body
+/// so handlers can propagate them with `?`.
+/// `QueryMap` can also be used as an extractor in handler arguments:
+///
+/// ```
+/// use actix_web::HttpResponse;
+/// use replicante_util_actixweb::errors::QueryError;
+/// use replicante_util_actixweb::QueryMap;
+///
+/// async fn handler(query: QueryMap) -> Result<HttpResponse, QueryError> {
+///     let limit = query.get_u64("limit")?.unwrap_or(10);
+///     let name = query.require("name")?;
+///     Ok(HttpResponse::Ok().body(format!("{} {}", name, limit)))
+/// }
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct QueryMap {
+    params: HashMap<String, String>,
+}
+
+impl QueryMap {
+    /// Parse a URL encoded query string.
+    ///
+    /// If a parameter is repeated only the last value is retained.
+    pub fn parse(query: &str) -> Result<QueryMap, QueryError> {
+        let params = Query::<HashMap<String, String>>::from_query(query)
+            .map_err(|error| QueryError::Malformed(error.to_string()))?
+            .into_inner();
+        Ok(QueryMap { params })
+    }
+
+    /// Return the raw value of a parameter, if it was provided.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.params.get(name).map(String::as_str)
+    }
+
+    /// Return a boolean parameter, if it was provided.
+    ///
+    /// Accepted values are `true`, `false`, `1` and `0`.
+    pub fn get_bool(&self, name: &str) -> Result<Option<bool>, QueryError> {
+        match self.get(name) {
+            None => Ok(None),
+            Some("true") | Some("1") => Ok(Some(true)),
+            Some("false") | Some("0") => Ok(Some(false)),
+            Some(value) => Err(QueryError::invalid_value(name, value, "a boolean")),
+        }
+    }
+
+    /// Return an unsigned integer parameter, if it was provided.
+    pub fn get_u64(&self, name: &str) -> Result<Option<u64>, QueryError> {
+        self.get(name)
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(|_| QueryError::invalid_value(name, value, "an unsigned integer"))
+            })
+            .transpose()
+    }
+
+    /// Return the raw value of a parameter that must be provided.
+    pub fn require(&self, name: &str) -> Result<&str, QueryError> {
+        self.get(name).ok_or_else(|| QueryError::missing(name))
+    }
+}
+
+impl FromRequest for QueryMap {
+    type Error = QueryError;
+    type Future = Ready<Result<QueryMap, QueryError>>;
+
+    fn from_request(request: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(query_params(request))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::StatusCode;
+    use actix_web::test::call_service;
+    use actix_web::test::init_service;
+    use actix_web::test::read_body;
+    use actix_web::test::read_body_json;
+    use actix_web::test::TestRequest;
+    use actix_web::web;
+    use actix_web::App;
+    use actix_web::HttpResponse;
+    use serde_json::Value;
+
+    use super::QueryMap;
+    use crate::errors::QueryError;
+
+    async fn handler(query: QueryMap) -> Result<HttpResponse, QueryError> {
+        let name = query.require("name")?;
+        let limit = query.get_u64("limit")?.unwrap_or(10);
+        let verbose = query.get_bool("verbose")?.unwrap_or(false);
+        let body = format!("{} {} {}", name, limit, verbose);
+        Ok(HttpResponse::Ok().body(body))
+    }
+
+    macro_rules! app {
+        () => {{
+            let app = App::new().route("/", web::get().to(handler));
+            init_service(app).await
+        }};
+    }
+
+    #[actix_rt::test]
+    async fn invalid_number() {
+        let app = app!();
+        let request = TestRequest::get().uri("/?name=test&limit=ten").to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body: Value = read_body_json(response).await;
+        assert_eq!(body["variant"], "QueryParamInvalid");
+        assert_eq!(
+            body["error"],
+            "invalid value 'ten' for query parameter 'limit', expected an unsigned integer"
+        );
+    }
+
+    #[actix_rt::test]
+    async fn missing_required() {
+        let app = app!();
+        let request = TestRequest::get().uri("/?limit=5").to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body: Value = read_body_json(response).await;
+        assert_eq!(body["variant"], "QueryParamMissing");
+        assert_eq!(body["error"], "missing required query parameter 'name'");
+    }
+
+    #[actix_rt::test]
+    async fn valid_params() {
+        let app = app!();
+        let request = TestRequest::get()
+            .uri("/?name=some%20test&limit=5&verbose=1")
+            .to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = read_body(response).await;
+        assert_eq!(body, "some test 5 true");
+    }
+}
@@ -0,0 +1,283 @@
+use std::collections::HashMap;
+use std::future::ready;
+use std::future::Ready;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+use actix_web::body::EitherBody;
+use actix_web::dev::forward_ready;
+use actix_web::dev::Service;
+use actix_web::dev::ServiceRequest;
+use actix_web::dev::ServiceResponse;
+use actix_web::dev::Transform;
+use actix_web::http::header::HeaderValue;
+use actix_web::http::header::RETRY_AFTER;
+use actix_web::Error;
+
+use crate::errors::api_error_response;
+use crate::errors::error_body;
+use crate::TrustedProxies;
+
+/// Interval between scans for idle buckets to evict.
+const EVICT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Actix Web middleware to limit the rate of requests from each client.
+///
+/// Requests are limited with a token bucket for each client IP address:
+/// buckets hold up to `burst` tokens and refill at `rate` tokens per second.
+/// Requests are rejected with a `429 Too Many Requests` response,
+/// including a `Retry-After` header, when the client's bucket is empty.
+///
+/// Buckets that have been refilled completely are evicted periodically to bound memory.
+/// Requests without a known client address are not limited.
+#[derive(Clone)]
+pub struct RateLimitMiddleware {
+    limiter: Arc<RateLimiter>,
+    proxies: TrustedProxies,
+}
+
+impl RateLimitMiddleware {
+    /// Allow `rate` requests per second with bursts of up to `burst` requests.
+    ///
+    /// # Panics
+    /// If `rate` is not a positive number or `burst` is zero.
+    pub fn new(rate: f64, burst: u32) -> RateLimitMiddleware {
+        assert!(rate > 0.0, "rate limit must be positive");
+        assert!(burst > 0, "rate limit burst must be positive");
+        let limiter = RateLimiter {
+            buckets: Mutex::new(HashMap::new()),
+            burst: f64::from(burst),
+            last_evict: Mutex::new(Instant::now()),
+            rate,
+        };
+        let limiter = Arc::new(limiter);
+        RateLimitMiddleware {
+            limiter,
+            proxies: TrustedProxies::default(),
+        }
+    }
+
+    /// Trust the given proxies to report the original client address.
+    pub fn trusted_proxies(mut self, proxies: TrustedProxies) -> RateLimitMiddleware {
+        self.proxies = proxies;
+        self
+    }
+}
+
+// `S` - type of the next service
+// `B` - type of response's body
+impl<S, B> Transform<S, ServiceRequest> for RateLimitMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = MiddlewareService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(MiddlewareService {
+            limiter: Arc::clone(&self.limiter),
+            proxies: self.proxies.clone(),
+            service,
+        }))
+    }
+}
+
+/// Inner middleware to process requests on behalf of `RateLimitMiddleware`.
+pub struct MiddlewareService<S> {
+    limiter: Arc<RateLimiter>,
+    proxies: TrustedProxies,
+    service: S,
+}
+
+impl<S, B> Service<ServiceRequest> for MiddlewareService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = crate::LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let client = self.proxies.origin(req.request()).client;
+        if let Some(Err(retry_after)) = client.map(|client| self.limiter.acquire(client)) {
+            let message = "too many requests, try again later";
+            let mut response = api_error_response(&error_body("TooManyRequests", message));
+            // Round up so clients retrying after the advertised delay find a token.
+            let retry_after = retry_after.as_secs_f64().ceil() as u64;
+            response
+                .headers_mut()
+                .insert(RETRY_AFTER, HeaderValue::from(retry_after));
+            let response = response.map_into_right_body();
+            let response = req.into_response(response);
+            return Box::pin(async move { Ok(response) });
+        }
+
+        let response = self.service.call(req);
+        Box::pin(async move {
+            let response = response.await?;
+            Ok(response.map_into_left_body())
+        })
+    }
+}
+
+/// Token bucket state for a client.
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Token buckets for all known clients.
+struct RateLimiter {
+    buckets: Mutex<HashMap<IpAddr, Bucket>>,
+    burst: f64,
+    last_evict: Mutex<Instant>,
+    rate: f64,
+}
+
+impl RateLimiter {
+    /// Take a token from the client's bucket or return the time until one is available.
+    fn acquire(&self, client: IpAddr) -> Result<(), Duration> {
+        let now = Instant::now();
+        let mut buckets = self
+            .buckets
+            .lock()
+            .expect("rate limit buckets lock poisoned");
+        self.evict(&mut buckets, now);
+        let bucket = buckets.entry(client).or_insert(Bucket {
+            tokens: self.burst,
+            updated: now,
+        });
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.rate).min(self.burst);
+        bucket.updated = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+        let missing = 1.0 - bucket.tokens;
+        Err(Duration::from_secs_f64(missing / self.rate).max(Duration::from_secs(1)))
+    }
+
+    /// Periodically remove buckets that would be full by now.
+    fn evict(&self, buckets: &mut HashMap<IpAddr, Bucket>, now: Instant) {
+        let mut last_evict = self
+            .last_evict
+            .lock()
+            .expect("rate limit eviction lock poisoned");
+        if now.duration_since(*last_evict) < EVICT_INTERVAL {
+            return;
+        }
+        *last_evict = now;
+        buckets.retain(|_, bucket| {
+            let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+            bucket.tokens + elapsed * self.rate < self.burst
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::IpAddr;
+    use std::net::Ipv4Addr;
+    use std::net::SocketAddr;
+    use std::time::Duration;
+    use std::time::Instant;
+
+    use actix_web::http::header::RETRY_AFTER;
+    use actix_web::http::StatusCode;
+    use actix_web::test::call_service;
+    use actix_web::test::init_service;
+    use actix_web::test::read_body_json;
+    use actix_web::test::TestRequest;
+    use actix_web::web;
+    use actix_web::App;
+    use actix_web::HttpResponse;
+    use serde_json::Value;
+
+    use super::RateLimitMiddleware;
+    use super::EVICT_INTERVAL;
+
+    fn request(port: u16) -> TestRequest {
+        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), port);
+        TestRequest::get().uri("/").peer_addr(addr)
+    }
+
+    #[actix_rt::test]
+    async fn burst_exceeded() {
+        let app = App::new()
+            .wrap(RateLimitMiddleware::new(0.1, 2))
+            .route("/", web::get().to(HttpResponse::Ok));
+        let app = init_service(app).await;
+        assert_eq!(
+            call_service(&app, request(1).to_request()).await.status(),
+            StatusCode::OK
+        );
+        assert_eq!(
+            call_service(&app, request(2).to_request()).await.status(),
+            StatusCode::OK
+        );
+        let response = call_service(&app, request(3).to_request()).await;
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(response.headers().contains_key(RETRY_AFTER));
+        let body: Value = read_body_json(response).await;
+        assert_eq!(body["variant"], "TooManyRequests");
+    }
+
+    #[actix_rt::test]
+    async fn retry_after_rounds_up() {
+        let app = App::new()
+            .wrap(RateLimitMiddleware::new(0.4, 1))
+            .route("/", web::get().to(HttpResponse::Ok));
+        let app = init_service(app).await;
+        assert_eq!(
+            call_service(&app, request(1).to_request()).await.status(),
+            StatusCode::OK
+        );
+        let response = call_service(&app, request(1).to_request()).await;
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers().get(RETRY_AFTER).unwrap(), "3");
+    }
+
+    #[actix_rt::test]
+    async fn bucket_refills() {
+        let app = App::new()
+            .wrap(RateLimitMiddleware::new(50.0, 1))
+            .route("/", web::get().to(HttpResponse::Ok));
+        let app = init_service(app).await;
+        assert_eq!(
+            call_service(&app, request(1).to_request()).await.status(),
+            StatusCode::OK
+        );
+        let response = call_service(&app, request(1).to_request()).await;
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        actix_rt::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(
+            call_service(&app, request(1).to_request()).await.status(),
+            StatusCode::OK
+        );
+    }
+
+    #[test]
+    fn idle_buckets_evicted() {
+        let middleware = RateLimitMiddleware::new(1.0, 1);
+        let limiter = &middleware.limiter;
+        limiter.acquire(IpAddr::V4(Ipv4Addr::LOCALHOST)).unwrap();
+        let later = Instant::now() + EVICT_INTERVAL;
+        let mut buckets = limiter.buckets.lock().unwrap();
+        assert_eq!(buckets.len(), 1);
+        limiter.evict(&mut buckets, later);
+        assert!(buckets.is_empty());
+    }
+}
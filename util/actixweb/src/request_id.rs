@@ -0,0 +1,209 @@
+use std::fmt;
+use std::future::ready;
+use std::future::Ready;
+
+use actix_web::dev::forward_ready;
+use actix_web::dev::Service;
+use actix_web::dev::ServiceRequest;
+use actix_web::dev::ServiceResponse;
+use actix_web::dev::Transform;
+use actix_web::http::header::HeaderName;
+use actix_web::http::header::HeaderValue;
+use actix_web::Error;
+use actix_web::HttpMessage;
+use actix_web::HttpRequest;
+use replicante_util_rndid::RndId;
+
+/// Maximum length of request IDs accepted from clients.
+const MAX_ID_LENGTH: usize = 128;
+
+/// Unique identifier of a request, available to handlers once `RequestIdMiddleware` runs.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct RequestId(String);
+
+impl RequestId {
+    /// String representation of the request ID.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for RequestId {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.write_str(&self.0)
+    }
+}
+
+/// Return the ID of the request, if `RequestIdMiddleware` processed it.
+pub fn request_id(request: &HttpRequest) -> Option<RequestId> {
+    request.extensions().get::<RequestId>().cloned()
+}
+
+/// Actix Web middleware to assign a unique ID to every request.
+///
+/// IDs sent by clients in the request ID header (`X-Request-Id` by default) are reused
+/// as long as they are non-empty, printable ASCII strings of up to 128 characters.
+/// Otherwise a new random ID is generated.
+///
+/// The ID is attached to the request, for use with `request_id`, and set in the
+/// same header of the response.
+pub struct RequestIdMiddleware {
+    header: HeaderName,
+}
+
+impl RequestIdMiddleware {
+    pub fn new() -> RequestIdMiddleware {
+        RequestIdMiddleware {
+            header: HeaderName::from_static("x-request-id"),
+        }
+    }
+
+    /// Read and write request IDs from a custom header.
+    pub fn header(mut self, header: HeaderName) -> RequestIdMiddleware {
+        self.header = header;
+        self
+    }
+
+    /// Wrap a service without going through `Transform::new_transform`.
+    pub(crate) fn wrap_service<S>(&self, service: S) -> MiddlewareService<S> {
+        MiddlewareService {
+            header: self.header.clone(),
+            service,
+        }
+    }
+}
+
+impl Default for RequestIdMiddleware {
+    fn default() -> RequestIdMiddleware {
+        RequestIdMiddleware::new()
+    }
+}
+
+// `S` - type of the next service
+// `B` - type of response's body
+impl<S, B> Transform<S, ServiceRequest> for RequestIdMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = MiddlewareService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(self.wrap_service(service)))
+    }
+}
+
+/// Inner middleware to process requests on behalf of `RequestIdMiddleware`.
+pub struct MiddlewareService<S> {
+    header: HeaderName,
+    service: S,
+}
+
+impl<S, B> Service<ServiceRequest> for MiddlewareService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = crate::LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let id = req
+            .headers()
+            .get(&self.header)
+            .and_then(|value| value.to_str().ok())
+            .filter(|id| is_valid_id(id))
+            .map(String::from)
+            .unwrap_or_else(|| RndId::new().to_string());
+        let value = HeaderValue::from_str(&id).expect("request IDs are valid header values");
+        req.extensions_mut().insert(RequestId(id));
+        let header = self.header.clone();
+        let response = self.service.call(req);
+        Box::pin(async move {
+            let mut response = response.await?;
+            response.headers_mut().insert(header, value);
+            Ok(response)
+        })
+    }
+}
+
+/// Check if a client provided request ID can be used.
+fn is_valid_id(id: &str) -> bool {
+    !id.is_empty() && id.len() <= MAX_ID_LENGTH && id.bytes().all(|byte| byte.is_ascii_graphic())
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::header::HeaderName;
+    use actix_web::test::call_service;
+    use actix_web::test::init_service;
+    use actix_web::test::read_body;
+    use actix_web::test::TestRequest;
+    use actix_web::web;
+    use actix_web::App;
+    use actix_web::HttpRequest;
+    use actix_web::HttpResponse;
+
+    use super::request_id;
+    use super::RequestIdMiddleware;
+
+    async fn echo(request: HttpRequest) -> HttpResponse {
+        let id = request_id(&request).expect("request ID not set");
+        HttpResponse::Ok().body(id.to_string())
+    }
+
+    #[actix_rt::test]
+    async fn client_id_is_reused() {
+        let app = App::new()
+            .wrap(RequestIdMiddleware::new())
+            .route("/", web::get().to(echo));
+        let app = init_service(app).await;
+        let req = TestRequest::get()
+            .uri("/")
+            .insert_header(("X-Request-Id", "client-id-1"))
+            .to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(res.headers().get("x-request-id").unwrap(), "client-id-1");
+        assert_eq!(read_body(res).await, "client-id-1");
+    }
+
+    #[actix_rt::test]
+    async fn custom_header() {
+        let header = HeaderName::from_static("x-correlation-id");
+        let app = App::new()
+            .wrap(RequestIdMiddleware::new().header(header))
+            .route("/", web::get().to(echo));
+        let app = init_service(app).await;
+        let req = TestRequest::get().uri("/").to_request();
+        let res = call_service(&app, req).await;
+        assert!(res.headers().get("x-request-id").is_none());
+        assert!(res.headers().get("x-correlation-id").is_some());
+    }
+
+    #[actix_rt::test]
+    async fn invalid_client_id_is_replaced() {
+        let app = App::new()
+            .wrap(RequestIdMiddleware::new())
+            .route("/", web::get().to(echo));
+        let app = init_service(app).await;
+        let long = "a".repeat(200);
+        let req = TestRequest::get()
+            .uri("/")
+            .insert_header(("X-Request-Id", long.as_str()))
+            .to_request();
+        let res = call_service(&app, req).await;
+        let id = res.headers().get("x-request-id").unwrap().to_str().unwrap();
+        assert_eq!(id.len(), 32);
+        let id = id.to_string();
+        assert_eq!(read_body(res).await, id);
+    }
+}
@@ -0,0 +1,182 @@
+/// Register several named routes under a common prefix in an `AppConfigContext`.
+///
+/// Each route is described by a `(METHOD, path, handler, name)` tuple, where `METHOD`
+/// is the name of an `actix_web::http::Method` constant (`GET`, `POST`, ...).
+/// Routes are registered with `AppConfigContext::scoped_route` so they share
+/// an `actix_web::Scope` for the prefix.
+///
+/// Route names must be constant expressions (usually string literals): they are checked
+/// at compile time so an invocation listing the same name twice does not build.
+///
+/// # Panic
+/// Registering a route with the same name as one from another invocation panics.
+///
+/// # Example
+/// ```
+/// use actix_web::HttpResponse;
+/// use replicante_util_actixweb::routes;
+/// use replicante_util_actixweb::AppConfig;
+/// use replicante_util_actixweb::RootDescriptor;
+///
+/// # struct Api;
+/// # impl RootDescriptor for Api {
+/// #     fn enabled(&self, _: &replicante_util_actixweb::APIFlags) -> bool { true }
+/// #     fn prefix(&self) -> &'static str { "/api/v1" }
+/// # }
+/// async fn info() -> HttpResponse {
+///     HttpResponse::Ok().finish()
+/// }
+///
+/// let mut config: AppConfig<()> = AppConfig::default();
+/// config.register(|conf| {
+///     routes!(conf, Api.prefix(), [
+///         (GET, "/info", info, "info"),
+///         (POST, "/info", info, "info_update"),
+///     ]);
+/// });
+/// ```
+///
+/// Duplicate names within an invocation are rejected at compile time:
+/// ```compile_fail
+/// # use actix_web::HttpResponse;
+/// # use replicante_util_actixweb::routes;
+/// # use replicante_util_actixweb::AppConfig;
+/// # async fn info() -> HttpResponse {
+/// #     HttpResponse::Ok().finish()
+/// # }
+/// let mut config: AppConfig<()> = AppConfig::default();
+/// config.register(|conf| {
+///     routes!(conf, "/api/v1", [
+///         (GET, "/info", info, "info"),
+///         (POST, "/info", info, "info"),
+///     ]);
+/// });
+/// ```
+#[macro_export]
+macro_rules! routes {
+    ($conf:expr, $prefix:expr, [$(($method:ident, $path:expr, $handler:expr, $name:expr)),* $(,)?]) => {{
+        const _: () = assert!(
+            !$crate::has_duplicate_route_names(&[$($name),*]),
+            "routes! invocation registers two routes with the same name",
+        );
+        let prefix: &str = $prefix;
+        $(
+            $conf.scoped_route(
+                prefix,
+                ::actix_web::http::Method::$method,
+                $path,
+                $name,
+                $handler,
+            );
+        )*
+    }};
+}
+
+/// Check if any name in the list is repeated, for use in constant expressions.
+#[doc(hidden)]
+pub const fn has_duplicate_route_names(names: &[&str]) -> bool {
+    let mut left = 0;
+    while left < names.len() {
+        let mut right = left + 1;
+        while right < names.len() {
+            if str_eq(names[left], names[right]) {
+                return true;
+            }
+            right += 1;
+        }
+        left += 1;
+    }
+    false
+}
+
+/// Compare strings in constant expressions, where `PartialEq` can't be used.
+const fn str_eq(left: &str, right: &str) -> bool {
+    let left = left.as_bytes();
+    let right = right.as_bytes();
+    if left.len() != right.len() {
+        return false;
+    }
+    let mut idx = 0;
+    while idx < left.len() {
+        if left[idx] != right[idx] {
+            return false;
+        }
+        idx += 1;
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::call_service;
+    use actix_web::test::init_service;
+    use actix_web::test::TestRequest;
+    use actix_web::App;
+    use actix_web::HttpRequest;
+    use actix_web::HttpResponse;
+
+    use super::has_duplicate_route_names;
+    use crate::AppConfig;
+
+    async fn ok() -> HttpResponse {
+        HttpResponse::Ok().finish()
+    }
+
+    async fn created() -> HttpResponse {
+        HttpResponse::Created().finish()
+    }
+
+    async fn url_for(req: HttpRequest) -> HttpResponse {
+        let url = req.url_for_static("ok").unwrap();
+        HttpResponse::Ok().body(url.path().to_string())
+    }
+
+    #[actix_rt::test]
+    async fn mount_routes() {
+        let mut conf = AppConfig::default();
+        conf.register(|conf| {
+            routes!(
+                conf,
+                "/api",
+                [
+                    (GET, "/ok", ok, "ok"),
+                    (POST, "/ok", created, "created"),
+                    (GET, "/url", url_for, "url"),
+                ]
+            );
+        });
+        let app = App::new().configure(|app| conf.configure(app, &()));
+        let app = init_service(app).await;
+
+        let req = TestRequest::get().uri("/api/ok").to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status().as_u16(), 200);
+        let req = TestRequest::post().uri("/api/ok").to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status().as_u16(), 201);
+        let req = TestRequest::get().uri("/api/url").to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status().as_u16(), 200);
+        let body = actix_web::test::read_body(res).await;
+        assert_eq!(body, "/api/ok");
+    }
+
+    #[test]
+    #[should_panic(expected = "a route named 'ok' is already registered")]
+    fn duplicate_names_panic() {
+        let mut conf = AppConfig::default();
+        conf.register(|conf| {
+            routes!(conf, "/api", [(GET, "/ok", ok, "ok")]);
+            routes!(conf, "/other", [(POST, "/created", created, "ok")]);
+        });
+        let _ = App::new().configure(|app| conf.configure(app, &()));
+    }
+
+    #[test]
+    fn duplicate_names_detected() {
+        assert!(!has_duplicate_route_names(&[]));
+        assert!(!has_duplicate_route_names(&["ok", "created", "url"]));
+        assert!(!has_duplicate_route_names(&["ok", "oks"]));
+        assert!(has_duplicate_route_names(&["ok", "created", "ok"]));
+    }
+}
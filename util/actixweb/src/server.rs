@@ -0,0 +1,129 @@
+use actix_web::dev::Server;
+use actix_web::rt::System;
+use humthreads::Builder;
+use humthreads::Error as HumthreadsError;
+
+use replicante_util_upkeep::Upkeep;
+
+/// Run an Actix Web `Server` in a background thread stopped gracefully by `Upkeep`.
+///
+/// When the `Upkeep` shutdown process starts the server stops accepting new connections
+/// and waits for in-flight requests to complete before the thread exits.
+/// Requests still active after the timeout set with `HttpServer::shutdown_timeout`
+/// (30 seconds by default) are dropped.
+///
+/// The server thread is registered with `Upkeep` so `Upkeep::keepalive` waits for the
+/// drain to complete before returning and the process shuts down if the server stops.
+pub fn run_with_upkeep(upkeep: &mut Upkeep, server: Server) -> Result<(), HumthreadsError> {
+    let handle = server.handle();
+    let thread = Builder::new("r:u:actixweb:server")
+        .full_name("replicante:util:actixweb:server")
+        .spawn(move |scope| {
+            scope.activity("running actix web server");
+            System::new().block_on(server)
+        })?;
+    upkeep.on_shutdown(move || {
+        // The stop command is sent eagerly, the server thread is joined by Upkeep.
+        let _stopping = handle.stop(true);
+    });
+    upkeep.register_thread_named("replicante:util:actixweb:server", thread);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+    use std::io::Write;
+    use std::net::TcpStream;
+    use std::sync::mpsc::channel;
+    use std::sync::mpsc::Receiver;
+    use std::sync::mpsc::Sender;
+    use std::sync::Arc;
+    use std::sync::Mutex;
+    use std::time::Duration;
+    use std::time::Instant;
+
+    use actix_web::web;
+    use actix_web::App;
+    use actix_web::HttpResponse;
+    use actix_web::HttpServer;
+
+    use replicante_util_upkeep::Upkeep;
+
+    use super::run_with_upkeep;
+
+    /// Signals to control a slow request from the test.
+    #[derive(Clone)]
+    struct SlowRequest {
+        release: Arc<Mutex<Receiver<()>>>,
+        started: Sender<()>,
+    }
+
+    /// Respond only once the test releases the request.
+    async fn slow(request: web::Data<SlowRequest>) -> HttpResponse {
+        request.started.send(()).unwrap();
+        let release = Arc::clone(&request.release);
+        web::block(move || release.lock().unwrap().recv())
+            .await
+            .unwrap()
+            .unwrap();
+        HttpResponse::Ok().body("done")
+    }
+
+    #[test]
+    fn drain_in_flight_requests() {
+        let (started, started_receiver) = channel();
+        let (release, release_receiver) = channel();
+        let slow_request = SlowRequest {
+            release: Arc::new(Mutex::new(release_receiver)),
+            started,
+        };
+        let server = HttpServer::new(move || {
+            App::new()
+                .app_data(web::Data::new(slow_request.clone()))
+                .route("/slow", web::get().to(slow))
+        })
+        .workers(1)
+        .shutdown_timeout(5)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+        let addr = server.addrs()[0];
+        let mut upkeep = Upkeep::new();
+        run_with_upkeep(&mut upkeep, server.run()).unwrap();
+        let (keepalive, shutdown) = upkeep.spawn_keepalive();
+
+        // Start a slow request and request shutdown while it is in flight.
+        let client = std::thread::spawn(move || {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            stream
+                .write_all(b"GET /slow HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+                .unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        });
+        started_receiver
+            .recv_timeout(Duration::from_secs(5))
+            .expect("slow request did not start");
+        shutdown.shutdown();
+
+        // New connections are refused while the slow request is still running.
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while TcpStream::connect(addr).is_ok() {
+            assert!(
+                Instant::now() < deadline,
+                "server kept accepting connections"
+            );
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert!(!client.is_finished());
+        assert!(!keepalive.is_finished());
+
+        // The in-flight request completes once released and the server drains.
+        release.send(()).unwrap();
+        let response = client.join().unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK"), "{}", response);
+        assert!(response.ends_with("done"));
+        assert!(keepalive.join().unwrap());
+    }
+}
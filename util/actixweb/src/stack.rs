@@ -0,0 +1,185 @@
+use std::future::ready;
+use std::future::Ready;
+use std::sync::Arc;
+
+use actix_web::dev::Service;
+use actix_web::dev::ServiceRequest;
+use actix_web::dev::ServiceResponse;
+use actix_web::dev::Transform;
+use actix_web::Error;
+use opentracingrust::Tracer;
+use slog::Logger;
+
+use crate::LoggingMiddleware;
+use crate::MetricsCollector;
+use crate::MetricsMiddleware;
+use crate::RequestIdMiddleware;
+use crate::SentryMiddleware;
+use crate::TracingMiddleware;
+
+/// Type of the service generated by the `StandardMiddleware`, from outermost to innermost.
+type StackService<S> = crate::request_id::MiddlewareService<
+    crate::logging::MiddlewareService<
+        crate::metrics::MiddlewareService<
+            crate::tracing::MiddlewareService<crate::capture::SentryService<S>>,
+        >,
+    >,
+>;
+
+/// Actix Web middleware bundling request ID, logging, metrics, tracing and sentry middlewares.
+///
+/// Middlewares are applied so that requests flow through them in the following order:
+///
+///   1. `RequestIdMiddleware`: assigns request IDs so all other middlewares can use them.
+///   2. `LoggingMiddleware`: logs all requests, including the ones rejected by other middlewares.
+///   3. `MetricsMiddleware`: observes the duration of the entire request.
+///   4. `TracingMiddleware`: starts the request span before errors are reported.
+///   5. `SentryMiddleware`: captures error responses while the request span is available.
+///
+/// Events captured to sentry are tagged with the `trace_id` and `span_id` of the request span
+/// so they can be correlated with traces and the request logs.
+///
+/// Responses travel back through the middlewares in reverse order.
+///
+/// # Example
+/// ```
+/// use std::sync::Arc;
+///
+/// use actix_web::App;
+/// use opentracingrust::tracers::NoopTracer;
+/// use replicante_util_actixweb::MetricsCollector;
+/// use replicante_util_actixweb::StandardMiddleware;
+/// use slog::o;
+/// use slog::Discard;
+/// use slog::Logger;
+///
+/// let logger = Logger::root(Discard, o!());
+/// let (tracer, _) = NoopTracer::new();
+/// let metrics = MetricsCollector::new("example");
+/// let app = App::new().wrap(StandardMiddleware::new(logger, Arc::new(tracer), metrics, 500));
+/// ```
+pub struct StandardMiddleware {
+    logging: LoggingMiddleware,
+    metrics: MetricsMiddleware,
+    request_id: RequestIdMiddleware,
+    sentry: SentryMiddleware,
+    tracing: TracingMiddleware,
+}
+
+impl StandardMiddleware {
+    /// Bundle the standard middlewares, capturing responses with status `sentry_level` or above.
+    pub fn new(
+        logger: Logger,
+        tracer: Arc<Tracer>,
+        metrics: MetricsCollector,
+        sentry_level: u16,
+    ) -> StandardMiddleware {
+        StandardMiddleware {
+            logging: LoggingMiddleware::new(logger.clone()),
+            metrics: MetricsMiddleware::new(metrics),
+            request_id: RequestIdMiddleware::new(),
+            sentry: SentryMiddleware::new(logger.clone(), sentry_level),
+            tracing: TracingMiddleware::new(logger, tracer),
+        }
+    }
+}
+
+// `S` - type of the next service
+// `B` - type of response's body
+impl<S, B> Transform<S, ServiceRequest> for StandardMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = StackService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        let service = self.sentry.wrap_service(service);
+        let service = self.tracing.wrap_service(service);
+        let service = self.metrics.wrap_service(service);
+        let service = self.logging.wrap_service(service);
+        ready(Ok(self.request_id.wrap_service(service)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use actix_web::test::call_service;
+    use actix_web::test::init_service;
+    use actix_web::test::TestRequest;
+    use actix_web::web;
+    use actix_web::App;
+    use actix_web::HttpResponse;
+    use opentracingrust_zipkin::ZipkinTracer;
+    use prometheus::Registry;
+    use replicante_logging::buffer_logger;
+    use sentry::test::with_captured_events;
+
+    use super::StandardMiddleware;
+    use crate::MetricsCollector;
+
+    #[test]
+    fn request_through_the_stack() {
+        let (logger, sink) = buffer_logger();
+        let registry = Registry::new();
+        let metrics = MetricsCollector::new("test");
+        metrics.register(&logger, &registry).unwrap();
+        let (tracer, receiver) = ZipkinTracer::new();
+        let tracer = Arc::new(tracer);
+
+        let mut trace_ids = None;
+        let events = with_captured_events(|| {
+            actix_rt::System::new().block_on(async {
+                let app = App::new()
+                    .wrap(StandardMiddleware::new(logger, tracer, metrics, 500))
+                    .route("/fail", web::get().to(HttpResponse::InternalServerError));
+                let app = init_service(app).await;
+                let req = TestRequest::get().uri("/fail").to_request();
+                let res = call_service(&app, req).await;
+                assert_eq!(res.status().as_u16(), 500);
+                assert!(res.headers().contains_key("x-request-id"));
+                let header = |name| {
+                    res.headers()
+                        .get(name)
+                        .unwrap()
+                        .to_str()
+                        .unwrap()
+                        .to_string()
+                };
+                trace_ids = Some((header("x-b3-traceid"), header("x-b3-spanid")));
+            });
+        });
+
+        // One log line from the sentry middleware and one from the logging middleware.
+        let records = sink.records();
+        assert_eq!(records.len(), 2);
+        assert_eq!(events.len(), 1);
+
+        // Captured events and request logs are correlated with the request span.
+        let (trace_id, span_id) = trace_ids.expect("request was not sent");
+        assert_eq!(events[0].tags.get("trace_id").unwrap(), &trace_id);
+        assert_eq!(events[0].tags.get("span_id").unwrap(), &span_id);
+        let sentry_record = records
+            .iter()
+            .find(|record| record.fields.contains_key("sentry_captured"))
+            .expect("sentry middleware did not log");
+        assert_eq!(sentry_record.fields["trace_id"], trace_id);
+        let span = receiver.try_recv().expect("request span was not finished");
+        assert_eq!(span.name(), "/fail");
+        let samples: u64 = registry
+            .gather()
+            .iter()
+            .filter(|family| family.get_name() == "test_endpoint_duration")
+            .flat_map(|family| family.get_metric())
+            .map(|metric| metric.get_histogram().get_sample_count())
+            .sum();
+        assert_eq!(samples, 1);
+    }
+}
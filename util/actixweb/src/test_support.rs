@@ -0,0 +1,112 @@
+use std::sync::Arc;
+
+use actix_web::body::BoxBody;
+use actix_web::dev::ServiceResponse;
+use actix_web::test::call_service;
+use actix_web::test::init_service;
+use actix_web::test::TestRequest;
+use actix_web::web::ServiceConfig;
+use actix_web::App;
+use opentracingrust::tracers::NoopTracer;
+use opentracingrust::FinishedSpan;
+use prometheus::proto::MetricFamily;
+use prometheus::Registry;
+use slog::o;
+use slog::Discard;
+use slog::Logger;
+
+use crate::MetricsCollector;
+use crate::StandardMiddleware;
+
+/// Prefix of the metrics collected by `capture_request`.
+pub const TEST_METRICS_PREFIX: &str = "test";
+
+/// Response, spans and metrics captured by [`capture_request`].
+pub struct CapturedRequest {
+    /// Metric families gathered from the registry once the response was returned.
+    pub metrics: Vec<MetricFamily>,
+
+    /// Response returned by the application.
+    pub response: ServiceResponse<BoxBody>,
+
+    /// Spans finished while the request was processed, in the order they finished.
+    pub spans: Vec<FinishedSpan>,
+}
+
+impl CapturedRequest {
+    /// Find a gathered metric family by its full name.
+    pub fn metric(&self, name: &str) -> Option<&MetricFamily> {
+        self.metrics.iter().find(|family| family.get_name() == name)
+    }
+
+    /// Find the first finished span with the given name.
+    pub fn span(&self, name: &str) -> Option<&FinishedSpan> {
+        self.spans.iter().find(|span| span.name() == name)
+    }
+}
+
+/// Run a request through an application wrapped in the `StandardMiddleware`.
+///
+/// The application is configured by `configure` and its spans and metrics are captured
+/// so tests can assert on them without setting up a tracer and registry.
+/// Metrics are named with the `TEST_METRICS_PREFIX` prefix (`test_endpoint_duration`, ...).
+pub async fn capture_request<F>(configure: F, request: TestRequest) -> CapturedRequest
+where
+    F: FnOnce(&mut ServiceConfig),
+{
+    let logger = Logger::root(Discard, o!());
+    let (tracer, receiver) = NoopTracer::new();
+    let metrics = MetricsCollector::new(TEST_METRICS_PREFIX);
+    let registry = Registry::new();
+    metrics
+        .register(&logger, &registry)
+        .expect("unable to register test metrics");
+    let middleware = StandardMiddleware::new(logger, Arc::new(tracer), metrics, 500);
+    let app = init_service(App::new().wrap(middleware).configure(configure)).await;
+    let response = call_service(&app, request.to_request())
+        .await
+        .map_into_boxed_body();
+    let spans = receiver.try_iter().collect();
+    CapturedRequest {
+        metrics: registry.gather(),
+        response,
+        spans,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::StatusCode;
+    use actix_web::test::TestRequest;
+    use actix_web::web;
+    use actix_web::HttpResponse;
+
+    use super::capture_request;
+
+    #[actix_rt::test]
+    async fn captures_span_and_duration() {
+        let configure = |config: &mut web::ServiceConfig| {
+            config.route("/items/{id}", web::get().to(HttpResponse::Ok));
+        };
+        let request = TestRequest::get().uri("/items/42");
+        let captured = capture_request(configure, request).await;
+        assert_eq!(captured.response.status(), StatusCode::OK);
+
+        let span = captured
+            .span("/items/42")
+            .expect("request span not captured");
+        assert!(span.tags().get("http.route.uri").is_some());
+
+        let duration = captured
+            .metric("test_endpoint_duration")
+            .expect("duration metric not captured");
+        let sample = &duration.get_metric()[0];
+        let labels: Vec<_> = sample
+            .get_label()
+            .iter()
+            .map(|label| (label.get_name(), label.get_value()))
+            .collect();
+        assert!(labels.contains(&("path", "/items/{id}")));
+        assert_eq!(sample.get_histogram().get_sample_count(), 1);
+    }
+}
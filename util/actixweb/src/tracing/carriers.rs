@@ -14,8 +14,133 @@ use opentracingrust::Tracer;
 use crate::errors::HttpError;
 use crate::errors::TracingContextError;
 
+/// Header name of the W3C Trace Context `traceparent` header.
+const W3C_TRACEPARENT: &str = "traceparent";
+
+/// Trace context propagation formats `HeadersCarrier::extract_any` can look for.
+///
+/// Tracers used by Replicante (Zipkin) understand B3 headers only:
+/// contexts in other formats are translated into B3 headers before they are extracted.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CarrierFormat {
+    /// Zipkin B3 multi-header format (`X-B3-TraceId`, `X-B3-SpanId`, ...).
+    B3,
+
+    /// W3C Trace Context `traceparent` header.
+    W3C,
+}
+
+impl CarrierFormat {
+    /// Copy the trace context in this format, if present, into B3 headers.
+    ///
+    /// Baggage headers (`OT-Baggage-*`) are copied regardless of the format.
+    fn to_b3(self, headers: &HeaderMap) -> Result<Option<HeaderMap>> {
+        let mut b3 = HeaderMap::new();
+        for (name, value) in headers.iter() {
+            if name.as_str().starts_with("ot-baggage-") {
+                b3.append(name.clone(), value.clone());
+            }
+        }
+        match self {
+            CarrierFormat::B3 => {
+                if !headers.contains_key("x-b3-traceid") {
+                    return Ok(None);
+                }
+                for (name, value) in headers.iter() {
+                    if name.as_str().starts_with("x-b3-") {
+                        b3.append(name.clone(), value.clone());
+                    }
+                }
+            }
+            CarrierFormat::W3C => {
+                let traceparent = match headers.get(W3C_TRACEPARENT) {
+                    None => return Ok(None),
+                    Some(traceparent) => traceparent,
+                };
+                let traceparent = traceparent
+                    .to_str()
+                    .ok()
+                    .and_then(parse_traceparent)
+                    .ok_or_else(|| HttpError::header_value_invalid(W3C_TRACEPARENT))?;
+                let (trace_id, span_id, sampled) = traceparent;
+                let sampled = if sampled { "1" } else { "0" };
+                let b3_headers = [
+                    ("x-b3-traceid", trace_id),
+                    ("x-b3-spanid", span_id),
+                    ("x-b3-sampled", sampled),
+                ];
+                for (name, value) in b3_headers {
+                    let value = HeaderValue::from_str(value)
+                        .with_context(|| HttpError::header_value_invalid(W3C_TRACEPARENT))?;
+                    b3.insert(HeaderName::from_static(name), value);
+                }
+            }
+        }
+        Ok(Some(b3))
+    }
+}
+
+/// Parse a W3C `traceparent` value into trace ID, parent span ID and sampled flag.
+///
+/// Versions other than `00` are accepted as long as the known fields are valid,
+/// as required by the specification for forward compatibility.
+fn parse_traceparent(value: &str) -> Option<(&str, &str, bool)> {
+    let mut parts = value.trim().split('-');
+    let version = parts.next()?;
+    let trace_id = parts.next()?;
+    let span_id = parts.next()?;
+    let flags = parts.next()?;
+    let is_hex = |value: &str, len: usize| {
+        value.len() == len && value.bytes().all(|byte| byte.is_ascii_hexdigit())
+    };
+    let is_zero = |value: &str| value.bytes().all(|byte| byte == b'0');
+    if !is_hex(version, 2) || version.eq_ignore_ascii_case("ff") {
+        return None;
+    }
+    if version == "00" && parts.next().is_some() {
+        return None;
+    }
+    if !is_hex(trace_id, 32) || is_zero(trace_id) || !is_hex(span_id, 16) || is_zero(span_id) {
+        return None;
+    }
+    if !is_hex(flags, 2) {
+        return None;
+    }
+    let flags = u8::from_str_radix(flags, 16).ok()?;
+    Some((trace_id, span_id, flags & 0x01 == 0x01))
+}
+
+/// Select which headers participate in context propagation.
+///
+/// Header names are compared case-insensitively.
+#[derive(Clone, Debug, Default)]
+pub enum HeaderFilter {
+    /// All headers are visible to the tracer.
+    #[default]
+    All,
+
+    /// Only the listed headers are visible to the tracer.
+    Allow(Vec<String>),
+
+    /// All headers except the listed ones are visible to the tracer.
+    Deny(Vec<String>),
+}
+
+impl HeaderFilter {
+    /// Check if the given header name should participate in propagation.
+    pub fn allows(&self, name: &str) -> bool {
+        let listed = |names: &Vec<String>| names.iter().any(|n| n.eq_ignore_ascii_case(name));
+        match self {
+            HeaderFilter::All => true,
+            HeaderFilter::Allow(names) => listed(names),
+            HeaderFilter::Deny(names) => !listed(names),
+        }
+    }
+}
+
 /// Implement the MapCarrier trait for Iron's Headers.
 pub struct HeadersCarrier<'a> {
+    filter: HeaderFilter,
     headers: &'a mut HeaderMap,
     // This is horrible, I am sorry.
     // The MapCarrier items function returns pointers which we don't have
@@ -31,6 +156,9 @@ impl<'a> HeadersCarrier<'a> {
     fn prepare_iter(&mut self) -> Result<()> {
         let mut items = HashMap::new();
         for (header, value) in self.headers.iter() {
+            if !self.filter.allows(header.as_str()) {
+                continue;
+            }
             let header = String::from(header.as_str());
             let value = value
                 .to_str()
@@ -46,7 +174,16 @@ impl<'a> HeadersCarrier<'a> {
 impl<'a> HeadersCarrier<'a> {
     /// Mutably borrow a response so it can be serialised.
     pub fn new(headers: &'a mut HeaderMap) -> Result<HeadersCarrier<'a>> {
+        HeadersCarrier::with_filter(headers, HeaderFilter::default())
+    }
+
+    /// Mutably borrow a response, exposing only headers allowed by the filter.
+    pub fn with_filter(
+        headers: &'a mut HeaderMap,
+        filter: HeaderFilter,
+    ) -> Result<HeadersCarrier<'a>> {
         let mut carrier = HeadersCarrier {
+            filter,
             iter_stage: HashMap::new(),
             headers,
         };
@@ -56,7 +193,17 @@ impl<'a> HeadersCarrier<'a> {
 
     /// Inject a `SpanContext` into the given Iron headers.
     pub fn inject(context: &SpanContext, headers: &mut HeaderMap, tracer: &Tracer) -> Result<()> {
-        let mut carrier = HeadersCarrier::new(headers)?;
+        HeadersCarrier::inject_filtered(context, headers, tracer, HeaderFilter::default())
+    }
+
+    /// Inject a `SpanContext` into the given headers, skipping headers denied by the filter.
+    pub fn inject_filtered(
+        context: &SpanContext,
+        headers: &mut HeaderMap,
+        tracer: &Tracer,
+        filter: HeaderFilter,
+    ) -> Result<()> {
+        let mut carrier = HeadersCarrier::with_filter(headers, filter)?;
         let format = InjectFormat::HttpHeaders(Box::new(&mut carrier));
         tracer
             .inject(context, format)
@@ -67,7 +214,16 @@ impl<'a> HeadersCarrier<'a> {
 
     /// Checks the headers for a span context and extract it if possible.
     pub fn extract(headers: &mut HeaderMap, tracer: &Tracer) -> Result<Option<SpanContext>> {
-        let carrier = HeadersCarrier::new(headers)?;
+        HeadersCarrier::extract_filtered(headers, tracer, HeaderFilter::default())
+    }
+
+    /// Checks the headers allowed by the filter for a span context and extract it if possible.
+    pub fn extract_filtered(
+        headers: &mut HeaderMap,
+        tracer: &Tracer,
+        filter: HeaderFilter,
+    ) -> Result<Option<SpanContext>> {
+        let carrier = HeadersCarrier::with_filter(headers, filter)?;
         let format = ExtractFormat::HttpHeaders(Box::new(&carrier));
         let context = tracer
             .extract(format)
@@ -75,6 +231,36 @@ impl<'a> HeadersCarrier<'a> {
             .context(TracingContextError::Extract)?;
         Ok(context)
     }
+
+    /// Checks the headers for a span context in any of the given formats.
+    ///
+    /// Formats are tried in order and the first extracted context is returned,
+    /// so list the preferred format first (for example B3 before W3C while migrating).
+    /// Formats that fail to extract a context do not prevent later formats from being tried
+    /// but, if no context is found, the first error encountered is returned.
+    pub fn extract_any(
+        headers: &HeaderMap,
+        tracer: &Tracer,
+        formats: &[CarrierFormat],
+    ) -> Result<Option<SpanContext>> {
+        let mut result = Ok(None);
+        for format in formats {
+            let extracted = format
+                .to_b3(headers)
+                .and_then(|b3| match b3 {
+                    None => Ok(None),
+                    Some(mut b3) => HeadersCarrier::extract(&mut b3, tracer),
+                })
+                .with_context(|| format!("failed to extract {:?} tracing context", format));
+            match extracted {
+                Ok(Some(context)) => return Ok(Some(context)),
+                Ok(None) => (),
+                Err(error) if result.is_ok() => result = Err(error),
+                Err(_) => (),
+            }
+        }
+        result
+    }
 }
 
 impl<'a> MapCarrier for HeadersCarrier<'a> {
@@ -83,6 +269,9 @@ impl<'a> MapCarrier for HeadersCarrier<'a> {
     }
 
     fn get(&self, key: &str) -> Option<String> {
+        if !self.filter.allows(key) {
+            return None;
+        }
         match self.headers.get(key) {
             None => None,
             Some(value) => {
@@ -94,9 +283,159 @@ impl<'a> MapCarrier for HeadersCarrier<'a> {
     }
 
     fn set(&mut self, key: &str, value: &str) {
+        if !self.filter.allows(key) {
+            return;
+        }
         let header = HeaderName::from_bytes(key.as_bytes()).unwrap();
         let value = HeaderValue::from_str(value).unwrap();
         self.headers.insert(header, value);
         self.prepare_iter().unwrap();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::header::HeaderMap;
+    use actix_web::http::header::HeaderName;
+    use actix_web::http::header::HeaderValue;
+    use opentracingrust::MapCarrier;
+    use opentracingrust::Tracer;
+    use opentracingrust_zipkin::ZipkinTracer;
+
+    use super::CarrierFormat;
+    use super::HeaderFilter;
+    use super::HeadersCarrier;
+
+    const B3_TRACE_ID: &str = "0102030405060708090a0b0c0d0e0f10";
+    const W3C_TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+    const BOTH: [CarrierFormat; 2] = [CarrierFormat::B3, CarrierFormat::W3C];
+
+    fn b3_headers(headers: &mut HeaderMap) {
+        headers.insert(
+            HeaderName::from_static("x-b3-traceid"),
+            HeaderValue::from_static(B3_TRACE_ID),
+        );
+        headers.insert(
+            HeaderName::from_static("x-b3-spanid"),
+            HeaderValue::from_static("2a"),
+        );
+    }
+
+    fn w3c_headers(headers: &mut HeaderMap) {
+        headers.insert(
+            HeaderName::from_static("traceparent"),
+            HeaderValue::from_static("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"),
+        );
+    }
+
+    /// Extract a context from the headers and return the trace ID it propagates.
+    fn extracted_trace_id(
+        headers: &HeaderMap,
+        tracer: &Tracer,
+        formats: &[CarrierFormat],
+    ) -> Option<String> {
+        let context = HeadersCarrier::extract_any(headers, tracer, formats).unwrap()?;
+        let mut injected = HeaderMap::new();
+        HeadersCarrier::inject(&context, &mut injected, tracer).unwrap();
+        let trace_id = injected.get("x-b3-traceid").unwrap();
+        Some(trace_id.to_str().unwrap().to_string())
+    }
+
+    fn headers() -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            HeaderName::from_static("authorization"),
+            HeaderValue::from_static("secret"),
+        );
+        headers.insert(
+            HeaderName::from_static("x-trace-id"),
+            HeaderValue::from_static("abc"),
+        );
+        headers
+    }
+
+    #[test]
+    fn default_exposes_all_headers() {
+        let mut headers = headers();
+        let carrier = HeadersCarrier::new(&mut headers).unwrap();
+        assert_eq!(carrier.items().len(), 2);
+        assert_eq!(carrier.get("authorization"), Some("secret".into()));
+    }
+
+    #[test]
+    fn allow_list_hides_other_headers() {
+        let mut headers = headers();
+        let filter = HeaderFilter::Allow(vec!["X-Trace-Id".into()]);
+        let carrier = HeadersCarrier::with_filter(&mut headers, filter).unwrap();
+        let items = carrier.items();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].0, "x-trace-id");
+        assert_eq!(carrier.get("authorization"), None);
+        assert_eq!(carrier.get("x-trace-id"), Some("abc".into()));
+    }
+
+    #[test]
+    fn extract_any_b3_only() {
+        let (tracer, _) = ZipkinTracer::new();
+        let mut headers = headers();
+        b3_headers(&mut headers);
+        let trace_id = extracted_trace_id(&headers, &tracer, &BOTH);
+        assert_eq!(trace_id.as_deref(), Some(B3_TRACE_ID));
+        let trace_id = extracted_trace_id(&headers, &tracer, &[CarrierFormat::W3C]);
+        assert_eq!(trace_id, None);
+    }
+
+    #[test]
+    fn extract_any_w3c_only() {
+        let (tracer, _) = ZipkinTracer::new();
+        let mut headers = headers();
+        w3c_headers(&mut headers);
+        let trace_id = extracted_trace_id(&headers, &tracer, &BOTH);
+        assert_eq!(trace_id.as_deref(), Some(W3C_TRACE_ID));
+        let trace_id = extracted_trace_id(&headers, &tracer, &[CarrierFormat::B3]);
+        assert_eq!(trace_id, None);
+    }
+
+    #[test]
+    fn extract_any_follows_format_precedence() {
+        let (tracer, _) = ZipkinTracer::new();
+        let mut headers = headers();
+        b3_headers(&mut headers);
+        w3c_headers(&mut headers);
+        let trace_id = extracted_trace_id(&headers, &tracer, &BOTH);
+        assert_eq!(trace_id.as_deref(), Some(B3_TRACE_ID));
+        let formats = [CarrierFormat::W3C, CarrierFormat::B3];
+        let trace_id = extracted_trace_id(&headers, &tracer, &formats);
+        assert_eq!(trace_id.as_deref(), Some(W3C_TRACE_ID));
+    }
+
+    #[test]
+    fn extract_any_skips_invalid_formats() {
+        let (tracer, _) = ZipkinTracer::new();
+        let mut headers = headers();
+        b3_headers(&mut headers);
+        headers.insert(
+            HeaderName::from_static("traceparent"),
+            HeaderValue::from_static("00-00000000000000000000000000000000-00f067aa0ba902b7-01"),
+        );
+        let formats = [CarrierFormat::W3C, CarrierFormat::B3];
+        let trace_id = extracted_trace_id(&headers, &tracer, &formats);
+        assert_eq!(trace_id.as_deref(), Some(B3_TRACE_ID));
+        let result = HeadersCarrier::extract_any(&headers, &tracer, &[CarrierFormat::W3C]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn deny_list_hides_denied_headers() {
+        let mut headers = headers();
+        let filter = HeaderFilter::Deny(vec!["Authorization".into()]);
+        let mut carrier = HeadersCarrier::with_filter(&mut headers, filter).unwrap();
+        assert!(carrier
+            .items()
+            .iter()
+            .all(|(key, _)| *key != "authorization"));
+        assert_eq!(carrier.get("authorization"), None);
+        carrier.set("authorization", "other");
+        assert_eq!(headers.get("authorization").unwrap(), "secret");
+    }
+}
@@ -0,0 +1,142 @@
+use actix_web::http::header::HeaderMap;
+use actix_web::HttpMessage;
+use actix_web::HttpRequest;
+use opentracingrust::Span;
+use opentracingrust::Tracer;
+use slog::o;
+use slog::Logger;
+
+use super::HeadersCarrier;
+
+/// Request extension to access a `Logger` enriched with the request's trace IDs.
+///
+/// Attached to requests by `TracingMiddleware` and, for Tower services, by `TracingLayer`.
+#[derive(Clone)]
+pub struct RequestLogger(Logger);
+
+impl RequestLogger {
+    pub fn new(logger: Logger) -> RequestLogger {
+        RequestLogger(logger)
+    }
+
+    /// Access the enriched logger.
+    pub fn logger(&self) -> &Logger {
+        &self.0
+    }
+}
+
+/// Return the request's trace enriched logger, if `TracingMiddleware` processed it.
+pub fn request_logger(request: &HttpRequest) -> Option<Logger> {
+    request
+        .extensions()
+        .get::<RequestLogger>()
+        .map(|logger| logger.logger().clone())
+}
+
+/// Trace and span IDs of a request span, attached to requests by `TracingMiddleware`.
+#[derive(Clone, Debug)]
+pub(crate) struct SpanIds {
+    pub trace_id: String,
+    pub span_id: String,
+}
+
+impl SpanIds {
+    /// Take the IDs from the B3 headers the tracer injects for the span's context.
+    ///
+    /// Returns `None` if the tracer does not propagate B3 headers (or injection fails).
+    pub fn of(span: &Span, tracer: &Tracer) -> Option<SpanIds> {
+        let mut headers = HeaderMap::new();
+        HeadersCarrier::inject(span.context(), &mut headers, tracer).ok()?;
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(String::from)
+        };
+        Some(SpanIds {
+            trace_id: header("x-b3-traceid")?,
+            span_id: header("x-b3-spanid")?,
+        })
+    }
+
+    /// Create a child `Logger` with the `trace_id` and `span_id` fields.
+    pub fn logger(&self, logger: &Logger) -> Logger {
+        logger.new(o!(
+            "trace_id" => self.trace_id.clone(),
+            "span_id" => self.span_id.clone(),
+        ))
+    }
+}
+
+/// Create a child `Logger` with the `trace_id` and `span_id` of the given span.
+///
+/// IDs are taken from the B3 headers the tracer injects for the span's context.
+/// If the tracer does not propagate B3 headers (or injection fails) the child
+/// logger is returned without the extra fields.
+pub fn span_logger(logger: &Logger, span: &Span, tracer: &Tracer) -> Logger {
+    match SpanIds::of(span, tracer) {
+        Some(ids) => ids.logger(logger),
+        None => logger.new(o!()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use actix_web::test::call_service;
+    use actix_web::test::init_service;
+    use actix_web::test::TestRequest;
+    use actix_web::web;
+    use actix_web::App;
+    use actix_web::HttpRequest;
+    use actix_web::HttpResponse;
+    use opentracingrust::tracers::NoopTracer;
+    use opentracingrust_zipkin::ZipkinTracer;
+    use replicante_logging::buffer_logger;
+    use slog::info;
+
+    use super::request_logger;
+    use super::span_logger;
+    use crate::TracingMiddleware;
+
+    async fn handler(request: HttpRequest) -> HttpResponse {
+        let logger = request_logger(&request).expect("request logger not attached");
+        info!(logger, "Handling request");
+        HttpResponse::Ok().finish()
+    }
+
+    #[actix_rt::test]
+    async fn request_logs_carry_trace_id() {
+        let (logger, sink) = buffer_logger();
+        let (tracer, _receiver) = ZipkinTracer::new();
+        let app = App::new()
+            .wrap(TracingMiddleware::new(logger, Arc::new(tracer)))
+            .route("/", web::get().to(handler));
+        let app = init_service(app).await;
+        let request = TestRequest::get().uri("/").to_request();
+        let response = call_service(&app, request).await;
+        assert!(response.status().is_success());
+
+        let trace_id = response.headers().get("x-b3-traceid").unwrap();
+        let span_id = response.headers().get("x-b3-spanid").unwrap();
+        let records = sink.records();
+        let record = records
+            .iter()
+            .find(|record| record.message == "Handling request")
+            .expect("handler did not log");
+        assert_eq!(record.fields["trace_id"], trace_id.to_str().unwrap());
+        assert_eq!(record.fields["span_id"], span_id.to_str().unwrap());
+    }
+
+    #[test]
+    fn no_ids_without_b3_headers() {
+        let (logger, sink) = buffer_logger();
+        let (tracer, _receiver) = NoopTracer::new();
+        let span = tracer.span("test");
+        let logger = span_logger(&logger, &span, &tracer);
+        info!(logger, "Test");
+        let records = sink.records();
+        assert!(!records[0].fields.contains_key("trace_id"));
+    }
+}
@@ -1,3 +1,4 @@
+use std::borrow::Cow;
 use std::future::ready;
 use std::future::Ready;
 use std::result::Result;
@@ -8,17 +9,29 @@ use actix_web::dev::Service;
 use actix_web::dev::ServiceRequest;
 use actix_web::dev::ServiceResponse;
 use actix_web::dev::Transform;
+use actix_web::http::header::HeaderName;
+use actix_web::http::header::HeaderValue;
 use actix_web::Error;
 use actix_web::HttpMessage;
 use actix_web::HttpRequest;
 use opentracingrust::Span;
 use opentracingrust::Tracer;
 use slog::error;
+use slog::o;
 use slog::Logger;
 
+use crate::TrustedProxies;
+
 mod carriers;
+mod logger;
 
+pub use self::carriers::CarrierFormat;
+pub use self::carriers::HeaderFilter;
 pub use self::carriers::HeadersCarrier;
+pub use self::logger::request_logger;
+pub use self::logger::span_logger;
+pub use self::logger::RequestLogger;
+pub(crate) use self::logger::SpanIds;
 
 /// Access the request's tracing span.
 pub fn with_request_span<B, R>(request: &mut HttpRequest, block: B) -> R
@@ -30,10 +43,96 @@ where
     block(span)
 }
 
+/// Set a baggage item on the request's tracing span, if any.
+///
+/// Baggage items are propagated to child spans and, for keys configured with
+/// `TracingMiddleware::baggage_headers`, returned as response headers.
+pub fn set_baggage(request: &mut HttpRequest, key: &str, value: &str) {
+    with_request_span(request, |span| {
+        if let Some(span) = span {
+            span.set_baggage_item(key, value);
+        }
+    })
+}
+
+type PathExclusion = Arc<dyn Fn(&str) -> bool + Send + Sync>;
+
+/// Marker appended to truncated span tag values.
+const TAG_ELLIPSIS: &str = "...";
+
+/// Limits applied to request attributes (URI, URL, route params) tagged onto spans.
+///
+/// Span names taken from the request path are truncated to the same maximum length.
+#[derive(Clone, Copy, Debug)]
+pub struct TagLimits {
+    max_length: usize,
+    skip_length: usize,
+}
+
+impl Default for TagLimits {
+    fn default() -> TagLimits {
+        TagLimits {
+            max_length: 1024,
+            skip_length: 64 * 1024,
+        }
+    }
+}
+
+impl TagLimits {
+    /// Truncate values longer than `max_length` bytes and skip values longer than `skip_length`.
+    ///
+    /// Truncated values are marked with a trailing `...` while skipped values are
+    /// replaced by a placeholder reporting their size.
+    ///
+    /// # Panic
+    /// Panics if `max_length` is greater than `skip_length`.
+    pub fn new(max_length: usize, skip_length: usize) -> TagLimits {
+        assert!(
+            max_length <= skip_length,
+            "tag max length ({}) must not exceed the skip length ({})",
+            max_length,
+            skip_length,
+        );
+        TagLimits {
+            max_length,
+            skip_length,
+        }
+    }
+
+    /// Tag the span with the value, truncated or skipped if it exceeds the limits.
+    pub(crate) fn tag(&self, span: &mut Span, key: &str, value: &str) {
+        if value.len() > self.skip_length {
+            span.tag(key, format!("<skipped: {} bytes>", value.len()));
+            return;
+        }
+        span.tag(key, self.truncate(value).into_owned());
+    }
+
+    /// Truncate the value to the maximum length, if needed.
+    pub(crate) fn truncate<'a>(&self, value: &'a str) -> Cow<'a, str> {
+        if value.len() <= self.max_length {
+            return Cow::Borrowed(value);
+        }
+        let mut end = self.max_length;
+        while !value.is_char_boundary(end) {
+            end -= 1;
+        }
+        Cow::Owned(format!("{}{}", &value[..end], TAG_ELLIPSIS))
+    }
+}
+
 /// Actix Web middleware to inject an `opentracingrust::Span` on each request.
+///
+/// Handlers can also access a `Logger` enriched with the span's trace IDs
+/// through `request_logger`.
 pub struct TracingMiddleware {
+    baggage: Arc<Vec<HeaderName>>,
+    exclude: Option<PathExclusion>,
+    filter: HeaderFilter,
     logger: Logger,
     name: Option<String>,
+    proxies: TrustedProxies,
+    tag_limits: TagLimits,
     tracer: Arc<Tracer>,
 }
 
@@ -41,8 +140,13 @@ impl TracingMiddleware {
     /// Inject spans using the request path as then name.
     pub fn new(logger: Logger, tracer: Arc<Tracer>) -> TracingMiddleware {
         TracingMiddleware {
+            baggage: Arc::new(Vec::new()),
+            exclude: None,
+            filter: HeaderFilter::default(),
             logger,
             name: None,
+            proxies: TrustedProxies::default(),
+            tag_limits: TagLimits::default(),
             tracer,
         }
     }
@@ -54,11 +158,86 @@ impl TracingMiddleware {
     {
         let name = Some(name.into());
         TracingMiddleware {
+            baggage: Arc::new(Vec::new()),
+            exclude: None,
+            filter: HeaderFilter::default(),
             logger,
             name,
+            proxies: TrustedProxies::default(),
+            tag_limits: TagLimits::default(),
             tracer,
         }
     }
+
+    /// Copy the given request headers into the span's baggage.
+    ///
+    /// Baggage items with the same names are set as headers on the response.
+    ///
+    /// # Panic
+    /// Panics if any of the given names is not a valid header name.
+    pub fn baggage_headers<I, S>(mut self, headers: I) -> TracingMiddleware
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let headers = headers
+            .into_iter()
+            .map(|name| {
+                HeaderName::from_bytes(name.as_ref().as_bytes())
+                    .expect("invalid baggage header name")
+            })
+            .collect();
+        self.baggage = Arc::new(headers);
+        self
+    }
+
+    /// Skip tracing of requests for paths matching the given predicate.
+    ///
+    /// Excluded requests, such as metrics scrapes and health probes, are passed
+    /// to the wrapped service untouched: no span is created and no trace context
+    /// is injected in the response.
+    pub fn exclude_paths<F>(mut self, predicate: F) -> TracingMiddleware
+    where
+        F: Fn(&str) -> bool + Send + Sync + 'static,
+    {
+        self.exclude = Some(Arc::new(predicate));
+        self
+    }
+
+    /// Limit the size of request attributes tagged onto spans.
+    ///
+    /// By default values are truncated after 1024 bytes and skipped after 64 KiB.
+    pub fn tag_limits(mut self, limits: TagLimits) -> TracingMiddleware {
+        self.tag_limits = limits;
+        self
+    }
+
+    /// Trust the given proxies to report the original request URL and client address.
+    pub fn trusted_proxies(mut self, proxies: TrustedProxies) -> TracingMiddleware {
+        self.proxies = proxies;
+        self
+    }
+
+    /// Limit the headers used to extract and inject trace contexts.
+    pub fn header_filter(mut self, filter: HeaderFilter) -> TracingMiddleware {
+        self.filter = filter;
+        self
+    }
+
+    /// Wrap a service without going through `Transform::new_transform`.
+    pub(crate) fn wrap_service<S>(&self, service: S) -> MiddlewareService<S> {
+        MiddlewareService {
+            baggage: Arc::clone(&self.baggage),
+            exclude: self.exclude.clone(),
+            filter: self.filter.clone(),
+            logger: self.logger.clone(),
+            name: self.name.clone(),
+            proxies: self.proxies.clone(),
+            service,
+            tag_limits: self.tag_limits,
+            tracer: Arc::clone(&self.tracer),
+        }
+    }
 }
 
 // `S` - type of the next service
@@ -76,20 +255,20 @@ where
     type Future = Ready<Result<Self::Transform, Self::InitError>>;
 
     fn new_transform(&self, service: S) -> Self::Future {
-        ready(Ok(MiddlewareService {
-            logger: self.logger.clone(),
-            name: self.name.clone(),
-            service,
-            tracer: Arc::clone(&self.tracer),
-        }))
+        ready(Ok(self.wrap_service(service)))
     }
 }
 
 /// Inner middleware to process requests on behalf of `TracingMiddleware`.
 pub struct MiddlewareService<S> {
+    baggage: Arc<Vec<HeaderName>>,
+    exclude: Option<PathExclusion>,
+    filter: HeaderFilter,
     logger: Logger,
     name: Option<String>,
+    proxies: TrustedProxies,
     service: S,
+    tag_limits: TagLimits,
     tracer: Arc<Tracer>,
 }
 
@@ -106,15 +285,22 @@ where
     forward_ready!(service);
 
     fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        if let Some(exclude) = self.exclude.as_ref() {
+            if exclude(req.path()) {
+                return Box::pin(self.service.call(req));
+            }
+        }
         let logger = self.logger.clone();
+        let limits = self.tag_limits;
         let name = match self.name.as_ref() {
-            None => req.path(),
-            Some(name) => name.as_str(),
+            None => limits.truncate(req.path()),
+            Some(name) => Cow::Borrowed(name.as_str()),
         };
-        let mut span = self.tracer.span(name);
+        let mut span = self.tracer.span(&name);
 
         // Extend the span with a parent and some request attributes.
-        match HeadersCarrier::extract(req.headers_mut(), &self.tracer) {
+        match HeadersCarrier::extract_filtered(req.headers_mut(), &self.tracer, self.filter.clone())
+        {
             Ok(Some(context)) => span.child_of(context),
             Ok(None) => (),
             Err(error) => {
@@ -127,13 +313,39 @@ where
                 );
             }
         };
+        for header in self.baggage.iter() {
+            let value = req
+                .headers()
+                .get(header)
+                .and_then(|value| value.to_str().ok());
+            if let Some(value) = value {
+                span.set_baggage_item(header.as_str(), value);
+            }
+        }
         span.tag("http.route.method", req.method().as_str());
-        span.tag("http.route.uri", req.uri().to_string());
+        limits.tag(&mut span, "http.route.uri", &req.uri().to_string());
+        let origin = self.proxies.origin(req.request());
+        limits.tag(&mut span, "http.url", &origin.url(req.uri()));
+        if let Some(client) = origin.client {
+            span.tag("peer.address", client.to_string());
+        }
         for (param, value) in req.match_info().iter() {
-            span.tag(&format!("http.route.param.{}", param), value);
+            limits.tag(&mut span, &format!("http.route.param.{}", param), value);
         }
 
         // Send the request and handle the span on response.
+        let request_logger = match SpanIds::of(&span, &self.tracer) {
+            Some(ids) => {
+                let request_logger = ids.logger(&self.logger);
+                req.extensions_mut().insert(ids);
+                request_logger
+            }
+            None => self.logger.new(o!()),
+        };
+        req.extensions_mut()
+            .insert(RequestLogger::new(request_logger));
+        let baggage = Arc::clone(&self.baggage);
+        let filter = self.filter.clone();
         let tracer = self.tracer.clone();
         req.extensions_mut().insert(span);
         let response = self.service.call(req);
@@ -141,10 +353,19 @@ where
             let mut response = response.await?;
             let span: Option<Span> = response.request().extensions_mut().remove();
             if let Some(span) = span {
-                let result = HeadersCarrier::inject(
+                let headers = response.response_mut().headers_mut();
+                for (key, value) in span.context().baggage_items() {
+                    let header = baggage.iter().find(|header| header.as_str() == key);
+                    let value = HeaderValue::from_str(value);
+                    if let (Some(header), Ok(value)) = (header, value) {
+                        headers.insert(header.clone(), value);
+                    }
+                }
+                let result = HeadersCarrier::inject_filtered(
                     span.context(),
                     response.response_mut().headers_mut(),
                     &tracer,
+                    filter,
                 );
                 if let Err(error) = result {
                     let error = anyhow::anyhow!(error);
@@ -170,3 +391,147 @@ where
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use actix_web::test::call_service;
+    use actix_web::test::init_service;
+    use actix_web::test::TestRequest;
+    use actix_web::web;
+    use actix_web::App;
+    use actix_web::HttpRequest;
+    use actix_web::HttpResponse;
+    use opentracingrust::tracers::NoopTracer;
+    use opentracingrust::TagValue;
+    use slog::o;
+    use slog::Discard;
+    use slog::Logger;
+
+    use super::set_baggage;
+    use super::TagLimits;
+    use super::TracingMiddleware;
+
+    #[actix_rt::test]
+    async fn excluded_paths_are_not_traced() {
+        let logger = Logger::root(Discard, o!());
+        let (tracer, receiver) = NoopTracer::new();
+        let middleware = TracingMiddleware::new(logger, Arc::new(tracer))
+            .exclude_paths(|path| path == "/metrics" || path.starts_with("/health/"));
+        let app = App::new()
+            .wrap(middleware)
+            .route("/metrics", web::get().to(HttpResponse::Ok))
+            .route("/health/live", web::get().to(HttpResponse::Ok))
+            .route("/api", web::get().to(HttpResponse::Ok));
+        let app = init_service(app).await;
+        for path in ["/metrics", "/health/live"] {
+            let request = TestRequest::get().uri(path).to_request();
+            let response = call_service(&app, request).await;
+            assert!(response.status().is_success());
+        }
+        assert!(receiver.try_recv().is_err());
+
+        let request = TestRequest::get().uri("/api").to_request();
+        call_service(&app, request).await;
+        let span = receiver.try_recv().expect("request span was not finished");
+        assert_eq!(span.name(), "/api");
+    }
+
+    #[actix_rt::test]
+    async fn oversized_tags_are_truncated() {
+        let logger = Logger::root(Discard, o!());
+        let (tracer, receiver) = NoopTracer::new();
+        let middleware =
+            TracingMiddleware::new(logger, Arc::new(tracer)).tag_limits(TagLimits::new(32, 4096));
+        let app = App::new()
+            .wrap(middleware)
+            .default_service(web::to(HttpResponse::Ok));
+        let app = init_service(app).await;
+        let uri = format!("/key?query={}", "q".repeat(100));
+        let request = TestRequest::get().uri(&uri).to_request();
+        call_service(&app, request).await;
+        let span = receiver.try_recv().expect("request span was not finished");
+        assert_eq!(span.name(), "/key");
+        match span.tags().get("http.route.uri") {
+            Some(TagValue::String(value)) => assert_eq!(value, &format!("{}...", &uri[..32])),
+            other => panic!("unexpected http.route.uri tag: {:?}", other),
+        }
+
+        let uri = format!("/{}", "k".repeat(8192));
+        let request = TestRequest::get().uri(&uri).to_request();
+        call_service(&app, request).await;
+        let span = receiver.try_recv().expect("request span was not finished");
+        assert_eq!(span.name(), &format!("{}...", &uri[..32]));
+        match span.tags().get("http.route.uri") {
+            Some(TagValue::String(value)) => assert_eq!(value, "<skipped: 8193 bytes>"),
+            other => panic!("unexpected http.route.uri tag: {:?}", other),
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "tag max length (100) must not exceed the skip length (10)")]
+    fn tag_limits_reject_max_over_skip() {
+        TagLimits::new(100, 10);
+    }
+
+    #[actix_rt::test]
+    async fn existing_tracer_produces_spans() {
+        let logger = Logger::root(Discard, o!());
+        let (tracer, receiver) = NoopTracer::new();
+        let tracer = Arc::new(tracer);
+        let app = App::new()
+            .wrap(TracingMiddleware::with_name(logger, tracer, "test"))
+            .route("/", web::get().to(HttpResponse::Ok));
+        let app = init_service(app).await;
+        let request = TestRequest::get().uri("/").to_request();
+        let response = call_service(&app, request).await;
+        assert!(response.status().is_success());
+        let span = receiver.try_recv().expect("request span was not finished");
+        assert_eq!(span.name(), "test");
+    }
+
+    async fn tenant_handler(mut req: HttpRequest) -> HttpResponse {
+        set_baggage(&mut req, "x-tenant-id", "acme");
+        set_baggage(&mut req, "internal", "hidden");
+        HttpResponse::Ok().finish()
+    }
+
+    #[actix_rt::test]
+    async fn baggage_set_by_handler_is_injected() {
+        let logger = Logger::root(Discard, o!());
+        let (tracer, receiver) = NoopTracer::new();
+        let middleware = TracingMiddleware::new(logger, Arc::new(tracer))
+            .baggage_headers(vec!["X-Tenant-Id", "X-Request-Origin"]);
+        let app = App::new()
+            .wrap(middleware)
+            .route("/", web::get().to(tenant_handler));
+        let app = init_service(app).await;
+        let request = TestRequest::get()
+            .uri("/")
+            .insert_header(("X-Request-Origin", "cli"))
+            .to_request();
+        let response = call_service(&app, request).await;
+        assert!(response.status().is_success());
+        let headers = response.headers();
+        assert_eq!(headers.get("x-tenant-id").unwrap(), "acme");
+        assert_eq!(headers.get("x-request-origin").unwrap(), "cli");
+        assert!(headers.get("internal").is_none());
+
+        let span = receiver.try_recv().expect("request span was not finished");
+        let mut baggage: Vec<(&str, &str)> = span
+            .context()
+            .baggage_items()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect();
+        baggage.sort_unstable();
+        assert_eq!(
+            baggage,
+            vec![
+                ("internal", "hidden"),
+                ("x-request-origin", "cli"),
+                ("x-tenant-id", "acme"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,284 @@
+use std::fmt;
+use std::future::ready;
+use std::future::Ready;
+use std::sync::Arc;
+
+use actix_web::body::EitherBody;
+use actix_web::dev::forward_ready;
+use actix_web::dev::Service;
+use actix_web::dev::ServiceRequest;
+use actix_web::dev::ServiceResponse;
+use actix_web::dev::Transform;
+use actix_web::http::StatusCode;
+use actix_web::Error;
+use actix_web::HttpMessage;
+use actix_web::HttpRequest;
+
+use crate::errors::api_error_response;
+use crate::errors::ApiVersionDisabled;
+use crate::APIFlags;
+use crate::RootDescriptor;
+
+/// Name of the API version a request was sent to, available once `ApiVersionMiddleware` runs.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct ApiVersion(String);
+
+impl ApiVersion {
+    /// Name of the API version.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for ApiVersion {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.write_str(&self.0)
+    }
+}
+
+/// Access the API version the request was sent to, if known.
+pub fn api_version(request: &HttpRequest) -> Option<ApiVersion> {
+    request.extensions().get::<ApiVersion>().cloned()
+}
+
+/// API version root known to `ApiVersionMiddleware`.
+#[derive(Clone)]
+struct VersionRoot {
+    enabled: bool,
+    name: String,
+    prefix: &'static str,
+}
+
+impl VersionRoot {
+    /// Check if the path is under this root.
+    fn matches(&self, path: &str) -> bool {
+        match path.strip_prefix(self.prefix) {
+            None => false,
+            Some(rest) => rest.is_empty() || rest.starts_with('/') || self.prefix.ends_with('/'),
+        }
+    }
+}
+
+/// Actix Web middleware to record the API version of requests based on `RootDescriptor`s.
+///
+/// Requests are matched to the version with the longest prefix containing the request path.
+/// The matched version is attached to the request, for use with `api_version`, and
+/// included in request logs by the `LoggingMiddleware`.
+///
+/// Requests to versions disabled by the `APIFlags` are rejected with an `ApiVersionDisabled`
+/// JSON error, with status `410 Gone` unless configured otherwise.
+pub struct ApiVersionMiddleware {
+    disabled_status: StatusCode,
+    roots: Arc<Vec<VersionRoot>>,
+}
+
+impl Default for ApiVersionMiddleware {
+    fn default() -> Self {
+        ApiVersionMiddleware::new()
+    }
+}
+
+impl ApiVersionMiddleware {
+    pub fn new() -> ApiVersionMiddleware {
+        ApiVersionMiddleware {
+            disabled_status: StatusCode::GONE,
+            roots: Arc::new(Vec::new()),
+        }
+    }
+
+    /// Status code of responses to requests for disabled versions.
+    pub fn disabled_status(mut self, status: StatusCode) -> ApiVersionMiddleware {
+        self.disabled_status = status;
+        self
+    }
+
+    /// Add an API version root, checking if it is enabled by the given flags.
+    pub fn version<R, S>(mut self, name: S, root: &R, flags: &APIFlags) -> ApiVersionMiddleware
+    where
+        R: RootDescriptor,
+        S: Into<String>,
+    {
+        let root = VersionRoot {
+            enabled: root.enabled(flags),
+            name: name.into(),
+            prefix: root.prefix(),
+        };
+        Arc::make_mut(&mut self.roots).push(root);
+        self
+    }
+}
+
+// `S` - type of the next service
+// `B` - type of response's body
+impl<S, B> Transform<S, ServiceRequest> for ApiVersionMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = MiddlewareService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(MiddlewareService {
+            disabled_status: self.disabled_status,
+            roots: Arc::clone(&self.roots),
+            service,
+        }))
+    }
+}
+
+/// Inner middleware to process requests on behalf of `ApiVersionMiddleware`.
+pub struct MiddlewareService<S> {
+    disabled_status: StatusCode,
+    roots: Arc<Vec<VersionRoot>>,
+    service: S,
+}
+
+impl<S, B> Service<ServiceRequest> for MiddlewareService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = crate::LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let root = self
+            .roots
+            .iter()
+            .filter(|root| root.matches(req.path()))
+            .max_by_key(|root| root.prefix.len());
+        if let Some(root) = root {
+            req.extensions_mut().insert(ApiVersion(root.name.clone()));
+            if !root.enabled {
+                let error = ApiVersionDisabled::new(root.name.clone(), self.disabled_status);
+                let response = api_error_response(&error).map_into_right_body();
+                let response = req.into_response(response);
+                return Box::pin(async move { Ok(response) });
+            }
+        }
+
+        let response = self.service.call(req);
+        Box::pin(async move {
+            let response = response.await?;
+            Ok(response.map_into_left_body())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::StatusCode;
+    use actix_web::test::call_service;
+    use actix_web::test::init_service;
+    use actix_web::test::read_body_json;
+    use actix_web::test::TestRequest;
+    use actix_web::web;
+    use actix_web::App;
+    use actix_web::HttpRequest;
+    use actix_web::HttpResponse;
+    use replicante_logging::buffer_logger;
+    use serde_json::Value;
+
+    use super::api_version;
+    use super::ApiVersionMiddleware;
+    use crate::APIFlags;
+    use crate::LoggingMiddleware;
+    use crate::RootDescriptor;
+
+    enum Version {
+        V1,
+        V2,
+    }
+
+    impl RootDescriptor for Version {
+        fn enabled(&self, flags: &APIFlags) -> bool {
+            match self {
+                Version::V1 => flags.get("v1").copied().unwrap_or(true),
+                Version::V2 => true,
+            }
+        }
+
+        fn prefix(&self) -> &'static str {
+            match self {
+                Version::V1 => "/api/v1",
+                Version::V2 => "/api/v2",
+            }
+        }
+    }
+
+    async fn version(request: HttpRequest) -> HttpResponse {
+        let version = api_version(&request).map(|version| version.to_string());
+        HttpResponse::Ok().json(version)
+    }
+
+    fn middleware(flags: &APIFlags) -> ApiVersionMiddleware {
+        ApiVersionMiddleware::new()
+            .version("v1", &Version::V1, flags)
+            .version("v2", &Version::V2, flags)
+    }
+
+    #[actix_rt::test]
+    async fn enabled_version_is_labelled() {
+        let (logger, sink) = buffer_logger();
+        let app = App::new()
+            .wrap(middleware(&APIFlags::new()))
+            .wrap(LoggingMiddleware::new(logger))
+            .route("/api/v2/test", web::get().to(version))
+            .route("/other", web::get().to(version));
+        let app = init_service(app).await;
+
+        let request = TestRequest::get().uri("/api/v2/test").to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: Value = read_body_json(response).await;
+        assert_eq!(body, "v2");
+
+        let request = TestRequest::get().uri("/other").to_request();
+        let response = call_service(&app, request).await;
+        let body: Value = read_body_json(response).await;
+        assert_eq!(body, Value::Null);
+
+        let records = sink.records();
+        assert_eq!(records[0].fields["api_version"], "v2");
+        assert_eq!(records[1].fields["api_version"], "");
+    }
+
+    #[actix_rt::test]
+    async fn disabled_version_is_rejected() {
+        let mut flags = APIFlags::new();
+        flags.insert("v1", false);
+        let app = App::new()
+            .wrap(middleware(&flags).disabled_status(StatusCode::NOT_FOUND))
+            .route("/api/v1/test", web::get().to(version));
+        let app = init_service(app).await;
+
+        let request = TestRequest::get().uri("/api/v1/test").to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body: Value = read_body_json(response).await;
+        assert_eq!(body["variant"], "ApiVersionDisabled");
+        assert_eq!(body["error"], "API version 'v1' is disabled");
+    }
+
+    #[actix_rt::test]
+    async fn disabled_version_default_status() {
+        let mut flags = APIFlags::new();
+        flags.insert("v1", false);
+        let app = App::new()
+            .wrap(middleware(&flags))
+            .route("/api/v1", web::get().to(version));
+        let app = init_service(app).await;
+        let request = TestRequest::get().uri("/api/v1").to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::GONE);
+    }
+}
@@ -0,0 +1,103 @@
+use serde::de::DeserializeOwned;
+use serde_json::Map;
+use serde_json::Value;
+
+/// Merge an `overlay` configuration document onto a `base` document.
+///
+/// Values in the overlay take precedence over values in the base:
+///
+///   * Objects (maps) are merged recursively, key by key.
+///   * Any other value (scalars, arrays and `null`) replaces the base value.
+pub fn merge(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(current) => merge(current, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// Merge partial configuration documents, in order of precedence, and decode the result.
+///
+/// Layers are merged with `merge` starting from an empty object so later layers
+/// (for example files and then environment overrides) take precedence over earlier
+/// ones (for example defaults). Options missing from all layers use serde defaults.
+pub fn merge_layers<T, I>(layers: I) -> serde_json::Result<T>
+where
+    T: DeserializeOwned,
+    I: IntoIterator<Item = Value>,
+{
+    let mut config = Value::Object(Map::new());
+    for layer in layers {
+        merge(&mut config, layer);
+    }
+    serde_json::from_value(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use serde_json::json;
+
+    use super::merge;
+    use super::merge_layers;
+
+    #[test]
+    fn scalars_override() {
+        let mut base = json!({"level": "info", "async": true, "limit": 10});
+        merge(&mut base, json!({"level": "debug", "limit": null}));
+        assert_eq!(
+            base,
+            json!({"level": "debug", "async": true, "limit": null})
+        );
+    }
+
+    #[test]
+    fn maps_deep_merge() {
+        let mut base = json!({
+            "backend": "zipkin",
+            "options": {"url": "http://base", "headers": {"a": "1", "b": "2"}},
+        });
+        let overlay = json!({
+            "options": {"headers": {"b": "3", "c": "4"}, "flush_count": 5},
+        });
+        merge(&mut base, overlay);
+        let expected = json!({
+            "backend": "zipkin",
+            "options": {
+                "flush_count": 5,
+                "headers": {"a": "1", "b": "3", "c": "4"},
+                "url": "http://base",
+            },
+        });
+        assert_eq!(base, expected);
+    }
+
+    #[test]
+    fn arrays_and_types_replace() {
+        let mut base = json!({"list": [1, 2, 3], "value": {"nested": true}});
+        merge(&mut base, json!({"list": [4], "value": "flat"}));
+        assert_eq!(base, json!({"list": [4], "value": "flat"}));
+    }
+
+    #[test]
+    fn layers_in_order() {
+        let layers = vec![
+            json!({"a": "default", "b": "default"}),
+            json!({"b": "file", "c": "file"}),
+            json!({"c": "env"}),
+        ];
+        let config: BTreeMap<String, String> = merge_layers(layers).unwrap();
+        assert_eq!(config["a"], "default");
+        assert_eq!(config["b"], "file");
+        assert_eq!(config["c"], "env");
+    }
+}
@@ -0,0 +1,76 @@
+use actix_web::http::StatusCode;
+use actix_web::HttpResponse;
+use replicante_util_actixweb::errors::ErrorBody;
+
+use super::SerializableFail;
+
+impl SerializableFail {
+    /// Convert the error into a JSON HTTP response with the given status.
+    ///
+    /// The response is built by `replicante_util_actixweb::errors::ErrorBody::into_response`
+    /// so the JSON body is pretty-printed if `replicante_util_actixweb::errors::pretty_errors`
+    /// says so. `replicante_util_actixweb::errors::api_error_response` selects the status
+    /// based on the error `variant` instead.
+    pub fn into_response(self, status: StatusCode) -> HttpResponse {
+        ErrorBody::from(self).into_response(status)
+    }
+
+    /// Encode the error as compact or pretty-printed JSON.
+    pub fn to_json(&self, pretty: bool) -> String {
+        ErrorBody::from(self.clone()).to_json(pretty)
+    }
+}
+
+impl From<SerializableFail> for ErrorBody {
+    fn from(error: SerializableFail) -> ErrorBody {
+        ErrorBody {
+            error: error.error,
+            layers: error.layers,
+            trace: error.trace,
+            variant: error.variant,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::body::to_bytes;
+    use actix_web::http::StatusCode;
+
+    use super::SerializableFail;
+
+    fn error() -> SerializableFail {
+        SerializableFail {
+            error: "not found".into(),
+            layers: vec!["not found".into()],
+            trace: None,
+            variant: Some("NotFound".into()),
+        }
+    }
+
+    #[actix_rt::test]
+    async fn into_response() {
+        let response = error().into_response(StatusCode::NOT_FOUND);
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body = to_bytes(response.into_body()).await.unwrap();
+        let body: SerializableFail = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body, error());
+    }
+
+    #[test]
+    fn to_json_compact() {
+        let body = error().to_json(false);
+        assert_eq!(
+            body,
+            r#"{"error":"not found","layers":["not found"],"trace":null,"variant":"NotFound"}"#
+        );
+    }
+
+    #[test]
+    fn to_json_pretty() {
+        let body = error().to_json(true);
+        assert!(body.starts_with("{\n  \"error\": \"not found\",\n"));
+        let body: SerializableFail = serde_json::from_str(&body).unwrap();
+        assert_eq!(body, error());
+    }
+}
@@ -1,4 +1,15 @@
+use std::fmt;
+
 use failure::Fail;
+use sentry::integrations::failure::event_from_fail;
+use sentry::protocol::Map;
+use sentry::protocol::Value;
+use slog::b;
+use slog::record;
+use slog::Key;
+use slog::Level;
+use slog::Serializer;
+use slog::KV;
 
 /// Capture a [`Fail`] and prevent propagation.
 ///
@@ -10,6 +21,9 @@ use failure::Fail;
 ///   * Reported to sentry (if enabled)
 ///   * Logged to the provided logger.
 ///
+/// Structured logging fields are also attached to the sentry event as `extra` data.
+/// Because of this, expressions in the key/value section are evaluated twice.
+///
 /// # Example
 /// ```ignore
 /// use replicante_util_failure::capture_fail;
@@ -23,10 +37,21 @@ use failure::Fail;
 /// [`Fail`]: https://docs.rs/failure/0.1.5/failure/trait.Fail.html
 #[macro_export]
 macro_rules! capture_fail(
-    ($error:expr, $($args:tt)+) => {
+    // Split the log message arguments from the structured key/value pairs.
+    (@split $error:expr, [$($args:tt)+] ; $($kv:tt)+) => {
+        $crate::capture_fail_inner_kv($error, &slog::b!($($kv)+));
+        slog::error!($($args)+; $($kv)+);
+    };
+    (@split $error:expr, [$($args:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::capture_fail!(@split $error, [$($args)* $next] $($rest)*)
+    };
+    (@split $error:expr, [$($args:tt)+]) => {
         $crate::capture_fail_inner($error);
         slog::error!($($args)+);
-    }
+    };
+    ($error:expr, $($args:tt)+) => {
+        $crate::capture_fail!(@split $error, [] $($args)+)
+    };
 );
 
 /// Helper function called from the `capture_fail` macro for extra processing.
@@ -35,12 +60,78 @@ pub fn capture_fail_inner(error: &dyn Fail) {
     sentry::integrations::failure::capture_fail(error);
 }
 
+/// Helper function called from the `capture_fail` macro when structured fields are given.
+#[doc(hidden)]
+pub fn capture_fail_inner_kv(error: &dyn Fail, kv: &dyn KV) {
+    let mut event = event_from_fail(error);
+    let mut extra = ExtraSerializer::default();
+    let args = format_args!("");
+    let record = record!(Level::Error, "", &args, b!());
+    // Serializing into a map can't fail.
+    let _ = kv.serialize(&record, &mut extra);
+    event.extra = extra.0;
+    sentry::capture_event(event);
+}
+
+/// Collect structured logging fields into sentry's `extra` map.
+#[derive(Default)]
+struct ExtraSerializer(Map<String, Value>);
+
+/// Implement `Serializer::emit_*` methods for types that convert into a `Value`.
+macro_rules! emit_value(
+    ($($method:ident => $type:ty),+ $(,)?) => {
+        $(
+            fn $method(&mut self, key: Key, value: $type) -> slog::Result {
+                self.0.insert(key.into(), Value::from(value));
+                Ok(())
+            }
+        )+
+    };
+);
+
+impl Serializer for ExtraSerializer {
+    fn emit_arguments(&mut self, key: Key, value: &fmt::Arguments) -> slog::Result {
+        self.0.insert(key.into(), Value::from(value.to_string()));
+        Ok(())
+    }
+
+    fn emit_none(&mut self, key: Key) -> slog::Result {
+        self.0.insert(key.into(), Value::Null);
+        Ok(())
+    }
+
+    fn emit_unit(&mut self, key: Key) -> slog::Result {
+        self.0.insert(key.into(), Value::Null);
+        Ok(())
+    }
+
+    emit_value!(
+        emit_bool => bool,
+        emit_f32 => f32,
+        emit_f64 => f64,
+        emit_i8 => i8,
+        emit_i16 => i16,
+        emit_i32 => i32,
+        emit_i64 => i64,
+        emit_isize => isize,
+        emit_str => &str,
+        emit_u8 => u8,
+        emit_u16 => u16,
+        emit_u32 => u32,
+        emit_u64 => u64,
+        emit_usize => usize,
+    );
+}
+
 #[cfg(test)]
 mod tests {
     use slog::o;
     use slog::Discard;
     use slog::Logger;
 
+    use sentry::protocol::Value;
+    use sentry::test::with_captured_events;
+
     use super::super::failure_info;
 
     #[test]
@@ -51,4 +142,17 @@ mod tests {
         capture_fail!(error.as_fail(), logger, "logging: {}", "test"; failure_info(error.as_fail()));
         capture_fail!(error.as_fail(), logger, "logging: {}", "test"; "key" => ?"value");
     }
+
+    #[test]
+    fn capture_with_extra() {
+        let logger = Logger::root(Discard, o!());
+        let error = failure::err_msg("test");
+        let events = with_captured_events(|| {
+            capture_fail!(error.as_fail(), logger, "logging"; "cluster" => "c1", "nodes" => 3);
+        });
+        assert_eq!(events.len(), 1);
+        let extra = &events[0].extra;
+        assert_eq!(extra.get("cluster"), Some(&Value::from("c1")));
+        assert_eq!(extra.get("nodes"), Some(&Value::from(3)));
+    }
 }
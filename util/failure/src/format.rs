@@ -1,17 +1,40 @@
+use std::fmt;
 use std::fmt::Write;
 
 use failure::Fail;
 use serde_derive::Deserialize;
 use serde_derive::Serialize;
 
+/// Default maximum number of causes inspected when formatting errors.
+pub const DEFAULT_CAUSES_LIMIT: usize = 32;
+
+/// Maximum number of causes counted past the limit before reporting `N+ more`.
+const MORE_CAUSES_LIMIT: usize = 100;
+
 /// Format the given `Fail` for display to the user.
+///
+/// At most `DEFAULT_CAUSES_LIMIT` causes are included, see `format_fail_with_limit`.
 pub fn format_fail(fail: &dyn Fail) -> String {
+    format_fail_with_limit(fail, DEFAULT_CAUSES_LIMIT)
+}
+
+/// Format the given `Fail` for display to the user including at most `limit` causes.
+///
+/// When the chain of causes is longer than `limit` a `... (N more)` marker is appended.
+/// Causes past the limit are counted up to a fixed bound, after which `... (N+ more)`
+/// is reported, and the backtrace of the deepest included cause is shown.
+pub fn format_fail_with_limit(fail: &dyn Fail, limit: usize) -> String {
     let mut message = String::new();
     write!(message, "Error: {}", fail).expect("failed to format error message");
-    for cause in fail.iter_causes() {
+    let (causes, more) = limited_causes(fail, limit);
+    for cause in &causes {
         write!(message, "\n    Caused by: {}", cause).expect("failed to format error message");
     }
-    let bt = match fail.find_root_cause().backtrace() {
+    if more > 0 {
+        write!(message, "\n    {}", more_marker(more)).expect("failed to format error message");
+    }
+    let deepest = causes.last().copied().unwrap_or(fail);
+    let bt = match deepest.backtrace() {
         None => None,
         Some(ref bt) if bt.to_string() == "" => None,
         Some(bt) => Some(bt),
@@ -39,11 +62,20 @@ pub struct SerializableFail {
     pub variant: Option<String>,
 }
 
-impl<E: Fail> From<&E> for SerializableFail {
-    fn from(error: &E) -> SerializableFail {
-        let layers = <dyn Fail>::iter_chain(error)
+impl SerializableFail {
+    /// Create a view of the given error including at most `limit` causes in its layers.
+    ///
+    /// When the chain of causes is longer than `limit` a `... (N more)` marker
+    /// is added as the last layer.
+    pub fn with_limit<E: Fail>(error: &E, limit: usize) -> SerializableFail {
+        let (causes, more) = limited_causes(error, limit);
+        let mut layers: Vec<String> = std::iter::once(error as &dyn Fail)
+            .chain(causes)
             .map(ToString::to_string)
             .collect();
+        if more > 0 {
+            layers.push(more_marker(more));
+        }
         let trace = match error.backtrace().map(ToString::to_string) {
             None => None,
             Some(ref bt) if bt.is_empty() => None,
@@ -57,6 +89,84 @@ impl<E: Fail> From<&E> for SerializableFail {
             variant,
         }
     }
+
+    /// Reconstruct an error from this view so it can be propagated as a `Fail`.
+    ///
+    /// The returned error displays the original message, exposes the layers
+    /// as its chain of causes and reports the original variant as its name.
+    /// Backtraces can't be reconstructed and are not available.
+    pub fn into_error(self) -> RemoteError {
+        let mut layers = self.layers;
+        if layers.first() == Some(&self.error) {
+            layers.remove(0);
+        }
+        let cause = layers.into_iter().rev().fold(None, |cause, message| {
+            Some(Box::new(RemoteError {
+                cause,
+                message,
+                variant: None,
+            }))
+        });
+        RemoteError {
+            cause,
+            message: self.error,
+            variant: self.variant,
+        }
+    }
+}
+
+impl fmt::Display for SerializableFail {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.error)
+    }
+}
+
+impl<E: Fail> From<&E> for SerializableFail {
+    fn from(error: &E) -> SerializableFail {
+        SerializableFail::with_limit(error, DEFAULT_CAUSES_LIMIT)
+    }
+}
+
+/// Collect at most `limit` causes of `fail` and count how many were left out.
+///
+/// Causes left out are counted up to `MORE_CAUSES_LIMIT + 1` so that long
+/// (or cyclic) chains are never walked in full.
+pub(crate) fn limited_causes(fail: &dyn Fail, limit: usize) -> (Vec<&dyn Fail>, usize) {
+    let mut iter = fail.iter_causes();
+    let causes = iter.by_ref().take(limit).collect();
+    (causes, iter.take(MORE_CAUSES_LIMIT + 1).count())
+}
+
+/// Marker reported in place of causes that were left out.
+pub(crate) fn more_marker(more: usize) -> String {
+    match more > MORE_CAUSES_LIMIT {
+        true => format!("... ({}+ more)", MORE_CAUSES_LIMIT),
+        false => format!("... ({} more)", more),
+    }
+}
+
+/// Error reconstructed from a `SerializableFail`, usually received over the network.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RemoteError {
+    cause: Option<Box<RemoteError>>,
+    message: String,
+    variant: Option<String>,
+}
+
+impl fmt::Display for RemoteError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.write_str(&self.message)
+    }
+}
+
+impl Fail for RemoteError {
+    fn name(&self) -> Option<&str> {
+        self.variant.as_deref()
+    }
+
+    fn cause(&self) -> Option<&dyn Fail> {
+        self.cause.as_ref().map(|cause| cause.as_ref() as &dyn Fail)
+    }
 }
 
 #[cfg(test)]
@@ -65,6 +175,7 @@ mod test {
     use failure::Fail;
 
     use super::format_fail;
+    use super::format_fail_with_limit;
     use super::SerializableFail;
 
     #[test]
@@ -105,4 +216,102 @@ mod test {
         );
         assert_eq!(error.trace, None);
     }
+
+    #[test]
+    fn serializable_fail_round_trip() {
+        let error = err_msg("test").context("chained").context("failures");
+        let original = SerializableFail::from(&error);
+        let remote = original.clone().into_error();
+        assert_eq!(remote.to_string(), "failures");
+        assert_eq!(
+            format_fail(&remote),
+            "Error: failures\n    Caused by: chained\n    Caused by: test"
+        );
+        // Backtraces are captured again on conversion (when enabled) so compare the rest.
+        let round_trip = SerializableFail::from(&remote);
+        assert_eq!(round_trip.error, original.error);
+        assert_eq!(round_trip.layers, original.layers);
+        assert_eq!(round_trip.variant, original.variant);
+    }
+
+    #[test]
+    fn serializable_fail_without_layers() {
+        let error = SerializableFail {
+            error: "remote".into(),
+            layers: Vec::new(),
+            trace: None,
+            variant: Some("Remote".into()),
+        };
+        let remote = error.into_error();
+        assert_eq!(remote.name(), Some("Remote"));
+        assert!(remote.cause().is_none());
+    }
+
+    fn deep_error(depth: usize) -> failure::Error {
+        let mut error = err_msg("root");
+        for idx in 0..depth {
+            error = error.context(format!("layer {}", idx)).into();
+        }
+        error
+    }
+
+    #[test]
+    fn format_fail_truncates_causes() {
+        let error = deep_error(100);
+        let msg = format_fail(error.as_fail());
+        assert_eq!(
+            msg.matches("Caused by:").count(),
+            super::DEFAULT_CAUSES_LIMIT
+        );
+        assert!(msg.ends_with("\n    ... (68 more)"));
+    }
+
+    #[test]
+    fn format_fail_with_custom_limit() {
+        let error = deep_error(3);
+        let msg = format_fail_with_limit(error.as_fail(), 1);
+        assert_eq!(
+            msg,
+            "Error: layer 2\n    Caused by: layer 1\n    ... (2 more)"
+        );
+    }
+
+    /// Error that is its own cause, with an infinite chain of causes.
+    #[derive(Debug)]
+    struct Cycle;
+
+    impl std::fmt::Display for Cycle {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            write!(f, "cycle")
+        }
+    }
+
+    impl Fail for Cycle {
+        fn cause(&self) -> Option<&dyn Fail> {
+            Some(self)
+        }
+    }
+
+    #[test]
+    fn format_fail_bounds_counted_causes() {
+        let error = deep_error(500);
+        let msg = format_fail(error.as_fail());
+        assert!(msg.ends_with("\n    ... (100+ more)"));
+
+        // A cyclic chain of causes never ends but formatting does.
+        let msg = format_fail(&Cycle);
+        assert_eq!(msg.matches("Caused by: cycle").count(), 32);
+        assert!(msg.ends_with("\n    ... (100+ more)"));
+        let error = SerializableFail::from(&Cycle);
+        assert_eq!(error.layers.last().unwrap(), "... (100+ more)");
+    }
+
+    #[test]
+    fn serializable_fail_truncates_layers() {
+        let error = deep_error(99).context("layer 99".to_string());
+        let error = SerializableFail::from(&error);
+        assert_eq!(error.layers.len(), super::DEFAULT_CAUSES_LIMIT + 2);
+        assert_eq!(error.layers.first().unwrap(), "layer 99");
+        assert_eq!(error.layers.last().unwrap(), "... (68 more)");
+    }
 }
@@ -1,9 +1,17 @@
+#[cfg(feature = "with_actixweb")]
+mod actixweb;
 mod capture;
 mod format;
 mod log;
 
 #[doc(hidden)]
 pub use self::capture::capture_fail_inner;
+#[doc(hidden)]
+pub use self::capture::capture_fail_inner_kv;
 pub use self::format::format_fail;
+pub use self::format::format_fail_with_limit;
+pub use self::format::RemoteError;
 pub use self::format::SerializableFail;
+pub use self::format::DEFAULT_CAUSES_LIMIT;
 pub use self::log::failure_info;
+pub use self::log::failure_info_with_limit;
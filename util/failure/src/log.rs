@@ -4,23 +4,42 @@ use slog::Record;
 use slog::Serializer;
 use slog::KV;
 
+use crate::format::limited_causes;
+use crate::format::more_marker;
+use crate::format::DEFAULT_CAUSES_LIMIT;
+
 /// Extract failure information to be added to structured logging.
+///
+/// At most `DEFAULT_CAUSES_LIMIT` causes are inspected, see `failure_info_with_limit`.
 pub fn failure_info(fail: &dyn Fail) -> FailureInfo {
-    let trace = match <dyn Fail>::find_root_cause(fail).backtrace() {
+    failure_info_with_limit(fail, DEFAULT_CAUSES_LIMIT)
+}
+
+/// Extract failure information inspecting at most `limit` causes.
+///
+/// When the chain of causes is longer than `limit` the deepest inspected cause
+/// (and its backtrace) is reported in place of the root cause, followed by
+/// a `... (N more)` marker. Very long chains report `... (N+ more)` and
+/// `error_layers` is then the number of layers inspected.
+pub fn failure_info_with_limit(fail: &dyn Fail, limit: usize) -> FailureInfo {
+    let (causes, more) = limited_causes(fail, limit);
+    let deepest = causes.last().copied().unwrap_or(fail);
+    let trace = match deepest.backtrace() {
         None => None,
         Some(ref bt) if bt.to_string() == "" => None,
         Some(bt) => Some(bt.to_string()),
     };
+    let cause = causes.last().map(|cause| match more {
+        0 => cause.to_string(),
+        more => format!("{} {}", cause, more_marker(more)),
+    });
     FailureInfo {
-        cause: fail
-            .cause()
-            .map(|cause| cause.find_root_cause().to_string()),
-        cause_name: fail
-            .cause()
-            .map(|cause| cause.find_root_cause())
-            .and_then(Fail::name)
+        cause,
+        cause_name: causes
+            .last()
+            .and_then(|cause| cause.name())
             .map(String::from),
-        layers: <dyn Fail>::iter_chain(fail).count(),
+        layers: causes.len() + more + 1,
         message: fail.to_string(),
         name: fail.name().map(String::from),
         trace,
@@ -63,6 +82,7 @@ mod test {
     use failure::Fail;
 
     use super::failure_info;
+    use super::failure_info_with_limit;
 
     #[test]
     fn flat_error() {
@@ -84,4 +104,16 @@ mod test {
         assert_eq!(info.layers, 4);
         assert_eq!(info.message, "test");
     }
+
+    #[test]
+    fn truncated_errors() {
+        let error = err_msg("errors")
+            .context("more")
+            .context("some")
+            .context("test");
+        let info = failure_info_with_limit(&error, 1);
+        assert_eq!(info.cause, Some("some ... (2 more)".into()));
+        assert_eq!(info.layers, 4);
+        assert_eq!(info.message, "test");
+    }
 }
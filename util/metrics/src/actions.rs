@@ -0,0 +1,141 @@
+//! Prometheus metrics about actions, partitioned by action kind and state.
+use prometheus::core::Collector;
+use prometheus::HistogramOpts;
+use prometheus::HistogramVec;
+use prometheus::IntCounterVec;
+use prometheus::Opts;
+use prometheus::Registry;
+use prometheus::Result;
+
+use replicante_models_agent::actions::ActionModel;
+
+/// Label names used to partition action metrics.
+const ACTION_LABELS: [&str; 2] = ["kind", "state"];
+
+/// Count actions and time their execution by action kind and state.
+#[derive(Clone)]
+pub struct ActionMetrics {
+    actions: IntCounterVec,
+    duration: HistogramVec,
+}
+
+impl ActionMetrics {
+    /// Create a new set of action metrics with the given prefix.
+    ///
+    /// An error is returned if the prefix results in invalid metric names.
+    pub fn new<S>(prefix: S) -> Result<ActionMetrics>
+    where
+        S: AsRef<str>,
+    {
+        let prefix = prefix.as_ref();
+        let actions = IntCounterVec::new(
+            Opts::new(
+                format!("{}_actions", prefix),
+                "Number of actions observed by kind and state",
+            ),
+            &ACTION_LABELS,
+        )?;
+        let duration = HistogramVec::new(
+            HistogramOpts::new(
+                format!("{}_action_duration", prefix),
+                "Duration (in seconds) of finished actions by kind and final state",
+            )
+            .buckets(vec![
+                0.1, 0.5, 1.0, 5.0, 10.0, 30.0, 60.0, 300.0, 900.0, 3600.0,
+            ]),
+            &ACTION_LABELS,
+        )?;
+        Ok(ActionMetrics { actions, duration })
+    }
+
+    /// Record the action in its current state.
+    ///
+    /// Actions are counted for every call so callers should record actions once per
+    /// state transition. The duration of finished actions is observed as well.
+    pub fn record(&self, action: &ActionModel) {
+        let labels = [action.kind.as_str(), action.state.as_str()];
+        self.actions.with_label_values(&labels).inc();
+        if !action.state.is_finished() {
+            return;
+        }
+        // Actions finished before they were created (clock skew) are not timed.
+        if let Some(duration) = action.duration().and_then(|d| d.to_std().ok()) {
+            self.duration
+                .with_label_values(&labels)
+                .observe(duration.as_secs_f64());
+        }
+    }
+
+    /// Register this set of metrics with the registry.
+    ///
+    /// Registration is attempted for all metrics, even if some of them fail,
+    /// and the first error encountered is returned.
+    pub fn register(&self, registry: &Registry) -> Result<()> {
+        let metrics: Vec<Box<dyn Collector>> = vec![
+            Box::new(self.actions.clone()),
+            Box::new(self.duration.clone()),
+        ];
+        let mut result = Ok(());
+        for metric in metrics {
+            let registered = registry.register(metric);
+            if result.is_ok() {
+                result = registered;
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use chrono::TimeZone;
+    use chrono::Utc;
+    use prometheus::Registry;
+    use serde_json::json;
+
+    use replicante_models_agent::actions::ActionModel;
+    use replicante_models_agent::actions::ActionRequester;
+    use replicante_models_agent::actions::ActionState;
+    use replicante_models_agent::clock::FixedClock;
+
+    use super::ActionMetrics;
+
+    #[test]
+    fn invalid_prefix() {
+        assert!(ActionMetrics::new("not a valid prefix").is_err());
+    }
+
+    #[test]
+    fn record_finished_action() {
+        let created_ts = Utc.with_ymd_and_hms(2022, 9, 12, 10, 20, 30).unwrap();
+        let clock = FixedClock(created_ts);
+        let mut action = ActionModel::new_with_clock(
+            "test.action",
+            json!({}),
+            ActionRequester::AgentApi,
+            &clock,
+        );
+        let metrics = ActionMetrics::new("test").unwrap();
+        let registry = Registry::new();
+        metrics.register(&registry).unwrap();
+
+        metrics.record(&action);
+        action.state = ActionState::Done;
+        action.finished_ts = Some(created_ts + Duration::seconds(42));
+        metrics.record(&action);
+
+        let new = metrics.actions.with_label_values(&["test.action", "NEW"]);
+        assert_eq!(new.get(), 1);
+        let done = metrics.actions.with_label_values(&["test.action", "DONE"]);
+        assert_eq!(done.get(), 1);
+        let duration = metrics.duration.with_label_values(&["test.action", "DONE"]);
+        assert_eq!(duration.get_sample_count(), 1);
+        assert_eq!(duration.get_sample_sum(), 42.0);
+        let unfinished = metrics.duration.with_label_values(&["test.action", "NEW"]);
+        assert_eq!(unfinished.get_sample_count(), 0);
+
+        // Metrics can't be registered twice.
+        assert!(metrics.register(&registry).is_err());
+    }
+}
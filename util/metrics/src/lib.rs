@@ -0,0 +1,3 @@
+mod actions;
+
+pub use self::actions::ActionMetrics;
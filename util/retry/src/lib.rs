@@ -0,0 +1,181 @@
+use std::thread::sleep;
+use std::time::Duration;
+
+use rand::Rng;
+
+/// Classify errors based on whether the failed operation can be retried.
+pub trait Retryable {
+    /// True if the operation that returned this error can be attempted again.
+    fn is_retryable(&self) -> bool;
+}
+
+/// Limits and delays between attempts used by `retry_with_backoff`.
+///
+/// The delay before retry `n` (starting at 1) is `base_delay * multiplier^(n - 1)`,
+/// randomly reduced by up to `jitter` (a fraction of the delay between 0 and 1).
+#[derive(Clone, Debug, PartialEq)]
+pub struct RetryPolicy {
+    base_delay: Duration,
+    jitter: f64,
+    max_attempts: u32,
+    multiplier: f64,
+}
+
+impl RetryPolicy {
+    /// Attempt operations up to `max_attempts` times (including the first attempt).
+    ///
+    /// Defaults to a 100ms base delay doubling after each attempt, with no jitter.
+    pub fn new(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            base_delay: Duration::from_millis(100),
+            jitter: 0.0,
+            max_attempts,
+            multiplier: 2.0,
+        }
+    }
+
+    /// Set the delay to wait for before the first retry.
+    pub fn base_delay(mut self, delay: Duration) -> RetryPolicy {
+        self.base_delay = delay;
+        self
+    }
+
+    /// Set the fraction of each delay that can be randomly skipped.
+    ///
+    /// The value is clamped between 0 (no jitter) and 1 (full jitter).
+    pub fn jitter(mut self, jitter: f64) -> RetryPolicy {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Set the factor delays grow by after each retry.
+    pub fn multiplier(mut self, multiplier: f64) -> RetryPolicy {
+        self.multiplier = multiplier;
+        self
+    }
+
+    /// Delay to wait for before the given retry (starting at 1).
+    fn delay(&self, retry: u32) -> Duration {
+        let exponent = i32::try_from(retry.saturating_sub(1)).unwrap_or(i32::MAX);
+        let delay = self.base_delay.as_secs_f64() * self.multiplier.powi(exponent);
+        let delay = if self.jitter > 0.0 {
+            delay * (1.0 - rand::thread_rng().gen_range(0.0..=self.jitter))
+        } else {
+            delay
+        };
+        if delay.is_finite() && delay > 0.0 {
+            Duration::from_secs_f64(delay)
+        } else {
+            Duration::from_secs(0)
+        }
+    }
+}
+
+/// Run an operation, retrying it with exponential backoff if it fails with retryable errors.
+///
+/// Retries stop as soon as the operation succeeds, fails with a non-retryable error
+/// or the policy's maximum number of attempts is reached.
+/// The result of the last attempt is returned.
+///
+/// Delays between attempts block the calling thread.
+pub fn retry_with_backoff<F, T, E>(policy: &RetryPolicy, mut op: F) -> Result<T, E>
+where
+    F: FnMut() -> Result<T, E>,
+    E: Retryable,
+{
+    let mut attempt = 1;
+    loop {
+        match op() {
+            Ok(value) => return Ok(value),
+            Err(error) if !error.is_retryable() || attempt >= policy.max_attempts => {
+                return Err(error)
+            }
+            Err(_) => {
+                sleep(policy.delay(attempt));
+                attempt += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::retry_with_backoff;
+    use super::RetryPolicy;
+    use super::Retryable;
+
+    #[derive(Debug, Eq, PartialEq)]
+    enum TestError {
+        Fatal,
+        Transient,
+    }
+
+    impl Retryable for TestError {
+        fn is_retryable(&self) -> bool {
+            matches!(self, TestError::Transient)
+        }
+    }
+
+    fn policy(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy::new(max_attempts).base_delay(Duration::from_millis(1))
+    }
+
+    #[test]
+    fn delays_grow() {
+        let policy = RetryPolicy::new(5)
+            .base_delay(Duration::from_millis(100))
+            .multiplier(3.0);
+        assert_eq!(policy.delay(1), Duration::from_millis(100));
+        assert_eq!(policy.delay(2), Duration::from_millis(300));
+        assert_eq!(policy.delay(3), Duration::from_millis(900));
+    }
+
+    #[test]
+    fn delays_with_jitter() {
+        let policy = RetryPolicy::new(5)
+            .base_delay(Duration::from_millis(100))
+            .jitter(0.5);
+        for _ in 0..10 {
+            let delay = policy.delay(1);
+            assert!(delay >= Duration::from_millis(50), "delay: {:?}", delay);
+            assert!(delay <= Duration::from_millis(100), "delay: {:?}", delay);
+        }
+    }
+
+    #[test]
+    fn gives_up_after_max_attempts() {
+        let mut attempts = 0;
+        let result: Result<(), TestError> = retry_with_backoff(&policy(3), || {
+            attempts += 1;
+            Err(TestError::Transient)
+        });
+        assert_eq!(result, Err(TestError::Transient));
+        assert_eq!(attempts, 3);
+    }
+
+    #[test]
+    fn stops_on_fatal_error() {
+        let mut attempts = 0;
+        let result: Result<(), TestError> = retry_with_backoff(&policy(3), || {
+            attempts += 1;
+            Err(TestError::Fatal)
+        });
+        assert_eq!(result, Err(TestError::Fatal));
+        assert_eq!(attempts, 1);
+    }
+
+    #[test]
+    fn succeeds_after_retries() {
+        let mut attempts = 0;
+        let result = retry_with_backoff(&policy(5), || {
+            attempts += 1;
+            if attempts < 3 {
+                return Err(TestError::Transient);
+            }
+            Ok(attempts)
+        });
+        assert_eq!(result, Ok(3));
+    }
+}
@@ -0,0 +1,188 @@
+use std::fmt;
+use std::str::FromStr;
+
+use data_encoding::BASE64URL_NOPAD;
+use serde::de::Deserializer;
+use serde::de::Error as _;
+use serde::ser::Serializer;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Length of the encoded timestamp, in bytes.
+const TIMESTAMP_LEN: usize = 8;
+
+/// Length of the checksum appended to encoded cursors, in bytes.
+const CHECKSUM_LEN: usize = 4;
+
+/// Opaque position in a list of items sorted by `(timestamp, id)`.
+///
+/// Cursors are encoded into URL-safe strings to be passed around by clients
+/// (for example as `?after=<cursor>` query parameters) without them needing
+/// to know what is inside.
+///
+/// Encoded cursors include a checksum so that garbage or altered values are rejected
+/// when parsed instead of silently pointing to a random position in the list.
+/// The checksum is NOT a signature: cursors are not protected against deliberate forgery.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub struct Cursor {
+    timestamp: i64,
+    id: String,
+}
+
+impl Cursor {
+    /// Create a cursor pointing at the item with the given timestamp and ID.
+    ///
+    /// The timestamp unit is up to the caller (milliseconds since the epoch are suggested).
+    pub fn new<S>(timestamp: i64, id: S) -> Cursor
+    where
+        S: Into<String>,
+    {
+        let id = id.into();
+        Cursor { timestamp, id }
+    }
+
+    /// ID of the item the cursor points at.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Timestamp of the item the cursor points at.
+    pub fn timestamp(&self) -> i64 {
+        self.timestamp
+    }
+}
+
+impl fmt::Display for Cursor {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let mut buf = Vec::with_capacity(TIMESTAMP_LEN + self.id.len() + CHECKSUM_LEN);
+        buf.extend_from_slice(&self.timestamp.to_be_bytes());
+        buf.extend_from_slice(self.id.as_bytes());
+        let checksum = checksum(&buf);
+        buf.extend_from_slice(&checksum.to_be_bytes());
+        write!(fmt, "{}", BASE64URL_NOPAD.encode(&buf))
+    }
+}
+
+impl FromStr for Cursor {
+    type Err = CursorError;
+    fn from_str(s: &str) -> ::std::result::Result<Self, Self::Err> {
+        let buf = BASE64URL_NOPAD
+            .decode(s.as_bytes())
+            .map_err(|_| CursorError::Encoding)?;
+        if buf.len() < TIMESTAMP_LEN + CHECKSUM_LEN {
+            return Err(CursorError::Length);
+        }
+
+        // Verify the checksum before looking at the content.
+        let (payload, expected) = buf.split_at(buf.len() - CHECKSUM_LEN);
+        let mut expected_bytes = [0; CHECKSUM_LEN];
+        expected_bytes.copy_from_slice(expected);
+        if checksum(payload) != u32::from_be_bytes(expected_bytes) {
+            return Err(CursorError::Checksum);
+        }
+
+        let (timestamp, id) = payload.split_at(TIMESTAMP_LEN);
+        let mut timestamp_bytes = [0; TIMESTAMP_LEN];
+        timestamp_bytes.copy_from_slice(timestamp);
+        let timestamp = i64::from_be_bytes(timestamp_bytes);
+        let id = String::from_utf8(id.to_vec()).map_err(|_| CursorError::Encoding)?;
+        Ok(Cursor { timestamp, id })
+    }
+}
+
+impl Serialize for Cursor {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Cursor {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let encoded = String::deserialize(deserializer)?;
+        encoded.parse().map_err(D::Error::custom)
+    }
+}
+
+/// Errors returned when parsing encoded cursors.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum CursorError {
+    /// The cursor checksum does not match its content.
+    Checksum,
+
+    /// The cursor is not correctly encoded.
+    Encoding,
+
+    /// The cursor is too short to be valid.
+    Length,
+}
+
+impl fmt::Display for CursorError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CursorError::Checksum => write!(fmt, "invalid cursor: checksum mismatch"),
+            CursorError::Encoding => write!(fmt, "invalid cursor: incorrect encoding"),
+            CursorError::Length => write!(fmt, "invalid cursor: too short"),
+        }
+    }
+}
+
+impl std::error::Error for CursorError {}
+
+/// FNV-1a 32 bits hash of the given bytes.
+fn checksum(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0x811c_9dc5, |hash, byte| {
+        (hash ^ u32::from(*byte)).wrapping_mul(0x0100_0193)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Cursor;
+    use super::CursorError;
+    use crate::RndId;
+
+    #[test]
+    fn round_trip() {
+        let id = RndId::new();
+        let cursor = Cursor::new(1_663_000_000_000, id.to_string());
+        let encoded = cursor.to_string();
+        let decoded: Cursor = encoded.parse().unwrap();
+        assert_eq!(decoded, cursor);
+        assert_eq!(decoded.id(), id.to_string());
+        assert_eq!(decoded.timestamp(), 1_663_000_000_000);
+    }
+
+    #[test]
+    fn url_safe() {
+        let cursor = Cursor::new(-1, "?&/+=");
+        let encoded = cursor.to_string();
+        assert!(encoded
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+    }
+
+    #[test]
+    fn reject_garbage() {
+        let error = "not a cursor!".parse::<Cursor>().unwrap_err();
+        assert_eq!(error, CursorError::Encoding);
+        let error = "AAAA".parse::<Cursor>().unwrap_err();
+        assert_eq!(error, CursorError::Length);
+    }
+
+    #[test]
+    fn reject_tampered() {
+        let encoded = Cursor::new(1_663_000_000_000, "abc").to_string();
+        let mut tampered: Vec<char> = encoded.chars().collect();
+        tampered[2] = if tampered[2] == 'A' { 'B' } else { 'A' };
+        let tampered: String = tampered.into_iter().collect();
+        let error = tampered.parse::<Cursor>().unwrap_err();
+        assert_eq!(error, CursorError::Checksum);
+        assert_eq!(error.to_string(), "invalid cursor: checksum mismatch");
+    }
+}
@@ -8,6 +8,11 @@ use rand::Rng;
 use serde_derive::Deserialize;
 use serde_derive::Serialize;
 
+mod cursor;
+
+pub use self::cursor::Cursor;
+pub use self::cursor::CursorError;
+
 /// Randomly generated (probably) unique IDs.
 ///
 /// IDs are generated as a random sequence of 128 bits.
@@ -21,6 +26,14 @@ impl RndId {
         let id: [u8; 16] = rng.gen();
         RndId(HEXLOWER_PERMISSIVE.encode(&id))
     }
+
+    /// Check if the string is a valid `RndId` without parsing (or allocating) it.
+    ///
+    /// Useful to cheaply reject malformed IDs before any work is done.
+    /// Valid IDs are 32 hex characters, in any case.
+    pub fn is_valid(id: &str) -> bool {
+        id.len() == 32 && id.bytes().all(|byte| byte.is_ascii_hexdigit())
+    }
 }
 
 impl fmt::Display for RndId {
@@ -76,6 +89,22 @@ mod tests {
         assert_eq!(id.to_string(), raw_id.to_lowercase());
     }
 
+    #[test]
+    fn is_valid() {
+        assert!(RndId::is_valid("ce84c2f150f72f1499d28b50c550c4c0"));
+        assert!(RndId::is_valid("CE84c2f150f72f1499D28b50c550c4c0"));
+        assert!(RndId::is_valid(&RndId::new().to_string()));
+    }
+
+    #[test]
+    fn is_valid_rejects_invalid_ids() {
+        assert!(!RndId::is_valid(""));
+        assert!(!RndId::is_valid("ABC"));
+        assert!(!RndId::is_valid("ce84c2f150f72f1499d28b50c550c4c0ff"));
+        assert!(!RndId::is_valid("%^84c2f150f72f1499d28b50c550c4c0"));
+        assert!(!RndId::is_valid("ce84c2f150f72f1499d28b50c550c4cg"));
+    }
+
     #[test]
     #[should_panic(expected = "kind: Length")]
     fn from_string_invalid_length() {
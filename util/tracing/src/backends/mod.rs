@@ -1,5 +1,6 @@
 mod noop;
 mod zipkin;
 
+pub use self::noop::disabled;
 pub use self::noop::noop;
 pub use self::zipkin::zipkin;
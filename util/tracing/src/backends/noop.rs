@@ -1,7 +1,15 @@
 use anyhow::Context;
 use humthreads::Builder;
 use opentracingrust::tracers::NoopTracer;
+use opentracingrust::ExtractFormat;
+use opentracingrust::InjectFormat;
+use opentracingrust::Result as TracingResult;
+use opentracingrust::Span;
+use opentracingrust::SpanContext;
+use opentracingrust::SpanReceiver;
+use opentracingrust::StartOptions;
 use opentracingrust::Tracer;
+use opentracingrust::TracerInterface;
 use slog::error;
 
 use crate::Error;
@@ -11,6 +19,10 @@ use crate::Result;
 /// Creates a noop tracer that discards all spans.
 pub fn noop(opts: Opts) -> Result<Tracer> {
     let (tracer, receiver) = NoopTracer::new();
+    let (tracer, receiver) = opts
+        .span_channel
+        .wrap(tracer, receiver)
+        .with_context(|| Error::ThreadSpawn("span relay"))?;
     let logger = opts.logger.clone();
     let recv_timeout = opts.flush_timeout;
     let thread = Builder::new("r:u:t:noop:collector")
@@ -40,14 +52,47 @@ pub fn noop(opts: Opts) -> Result<Tracer> {
     Ok(tracer)
 }
 
+/// Creates an inert noop tracer that discards all spans without a background thread.
+///
+/// Finished spans are dropped as new spans are started so, unlike the `noop`
+/// backend, no collector thread is registered with `Upkeep`.
+pub fn disabled(_opts: Opts) -> Result<Tracer> {
+    let (inner, receiver) = NoopTracer::new();
+    Ok(Tracer::new(InertTracer { inner, receiver }))
+}
+
+/// `NoopTracer` wrapper that drains finished spans synchronously.
+struct InertTracer {
+    inner: Tracer,
+    receiver: SpanReceiver,
+}
+
+impl TracerInterface for InertTracer {
+    fn extract(&self, fmt: ExtractFormat) -> TracingResult<Option<SpanContext>> {
+        self.inner.extract(fmt)
+    }
+
+    fn inject(&self, context: &SpanContext, fmt: InjectFormat) -> TracingResult<()> {
+        self.inner.inject(context, fmt)
+    }
+
+    fn span(&self, name: &str, options: StartOptions) -> Span {
+        self.receiver.try_iter().for_each(drop);
+        self.inner.span_with_options(name, options)
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use std::time::Duration;
+
     use slog::o;
     use slog::Discard;
     use slog::Logger;
 
     use replicante_util_upkeep::Upkeep;
 
+    use super::disabled;
     use super::noop;
     use crate::Opts;
 
@@ -58,4 +103,28 @@ mod tests {
         let opts = Opts::new("test", logger, &mut upkeep);
         let _tracer = noop(opts).expect("Failed to configure NoopTracer");
     }
+
+    #[test]
+    fn disabled_is_inert() {
+        let logger = Logger::root(Discard, o!());
+        let mut upkeep = Upkeep::new();
+        let opts = Opts::new("test", logger, &mut upkeep);
+        let tracer = disabled(opts).expect("Failed to configure disabled tracer");
+        for _ in 0..3 {
+            tracer.span("test").finish().expect("Failed to finish span");
+        }
+        assert!(upkeep.thread_statuses().is_empty());
+    }
+
+    #[test]
+    fn factory_with_builder() {
+        let logger = Logger::root(Discard, o!());
+        let mut upkeep = Upkeep::new();
+        let opts = Opts::builder("test", logger, &mut upkeep)
+            .flush_timeout(Duration::from_millis(50))
+            .build();
+        assert_eq!(opts.service_name, "test");
+        assert_eq!(opts.flush_timeout, Duration::from_millis(50));
+        let _tracer = noop(opts).expect("Failed to configure NoopTracer");
+    }
 }
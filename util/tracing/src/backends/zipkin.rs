@@ -1,16 +1,25 @@
+use std::collections::HashMap;
+use std::collections::VecDeque;
 use std::str::FromStr;
 use std::time::Duration;
+use std::time::Instant;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
 
 use anyhow::Context;
 use anyhow::Result;
 use humthreads::Builder;
 use humthreads::ThreadScope;
 use opentracingrust::FinishedSpan;
+use opentracingrust::InjectFormat;
+use opentracingrust::LogValue;
+use opentracingrust::TagValue;
 use opentracingrust::Tracer;
-use opentracingrust_zipkin::HttpCollector;
-use opentracingrust_zipkin::HttpCollectorOpts;
-use opentracingrust_zipkin::ZipkinEndpoint;
 use opentracingrust_zipkin::ZipkinTracer;
+use reqwest::blocking::Client;
+use reqwest::header::CONTENT_TYPE;
+use serde_json::json;
+use serde_json::Value;
 use slog::error;
 use slog::Logger;
 
@@ -18,11 +27,14 @@ use crate::config::ZipkinConfig;
 use crate::Error;
 use crate::Opts;
 
-/// Creates a zipkin tracer that sends spans over kafka.
+/// Creates a zipkin tracer that sends spans over HTTP.
 pub fn zipkin(config: ZipkinConfig, opts: Opts) -> Result<Tracer> {
     // Initialise tracer and collector.
     let (tracer, receiver) = ZipkinTracer::new();
-    let endpoint = ZipkinEndpoint::new(None, None, Some(opts.service_name.to_string()), None);
+    let (tracer, receiver) = opts
+        .span_channel
+        .wrap(tracer, receiver)
+        .with_context(|| Error::ThreadSpawn("span relay"))?;
     let mut collector = match config {
         ZipkinConfig::Http(config) => {
             let mut headers = reqwest::header::HeaderMap::new();
@@ -43,16 +55,26 @@ pub fn zipkin(config: ZipkinConfig, opts: Opts) -> Result<Tracer> {
                     })?;
                 headers.insert(key, value);
             }
-            let options = HttpCollectorOpts::new(config.url.as_str(), endpoint)
-                .flush_count(config.flush_count)
-                .flush_timeout(
-                    config
-                        .flush_timeout_millis
-                        .map(Duration::from_millis)
-                        .unwrap_or(opts.flush_timeout),
-                )
-                .headers(headers);
-            let collector = HttpCollector::new(options);
+            let timeout = Duration::from_millis(config.request_timeout_millis);
+            let client = Client::builder()
+                .default_headers(headers)
+                .connect_timeout(timeout)
+                .timeout(timeout)
+                .build()
+                .with_context(|| {
+                    Error::Config("unable to build Zipkin's HTTP client".to_string())
+                })?;
+            let sink = HttpSink::new(client, &config.url, opts.service_name);
+            let flush_timeout = config
+                .flush_timeout_millis
+                .map(Duration::from_millis)
+                .unwrap_or(opts.flush_timeout);
+            let collector = BufferedCollector::new(
+                sink,
+                config.buffer_limit,
+                config.flush_count,
+                flush_timeout,
+            );
             ZipkinCollector::Http(Box::new(collector))
         }
     };
@@ -101,11 +123,13 @@ fn zipkin_process(
             if let Some(span) = span {
                 collector.collect(span);
             }
-            if let Err(error) = collector.lazy_flush() {
+            if let Err(error) = collector.flush(Instant::now()) {
                 error!(
                     logger,
                     "Error collecting distributed tracer span";
                     "collector" => "http",
+                    "dropped_spans" => collector.dropped(),
+                    "retry_in_millis" => collector.backoff().as_millis() as u64,
                     "tracer" => "zipkin",
                     "error" => %error,
                 );
@@ -116,5 +140,478 @@ fn zipkin_process(
 
 /// Container for the configured zipkin collector.
 enum ZipkinCollector {
-    Http(Box<HttpCollector>),
+    Http(Box<BufferedCollector<HttpSink>>),
+}
+
+/// Initial delay before a failed flush is retried.
+const BACKOFF_INITIAL: Duration = Duration::from_millis(500);
+
+/// Maximum delay between flush attempts while the collector is failing.
+const BACKOFF_MAX: Duration = Duration::from_secs(60);
+
+/// Abstract the destination of spans so flush failures can be tested.
+trait SpanSink {
+    /// Send a batch of spans, failing if they were not accepted.
+    fn send(&mut self, spans: &[FinishedSpan]) -> Result<()>;
+}
+
+/// Post spans to Zipkin's v1 JSON API.
+///
+/// Spans are only borrowed so they can be retried if the request fails.
+struct HttpSink {
+    client: Client,
+    endpoint: Value,
+    target: String,
+
+    /// Zipkin tracer used to read span and trace IDs out of span contexts.
+    tracer: Tracer,
+}
+
+impl HttpSink {
+    fn new(client: Client, url: &str, service_name: &str) -> HttpSink {
+        let (tracer, _) = ZipkinTracer::new();
+        HttpSink {
+            client,
+            endpoint: json!({ "serviceName": service_name }),
+            target: format!("{}/api/v1/spans", url),
+            tracer,
+        }
+    }
+
+    /// Encode a span for Zipkin's v1 JSON API.
+    fn encode(&self, span: &FinishedSpan) -> Result<Value> {
+        let mut ids: HashMap<String, String> = HashMap::new();
+        self.tracer
+            .inject(
+                span.context(),
+                InjectFormat::HttpHeaders(Box::new(&mut ids)),
+            )
+            .map_err(|error| anyhow::anyhow!(error.to_string()))?;
+        let id = |key: &str| ids.get(key).map(|id| format!("{:0>16}", id));
+
+        let annotations: Vec<Value> = span
+            .logs()
+            .iter()
+            .map(|log| {
+                let fields: HashMap<&String, String> = log
+                    .iter()
+                    .map(|(key, value)| (key, log_value(value)))
+                    .collect();
+                let timestamp = log.timestamp().copied().unwrap_or_else(SystemTime::now);
+                json!({
+                    "endpoint": self.endpoint,
+                    "timestamp": micros(UNIX_EPOCH, timestamp),
+                    "value": serde_json::to_string(&fields).unwrap_or_default(),
+                })
+            })
+            .collect();
+        let mut binary_annotations: Vec<Value> = span
+            .tags()
+            .iter()
+            .map(|(key, value)| {
+                json!({
+                    "endpoint": self.endpoint,
+                    "key": key,
+                    "value": tag_value(value),
+                })
+            })
+            .collect();
+        // Ensure at least an annotation is present to carry the endpoint information.
+        if annotations.is_empty() && binary_annotations.is_empty() {
+            binary_annotations.push(json!({
+                "endpoint": self.endpoint,
+                "key": "zipkin.endpoint.injected",
+                "value": "true",
+            }));
+        }
+
+        let mut encoded = json!({
+            "annotations": annotations,
+            "binaryAnnotations": binary_annotations,
+            "debug": ids.get("X-B3-Flags").map(String::as_str) == Some("1"),
+            "duration": micros(*span.start_time(), *span.finish_time()).max(1),
+            "id": id("X-B3-SpanId"),
+            "name": span.name(),
+            "timestamp": micros(UNIX_EPOCH, *span.start_time()),
+            "traceId": ids.get("X-B3-TraceId"),
+        });
+        if let Some(parent) = id("X-B3-ParentSpanId") {
+            encoded["parentId"] = Value::from(parent);
+        }
+        Ok(encoded)
+    }
+}
+
+impl SpanSink for HttpSink {
+    fn send(&mut self, spans: &[FinishedSpan]) -> Result<()> {
+        let spans = spans
+            .iter()
+            .map(|span| self.encode(span))
+            .collect::<Result<Vec<Value>>>()?;
+        let body = serde_json::to_vec(&spans)?;
+        let response = self
+            .client
+            .post(&self.target)
+            .header(CONTENT_TYPE, "application/json")
+            .body(body)
+            .send()?;
+        let status = response.status();
+        if !status.is_success() {
+            anyhow::bail!("zipkin responded with status {}", status);
+        }
+        Ok(())
+    }
+}
+
+/// Microseconds between two points in time.
+fn micros(start: SystemTime, end: SystemTime) -> u64 {
+    let delta = end.duration_since(start).unwrap_or_default();
+    u64::try_from(delta.as_micros()).unwrap_or(u64::MAX)
+}
+
+/// Encode a log value as a string, like the thrift encoder does.
+fn log_value(value: &LogValue) -> String {
+    match value {
+        LogValue::Boolean(value) => value.to_string(),
+        LogValue::Float(value) => value.to_string(),
+        LogValue::Integer(value) => value.to_string(),
+        LogValue::String(value) => value.clone(),
+    }
+}
+
+/// Encode a tag value as a string annotation, like the thrift encoder does.
+fn tag_value(value: &TagValue) -> String {
+    match value {
+        TagValue::Boolean(value) => value.to_string(),
+        TagValue::Float(value) => value.to_string(),
+        TagValue::Integer(value) => value.to_string(),
+        TagValue::String(value) => value.clone(),
+    }
+}
+
+/// Bounded buffer of spans in front of a sink, with backoff between failed flushes.
+///
+/// Spans stay in the buffer until the sink accepts them so failed flushes are retried.
+/// Once the limit is reached the oldest spans are dropped to make room for new ones.
+struct BufferedCollector<S: SpanSink> {
+    backoff: Duration,
+    buffer: VecDeque<FinishedSpan>,
+    dropped: u64,
+    flush_count: usize,
+    flush_last: Instant,
+    flush_timeout: Duration,
+    limit: usize,
+    retry_at: Option<Instant>,
+    sink: S,
+}
+
+impl<S: SpanSink> BufferedCollector<S> {
+    fn new(
+        sink: S,
+        limit: usize,
+        flush_count: usize,
+        flush_timeout: Duration,
+    ) -> BufferedCollector<S> {
+        BufferedCollector {
+            backoff: BACKOFF_INITIAL,
+            buffer: VecDeque::new(),
+            dropped: 0,
+            flush_count,
+            flush_last: Instant::now(),
+            flush_timeout,
+            limit: limit.max(1),
+            retry_at: None,
+            sink,
+        }
+    }
+
+    /// Current delay before a failed flush is retried.
+    fn backoff(&self) -> Duration {
+        self.backoff
+    }
+
+    /// Buffer a span, dropping the oldest span if the buffer is full.
+    fn collect(&mut self, span: FinishedSpan) {
+        if self.buffer.len() >= self.limit {
+            self.buffer.pop_front();
+            self.dropped += 1;
+        }
+        self.buffer.push_back(span);
+    }
+
+    /// Total number of spans dropped because the buffer was full.
+    fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Send buffered spans to the sink if enough were collected or the last flush was too long ago.
+    ///
+    /// Nothing is sent while waiting for a backoff to expire.
+    /// Buffered spans are retried as soon as the backoff expires.
+    fn flush(&mut self, now: Instant) -> Result<()> {
+        if let Some(retry_at) = self.retry_at {
+            if now < retry_at {
+                return Ok(());
+            }
+        }
+        let due = self.retry_at.is_some()
+            || self.buffer.len() >= self.flush_count
+            || now.saturating_duration_since(self.flush_last) >= self.flush_timeout;
+        if self.buffer.is_empty() || !due {
+            return Ok(());
+        }
+
+        self.flush_last = now;
+        match self.sink.send(self.buffer.make_contiguous()) {
+            Ok(()) => {
+                self.buffer.clear();
+                self.backoff = BACKOFF_INITIAL;
+                self.retry_at = None;
+                Ok(())
+            }
+            Err(error) => {
+                // Keep the spans, wait for the current backoff and grow it for the next failure.
+                self.retry_at = Some(now + self.backoff);
+                self.backoff = std::cmp::min(self.backoff * 2, BACKOFF_MAX);
+                Err(error)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+    use std::io::Write;
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+    use std::time::Duration;
+    use std::time::Instant;
+
+    use anyhow::Result;
+    use opentracingrust::FinishedSpan;
+    use opentracingrust::StartOptions;
+    use opentracingrust_zipkin::ZipkinTracer;
+    use reqwest::blocking::Client;
+    use serde_json::Value;
+
+    use super::BufferedCollector;
+    use super::HttpSink;
+    use super::SpanSink;
+    use super::BACKOFF_INITIAL;
+    use super::BACKOFF_MAX;
+
+    /// Sink that records spans and fails flushes on demand.
+    #[derive(Default)]
+    struct MockSink {
+        fail: bool,
+        flushes: usize,
+        spans: usize,
+    }
+
+    impl SpanSink for MockSink {
+        fn send(&mut self, spans: &[FinishedSpan]) -> Result<()> {
+            self.flushes += 1;
+            if self.fail {
+                anyhow::bail!("collector unreachable");
+            }
+            self.spans += spans.len();
+            Ok(())
+        }
+    }
+
+    fn failing(limit: usize) -> BufferedCollector<MockSink> {
+        let sink = MockSink {
+            fail: true,
+            ..Default::default()
+        };
+        BufferedCollector::new(sink, limit, 1, Duration::from_secs(1))
+    }
+
+    fn spans(count: usize) -> Vec<FinishedSpan> {
+        let (tracer, receiver) = ZipkinTracer::new();
+        for _ in 0..count {
+            tracer.span("test").finish().unwrap();
+        }
+        (0..count).map(|_| receiver.recv().unwrap()).collect()
+    }
+
+    /// Serve one HTTP request with the given status and return the request body.
+    fn serve_once(status: &'static str) -> (String, mpsc::Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buffer = [0; 4096];
+            let body = loop {
+                let read = stream.read(&mut buffer).unwrap();
+                request.extend_from_slice(&buffer[..read]);
+                let text = String::from_utf8_lossy(&request).to_string();
+                if let Some(end) = text.find("\r\n\r\n") {
+                    let length = text[..end]
+                        .lines()
+                        .find_map(|line| {
+                            let line = line.to_lowercase();
+                            line.strip_prefix("content-length:")
+                                .map(|length| length.trim().parse::<usize>().unwrap())
+                        })
+                        .unwrap_or(0);
+                    if request.len() >= end + 4 + length {
+                        break text[end + 4..].to_string();
+                    }
+                }
+            };
+            let response = format!("HTTP/1.1 {}\r\ncontent-length: 0\r\n\r\n", status);
+            stream.write_all(response.as_bytes()).unwrap();
+            sender.send(body).unwrap();
+        });
+        (url, receiver)
+    }
+
+    #[test]
+    fn backoff_grows_and_resets() {
+        let mut collector = failing(10);
+        for span in spans(1) {
+            collector.collect(span);
+        }
+        let now = Instant::now();
+        collector.flush(now).unwrap_err();
+        assert_eq!(collector.backoff(), BACKOFF_INITIAL * 2);
+
+        // Flushes are skipped until the backoff expires.
+        collector.flush(now + BACKOFF_INITIAL / 2).unwrap();
+        assert_eq!(collector.sink.flushes, 1);
+        let now = now + BACKOFF_INITIAL;
+        collector.flush(now).unwrap_err();
+        assert_eq!(collector.sink.flushes, 2);
+        assert_eq!(collector.backoff(), BACKOFF_INITIAL * 4);
+
+        collector.sink.fail = false;
+        collector.flush(now + Duration::from_secs(1)).unwrap();
+        assert_eq!(collector.backoff(), BACKOFF_INITIAL);
+    }
+
+    #[test]
+    fn backoff_is_capped() {
+        let mut collector = failing(10);
+        for span in spans(1) {
+            collector.collect(span);
+        }
+        let mut now = Instant::now();
+        for _ in 0..20 {
+            let _ = collector.flush(now);
+            now += BACKOFF_MAX;
+        }
+        assert_eq!(collector.backoff(), BACKOFF_MAX);
+    }
+
+    #[test]
+    fn flushes_wait_for_count_or_timeout() {
+        let sink = MockSink::default();
+        let mut collector = BufferedCollector::new(sink, 10, 3, Duration::from_secs(1));
+        let now = collector.flush_last;
+        let mut spans = spans(4).into_iter();
+        collector.collect(spans.next().unwrap());
+        collector.collect(spans.next().unwrap());
+        collector.flush(now).unwrap();
+        assert_eq!(collector.sink.flushes, 0);
+
+        collector.collect(spans.next().unwrap());
+        collector.flush(now).unwrap();
+        assert_eq!(collector.sink.spans, 3);
+
+        collector.collect(spans.next().unwrap());
+        collector.flush(now + Duration::from_millis(500)).unwrap();
+        assert_eq!(collector.sink.flushes, 1);
+        collector.flush(now + Duration::from_secs(1)).unwrap();
+        assert_eq!(collector.sink.spans, 4);
+    }
+
+    #[test]
+    fn spans_are_dropped_past_the_limit() {
+        let mut collector = failing(5);
+        let mut spans = spans(12).into_iter();
+        collector.collect(spans.next().unwrap());
+        let now = Instant::now();
+        collector.flush(now).unwrap_err();
+
+        // While backing off, spans are buffered up to the limit.
+        for span in spans {
+            collector.collect(span);
+            collector.flush(now).unwrap();
+        }
+        assert_eq!(collector.buffer.len(), 5);
+        assert_eq!(collector.dropped(), 7);
+        assert_eq!(collector.sink.spans, 0);
+
+        // A retry that fails keeps the buffered spans.
+        let now = now + BACKOFF_INITIAL;
+        collector.flush(now).unwrap_err();
+        assert_eq!(collector.sink.flushes, 2);
+        assert_eq!(collector.buffer.len(), 5);
+        assert_eq!(collector.dropped(), 7);
+
+        // Once the backoff expires the buffered spans are sent again.
+        collector.sink.fail = false;
+        collector.flush(now + BACKOFF_INITIAL * 2).unwrap();
+        assert_eq!(collector.buffer.len(), 0);
+        assert_eq!(collector.sink.spans, 5);
+    }
+
+    #[test]
+    fn http_sink_posts_json() {
+        let (url, body) = serve_once("202 Accepted");
+        let mut sink = HttpSink::new(Client::new(), &url, "test-service");
+        let (tracer, receiver) = ZipkinTracer::new();
+        let parent = tracer.span("parent");
+        let options = StartOptions::default().child_of(parent.context().clone());
+        let mut child = tracer.span_with_options("child", options);
+        child.tag("key", "value");
+        child.finish().unwrap();
+        let span = receiver.recv().unwrap();
+        sink.send(&[span]).unwrap();
+
+        let body: Value = serde_json::from_str(&body.recv().unwrap()).unwrap();
+        let span = &body[0];
+        assert_eq!(span["name"], "child");
+        assert_eq!(span["id"].as_str().unwrap().len(), 16);
+        assert_eq!(span["parentId"].as_str().unwrap().len(), 16);
+        assert!(span["traceId"].is_string());
+        assert_eq!(span["binaryAnnotations"][0]["key"], "key");
+        assert_eq!(span["binaryAnnotations"][0]["value"], "value");
+        assert_eq!(
+            span["binaryAnnotations"][0]["endpoint"]["serviceName"],
+            "test-service"
+        );
+    }
+
+    #[test]
+    fn http_sink_aborts_hung_requests() {
+        // Connections are queued by the OS but no response is ever sent.
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let client = Client::builder()
+            .timeout(Duration::from_millis(50))
+            .build()
+            .unwrap();
+        let mut sink = HttpSink::new(client, &url, "test-service");
+        let start = Instant::now();
+        let error = sink.send(&spans(1)).unwrap_err();
+        let error = error.downcast::<reqwest::Error>().unwrap();
+        assert!(error.is_timeout());
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn http_sink_fails_on_error_status() {
+        let (url, _body) = serve_once("500 Internal Server Error");
+        let mut sink = HttpSink::new(Client::new(), &url, "test-service");
+        let error = sink.send(&spans(1)).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "zipkin responded with status 500 Internal Server Error"
+        );
+    }
 }
@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+
+use opentracingrust::ExtractFormat;
+use opentracingrust::InjectFormat;
+use opentracingrust::Result as OTResult;
+use opentracingrust::SpanContext;
+use opentracingrust::Tracer;
+
+/// Propagate span contexts over plain `HashMap<String, String>`s.
+///
+/// Useful for non-HTTP channels such as message queue metadata or job payloads.
+/// Contexts are encoded using the `TextMap` format.
+///
+/// # Examples
+///
+/// Inject a span context:
+///
+/// ```ignore
+/// use replicante_util_tracing::carriers::map::MapCarrier;
+///
+/// let mut metadata = HashMap::new();
+/// MapCarrier::inject(span.context(), &mut metadata, &tracer);
+/// ```
+///
+/// Optionally extract a context:
+///
+/// ```ignore
+/// use replicante_util_tracing::carriers::map::MapCarrier;
+///
+/// let context = MapCarrier::extract(&message.metadata, &tracer);
+/// ```
+pub struct MapCarrier;
+
+impl MapCarrier {
+    /// Inject a `SpanContext` into the given map.
+    #[allow(unknown_lints, clippy::result_large_err)]
+    pub fn inject(
+        context: &SpanContext,
+        map: &mut HashMap<String, String>,
+        tracer: &Tracer,
+    ) -> OTResult<()> {
+        let format = InjectFormat::TextMap(Box::new(map));
+        tracer.inject(context, format)?;
+        Ok(())
+    }
+
+    /// Checks the map for a span context and extract it if possible.
+    #[allow(unknown_lints, clippy::result_large_err)]
+    pub fn extract(
+        map: &HashMap<String, String>,
+        tracer: &Tracer,
+    ) -> OTResult<Option<SpanContext>> {
+        let format = ExtractFormat::TextMap(Box::new(map));
+        tracer.extract(format)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use opentracingrust_zipkin::ZipkinTracer;
+
+    use super::MapCarrier;
+
+    #[test]
+    fn extract_empty_map() {
+        let (tracer, _) = ZipkinTracer::new();
+        let map = HashMap::new();
+        let context = MapCarrier::extract(&map, &tracer).expect("extract to succeed");
+        assert!(context.is_none());
+    }
+
+    #[test]
+    fn round_trip() {
+        let (tracer, _) = ZipkinTracer::new();
+        let mut span = tracer.span("test");
+        span.set_baggage_item("key", "value");
+        let mut map = HashMap::new();
+        MapCarrier::inject(span.context(), &mut map, &tracer).expect("inject to succeed");
+
+        let context = MapCarrier::extract(&map, &tracer)
+            .expect("extract to succeed")
+            .expect("context to be found");
+        let baggage: Vec<(&String, &String)> = context.baggage_items().collect();
+        assert_eq!(baggage, vec![(&"key".to_string(), &"value".to_string())]);
+
+        let mut copy = HashMap::new();
+        MapCarrier::inject(&context, &mut copy, &tracer).expect("inject to succeed");
+        assert_eq!(map, copy);
+    }
+}
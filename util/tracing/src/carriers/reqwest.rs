@@ -11,6 +11,34 @@ use reqwest::header::HeaderMap;
 use reqwest::header::HeaderName;
 use reqwest::header::HeaderValue;
 
+/// Select which headers participate in context propagation.
+///
+/// Header names are compared case-insensitively.
+#[derive(Clone, Debug, Default)]
+pub enum HeaderFilter {
+    /// All headers are visible to the tracer.
+    #[default]
+    All,
+
+    /// Only the listed headers are visible to the tracer.
+    Allow(Vec<String>),
+
+    /// All headers except the listed ones are visible to the tracer.
+    Deny(Vec<String>),
+}
+
+impl HeaderFilter {
+    /// Check if the given header name should participate in propagation.
+    pub fn allows(&self, name: &str) -> bool {
+        let listed = |names: &Vec<String>| names.iter().any(|n| n.eq_ignore_ascii_case(name));
+        match self {
+            HeaderFilter::All => true,
+            HeaderFilter::Allow(names) => listed(names),
+            HeaderFilter::Deny(names) => !listed(names),
+        }
+    }
+}
+
 /// Implement the MapCarrier trait for Reqwest's HeaderMap.
 ///
 /// # Examples
@@ -33,6 +61,7 @@ use reqwest::header::HeaderValue;
 /// HeadersCarrier::extract(span.context(), &response.headers, &tracer);
 /// ```
 pub struct HeadersCarrier<'a> {
+    filter: HeaderFilter,
     headers: &'a mut HeaderMap,
     // This is horrible, I am sorry.
     // The MapCarrier items function returns String which we don't have
@@ -45,7 +74,13 @@ pub struct HeadersCarrier<'a> {
 impl<'a> HeadersCarrier<'a> {
     /// Mutably borrow a response so it can be serialised.
     pub fn new(headers: &'a mut HeaderMap) -> HeadersCarrier<'a> {
+        HeadersCarrier::with_filter(headers, HeaderFilter::default())
+    }
+
+    /// Mutably borrow a response, exposing only headers allowed by the filter.
+    pub fn with_filter(headers: &'a mut HeaderMap, filter: HeaderFilter) -> HeadersCarrier<'a> {
         let mut carrier = HeadersCarrier {
+            filter,
             iter_stage: HashMap::new(),
             headers,
         };
@@ -56,7 +91,18 @@ impl<'a> HeadersCarrier<'a> {
     /// Inject a `SpanContext` into the given Iron headers.
     #[allow(unknown_lints, clippy::result_large_err)]
     pub fn inject(context: &SpanContext, headers: &mut HeaderMap, tracer: &Tracer) -> OTResult<()> {
-        let mut carrier = HeadersCarrier::new(headers);
+        HeadersCarrier::inject_filtered(context, headers, tracer, HeaderFilter::default())
+    }
+
+    /// Inject a `SpanContext` into the given headers, skipping headers denied by the filter.
+    #[allow(unknown_lints, clippy::result_large_err)]
+    pub fn inject_filtered(
+        context: &SpanContext,
+        headers: &mut HeaderMap,
+        tracer: &Tracer,
+        filter: HeaderFilter,
+    ) -> OTResult<()> {
+        let mut carrier = HeadersCarrier::with_filter(headers, filter);
         let format = InjectFormat::HttpHeaders(Box::new(&mut carrier));
         tracer.inject(context, format)?;
         Ok(())
@@ -65,7 +111,17 @@ impl<'a> HeadersCarrier<'a> {
     /// Checks the headers for a span context and extract it if possible.
     #[allow(unknown_lints, clippy::result_large_err)]
     pub fn extract(headers: &mut HeaderMap, tracer: &Tracer) -> OTResult<Option<SpanContext>> {
-        let carrier = HeadersCarrier::new(headers);
+        HeadersCarrier::extract_filtered(headers, tracer, HeaderFilter::default())
+    }
+
+    /// Checks the headers allowed by the filter for a span context and extract it if possible.
+    #[allow(unknown_lints, clippy::result_large_err)]
+    pub fn extract_filtered(
+        headers: &mut HeaderMap,
+        tracer: &Tracer,
+        filter: HeaderFilter,
+    ) -> OTResult<Option<SpanContext>> {
+        let carrier = HeadersCarrier::with_filter(headers, filter);
         let format = ExtractFormat::HttpHeaders(Box::new(&carrier));
         tracer.extract(format)
     }
@@ -76,6 +132,7 @@ impl<'a> HeadersCarrier<'a> {
         let items: HashMap<String, String> = {
             self.headers
                 .iter()
+                .filter(|(header, _)| self.filter.allows(header.as_str()))
                 .map(|(header, value)| {
                     let header = header.as_str().into();
                     let value = value
@@ -96,6 +153,9 @@ impl<'a> MapCarrier for HeadersCarrier<'a> {
     }
 
     fn get(&self, key: &str) -> Option<String> {
+        if !self.filter.allows(key) {
+            return None;
+        }
         match self.headers.get(key) {
             Some(value) => {
                 let value = value
@@ -109,6 +169,9 @@ impl<'a> MapCarrier for HeadersCarrier<'a> {
     }
 
     fn set(&mut self, key: &str, value: &str) {
+        if !self.filter.allows(key) {
+            return;
+        }
         let key = HeaderName::from_bytes(key.as_bytes())
             .expect("failed to convert string into header name");
         let value =
@@ -117,3 +180,60 @@ impl<'a> MapCarrier for HeadersCarrier<'a> {
         self.prepare_iter();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use opentracingrust::MapCarrier;
+    use reqwest::header::HeaderMap;
+    use reqwest::header::HeaderName;
+    use reqwest::header::HeaderValue;
+
+    use super::HeaderFilter;
+    use super::HeadersCarrier;
+
+    fn headers() -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            HeaderName::from_static("authorization"),
+            HeaderValue::from_static("secret"),
+        );
+        headers.insert(
+            HeaderName::from_static("x-trace-id"),
+            HeaderValue::from_static("abc"),
+        );
+        headers
+    }
+
+    #[test]
+    fn default_exposes_all_headers() {
+        let mut headers = headers();
+        let carrier = HeadersCarrier::new(&mut headers);
+        assert_eq!(carrier.items().len(), 2);
+        assert_eq!(carrier.get("authorization"), Some("secret".into()));
+    }
+
+    #[test]
+    fn allow_list_hides_other_headers() {
+        let mut headers = headers();
+        let filter = HeaderFilter::Allow(vec!["X-Trace-Id".into()]);
+        let carrier = HeadersCarrier::with_filter(&mut headers, filter);
+        let items = carrier.items();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].0, "x-trace-id");
+        assert_eq!(carrier.get("authorization"), None);
+    }
+
+    #[test]
+    fn deny_list_hides_denied_headers() {
+        let mut headers = headers();
+        let filter = HeaderFilter::Deny(vec!["Authorization".into()]);
+        let mut carrier = HeadersCarrier::with_filter(&mut headers, filter);
+        assert!(carrier
+            .items()
+            .iter()
+            .all(|(key, _)| *key != "authorization"));
+        assert_eq!(carrier.get("authorization"), None);
+        carrier.set("authorization", "other");
+        assert_eq!(headers.get("authorization").unwrap(), "secret");
+    }
+}
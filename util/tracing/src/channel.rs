@@ -0,0 +1,225 @@
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use crossbeam_channel::TrySendError;
+use opentracingrust::SpanReceiver;
+use opentracingrust::Tracer;
+
+/// Capacity of the channel finished spans are sent to the collector over.
+///
+/// By default spans are sent over an unbounded channel, which can grow without limits
+/// when the collector is slower than the process generating spans.
+/// Bounded channels limit the spans waiting for the collector to `capacity`
+/// and discard spans finished while the channel is full, counting them as dropped.
+///
+/// Clones of a `SpanChannel` share the count of dropped spans so one copy can
+/// be passed to `OptsBuilder::span_channel` and another kept to export the count.
+#[derive(Clone, Debug)]
+pub struct SpanChannel {
+    capacity: Option<usize>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl SpanChannel {
+    /// Channel holding up to `capacity` spans that drops spans finished while it is full.
+    ///
+    /// # Panics
+    /// If `capacity` is zero.
+    pub fn bounded(capacity: usize) -> SpanChannel {
+        assert!(capacity > 0, "span channel capacity must be positive");
+        SpanChannel {
+            capacity: Some(capacity),
+            dropped: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Channel that holds any number of spans (the default).
+    pub fn unbounded() -> SpanChannel {
+        SpanChannel {
+            capacity: None,
+            dropped: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Number of spans discarded because the channel was full.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Relay spans finished by `tracer` to the collector over this channel.
+    ///
+    /// Tracers send finished spans over their own unbounded channel, with a blocking send.
+    /// A relay thread moves spans from it into the bounded channel without ever blocking,
+    /// so the tracer channel is drained as fast as spans are finished and the spans
+    /// held in memory are bounded by the capacity of this channel.
+    ///
+    /// The relay thread exits once the tracer (and all its spans) or the collector is dropped.
+    pub(crate) fn wrap(
+        &self,
+        tracer: Tracer,
+        receiver: SpanReceiver,
+    ) -> std::io::Result<(Tracer, SpanReceiver)> {
+        let capacity = match self.capacity {
+            None => return Ok((tracer, receiver)),
+            Some(capacity) => capacity,
+        };
+        let (sender, bounded) = crossbeam_channel::bounded(capacity);
+        let dropped = Arc::clone(&self.dropped);
+        std::thread::Builder::new()
+            .name("r:u:t:span:relay".into())
+            .spawn(move || {
+                for span in receiver {
+                    match sender.try_send(span) {
+                        Ok(()) => (),
+                        Err(TrySendError::Full(_)) => {
+                            dropped.fetch_add(1, Ordering::Relaxed);
+                        }
+                        // Stop relaying once the collector is gone.
+                        Err(TrySendError::Disconnected(_)) => break,
+                    }
+                }
+            })?;
+        Ok((tracer, bounded))
+    }
+}
+
+impl Default for SpanChannel {
+    fn default() -> SpanChannel {
+        SpanChannel::unbounded()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use std::time::Instant;
+
+    use opentracingrust::tracers::NoopTracer;
+
+    use super::SpanChannel;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// Wait for the relay thread to reach the expected state.
+    fn wait_for<F: Fn() -> bool>(check: F) {
+        let deadline = Instant::now() + TIMEOUT;
+        while !check() {
+            assert!(Instant::now() < deadline, "timeout waiting for span relay");
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn drop_spans_when_full() {
+        let channel = SpanChannel::bounded(2);
+        let (tracer, receiver) = NoopTracer::new();
+        let (tracer, receiver) = channel.clone().wrap(tracer, receiver).unwrap();
+        for _ in 0..5 {
+            tracer.span("test").finish().unwrap();
+        }
+        wait_for(|| channel.dropped() == 3);
+        assert_eq!(receiver.len(), 2);
+
+        // Spans are accepted again once the collector catches up.
+        receiver.try_iter().for_each(drop);
+        tracer.span("after").finish().unwrap();
+        let span = receiver.recv_timeout(TIMEOUT).unwrap();
+        assert_eq!(span.name(), "after");
+        assert_eq!(channel.dropped(), 3);
+    }
+
+    #[test]
+    fn drop_spans_filled_after_start() {
+        let channel = SpanChannel::bounded(2);
+        let (tracer, receiver) = NoopTracer::new();
+        let (tracer, receiver) = channel.clone().wrap(tracer, receiver).unwrap();
+        let early = tracer.span("early");
+        tracer.span("first").finish().unwrap();
+        tracer.span("second").finish().unwrap();
+        wait_for(|| receiver.len() == 2);
+
+        // The channel filled up after the span started: finishing must not block.
+        early.finish().unwrap();
+        wait_for(|| channel.dropped() == 1);
+        let names: Vec<String> = receiver
+            .try_iter()
+            .map(|span| span.name().clone())
+            .collect();
+        assert_eq!(names, vec!["first", "second"]);
+    }
+
+    #[test]
+    fn memory_bounded_by_capacity() {
+        let channel = SpanChannel::bounded(10);
+        let (tracer, receiver) = NoopTracer::new();
+        let (tracer, receiver) = channel.clone().wrap(tracer, receiver).unwrap();
+        for _ in 0..10_000 {
+            tracer.span("test").finish().unwrap();
+        }
+
+        // All spans leave the tracer channel: they are either waiting or dropped.
+        wait_for(|| channel.dropped() == 9_990);
+        assert_eq!(receiver.len(), 10);
+    }
+
+    #[test]
+    fn relay_stops_when_tracer_dropped() {
+        let channel = SpanChannel::bounded(2);
+        let (tracer, receiver) = NoopTracer::new();
+        let (tracer, receiver) = channel.wrap(tracer, receiver).unwrap();
+        tracer.span("last").finish().unwrap();
+        drop(tracer);
+
+        // Pending spans are delivered and the channel disconnects once the relay exits.
+        let span = receiver.recv_timeout(TIMEOUT).unwrap();
+        assert_eq!(span.name(), "last");
+        assert!(receiver
+            .recv_timeout(TIMEOUT)
+            .unwrap_err()
+            .is_disconnected());
+    }
+
+    #[test]
+    fn relay_stops_when_collector_dropped() {
+        let channel = SpanChannel::bounded(2);
+        let (tracer, receiver) = NoopTracer::new();
+        let (tracer, receiver) = channel.wrap(tracer, receiver).unwrap();
+        drop(receiver);
+
+        // The relay exits on the next span and closes the tracer channel.
+        wait_for(|| tracer.span("test").finish().is_err());
+    }
+
+    #[test]
+    fn child_spans_keep_parent() {
+        let channel = SpanChannel::bounded(4);
+        let (tracer, receiver) = NoopTracer::new();
+        let (tracer, receiver) = channel.wrap(tracer, receiver).unwrap();
+        let mut parent = tracer.span("parent");
+        parent.set_baggage_item("key", "value");
+        let child = tracer.span_with_options(
+            "child",
+            opentracingrust::StartOptions::default().child_of(parent.context().clone()),
+        );
+        assert_eq!(
+            child.get_baggage_item("key").map(String::as_str),
+            Some("value")
+        );
+        child.finish().unwrap();
+        let child = receiver.recv_timeout(TIMEOUT).unwrap();
+        assert_eq!(child.references().len(), 1);
+    }
+
+    #[test]
+    fn unbounded_by_default() {
+        let channel = SpanChannel::default();
+        let (tracer, receiver) = NoopTracer::new();
+        let (tracer, receiver) = channel.wrap(tracer, receiver).unwrap();
+        for _ in 0..100 {
+            tracer.span("test").finish().unwrap();
+        }
+        assert_eq!(receiver.len(), 100);
+        assert_eq!(channel.dropped(), 0);
+    }
+}
@@ -1,12 +1,27 @@
 use std::collections::BTreeMap;
+use std::env;
+use std::str::FromStr;
 
+use reqwest::header::HeaderName;
+use reqwest::header::HeaderValue;
+use reqwest::Url;
 use serde::Deserialize;
 use serde::Serialize;
+use serde_json::Value;
+
+use crate::ConfigError;
 
 /// Supported tracing backends and their configuration.
 #[derive(Clone, Default, Eq, PartialEq, Hash, Debug, Serialize, Deserialize)]
 #[serde(tag = "backend", content = "options")]
 pub enum Config {
+    /// Tracing is explicitly disabled.
+    ///
+    /// Like the `Noop` tracer all spans are discarded but no background thread
+    /// is started to collect them.
+    #[serde(rename = "disabled")]
+    Disabled,
+
     /// The `Noop` tracer (default).
     ///
     /// A tracer that discards all spans.
@@ -25,6 +40,73 @@ pub enum Config {
     Zipkin(ZipkinConfig),
 }
 
+impl Config {
+    /// Create a configuration by merging partial documents in order of precedence.
+    ///
+    /// See `replicante_util_config::merge` for how layers are merged.
+    pub fn from_layers<I>(layers: I) -> serde_json::Result<Config>
+    where
+        I: IntoIterator<Item = Value>,
+    {
+        replicante_util_config::merge_layers(layers)
+    }
+
+    /// Overlay tracing options set in environment variables onto this configuration.
+    ///
+    /// Environment variables take precedence over values loaded from files:
+    ///
+    ///   * `TRACING_BACKEND`: selects the backend (`disabled`, `noop` or `zipkin`).
+    ///   * `TRACING_ZIPKIN_URL`: the URL to post spans to, if the zipkin backend is used.
+    ///
+    /// Switching to the zipkin backend requires `TRACING_ZIPKIN_URL` unless the zipkin
+    /// backend is already configured, in which case its other options are kept.
+    /// Unset or empty variables leave the configuration unchanged.
+    pub fn apply_env_overrides(&mut self) -> Result<(), ConfigError> {
+        self.apply_overrides(|name| env::var(name).ok())
+    }
+
+    /// Overlay options returned by `lookup` onto this configuration.
+    fn apply_overrides<F>(&mut self, lookup: F) -> Result<(), ConfigError>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let lookup = |name| lookup(name).filter(|value| !value.is_empty());
+        let url = lookup("TRACING_ZIPKIN_URL");
+        match lookup("TRACING_BACKEND").as_deref() {
+            None => (),
+            Some("disabled") => *self = Config::Disabled,
+            Some("noop") => *self = Config::Noop,
+            Some("zipkin") if matches!(self, Config::Zipkin(_)) => (),
+            Some("zipkin") => {
+                let url = url
+                    .clone()
+                    .ok_or(ConfigError::MissingEnvOverride("TRACING_ZIPKIN_URL"))?;
+                *self = Config::Zipkin(ZipkinConfig::Http(ZipkinHttp::new(url)));
+            }
+            Some(value) => {
+                return Err(ConfigError::InvalidEnvOverride {
+                    name: "TRACING_BACKEND".into(),
+                    value: value.to_string(),
+                })
+            }
+        };
+        if let (Config::Zipkin(ZipkinConfig::Http(http)), Some(url)) = (self, url) {
+            http.url = url;
+        }
+        Ok(())
+    }
+
+    /// Check the configuration is valid without configuring any tracer.
+    ///
+    /// Useful to validate configuration changes before they are applied.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        match self {
+            Config::Disabled | Config::Noop => Ok(()),
+            Config::Zipkin(ZipkinConfig::Http(config)) => config.validate(),
+        }
+    }
+}
+
 /// Zipkin specific configuration options.
 #[derive(Clone, Eq, PartialEq, Hash, Debug, Serialize, Deserialize)]
 #[serde(tag = "transport", content = "options")]
@@ -37,6 +119,12 @@ pub enum ZipkinConfig {
 /// Zipkin HTTP transport options.
 #[derive(Clone, Eq, PartialEq, Hash, Debug, Serialize, Deserialize)]
 pub struct ZipkinHttp {
+    /// Maximum number of spans buffered while the collector is unreachable.
+    ///
+    /// When the limit is reached the oldest spans are dropped.
+    #[serde(default = "ZipkinHttp::default_buffer_limit")]
+    pub buffer_limit: usize,
+
     /// Number of buffered spans that should trigger a flush.
     #[serde(default = "ZipkinHttp::default_flush_count")]
     pub flush_count: usize,
@@ -49,21 +137,145 @@ pub struct ZipkinHttp {
     #[serde(default)]
     pub headers: BTreeMap<String, String>,
 
+    /// Maximum time to wait for spans to be posted, in milliseconds.
+    ///
+    /// Requests that take longer, including establishing the connection, are aborted
+    /// and the flush is reported as failed so an unresponsive collector does not
+    /// stall span processing.
+    #[serde(default = "ZipkinHttp::default_request_timeout_millis")]
+    pub request_timeout_millis: u64,
+
     /// Target URL to post spans to.
     pub url: String,
 }
 
 impl ZipkinHttp {
+    /// Zipkin HTTP transport options with defaults for everything but the URL.
+    fn new(url: String) -> ZipkinHttp {
+        ZipkinHttp {
+            buffer_limit: ZipkinHttp::default_buffer_limit(),
+            flush_count: ZipkinHttp::default_flush_count(),
+            flush_timeout_millis: None,
+            headers: BTreeMap::new(),
+            request_timeout_millis: ZipkinHttp::default_request_timeout_millis(),
+            url,
+        }
+    }
+
+    fn default_buffer_limit() -> usize {
+        10000
+    }
+
     fn default_flush_count() -> usize {
         100
     }
+
+    fn default_request_timeout_millis() -> u64 {
+        10000
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        Url::parse(&self.url).map_err(|_| ConfigError::InvalidUrl(self.url.clone()))?;
+        for (key, value) in self.headers.iter() {
+            HeaderName::from_str(key).map_err(|_| ConfigError::InvalidHeaderName(key.clone()))?;
+            HeaderValue::from_str(value)
+                .map_err(|_| ConfigError::InvalidHeaderValue(key.clone()))?;
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    mod noop {
-        use serde_yaml;
+    mod env {
+        use std::collections::HashMap;
+
+        use super::super::Config;
+        use super::super::ZipkinConfig;
+        use super::super::ZipkinHttp;
+        use crate::ConfigError;
+
+        fn lookup(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+            let vars: HashMap<String, String> = vars
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect();
+            move |name| vars.get(name).cloned()
+        }
+
+        #[test]
+        fn backend_noop() {
+            let mut config = Config::Zipkin(ZipkinConfig::Http(ZipkinHttp::new("http://a".into())));
+            config
+                .apply_overrides(lookup(&[("TRACING_BACKEND", "noop")]))
+                .unwrap();
+            assert_eq!(config, Config::Noop);
+        }
+
+        #[test]
+        fn backend_invalid() {
+            let mut config = Config::Noop;
+            let error = config
+                .apply_overrides(lookup(&[("TRACING_BACKEND", "jaeger")]))
+                .unwrap_err();
+            assert_eq!(
+                error.to_string(),
+                "invalid value 'jaeger' for environment variable TRACING_BACKEND"
+            );
+        }
+
+        #[test]
+        fn backend_zipkin_requires_url() {
+            let mut config = Config::Noop;
+            let error = config
+                .apply_overrides(lookup(&[("TRACING_BACKEND", "zipkin")]))
+                .unwrap_err();
+            assert_eq!(error, ConfigError::MissingEnvOverride("TRACING_ZIPKIN_URL"));
+        }
+
+        #[test]
+        fn from_environment() {
+            std::env::set_var("TRACING_BACKEND", "zipkin");
+            std::env::set_var("TRACING_ZIPKIN_URL", "http://zipkin:9411");
+            let mut config = Config::Noop;
+            let result = config.apply_env_overrides();
+            std::env::remove_var("TRACING_BACKEND");
+            std::env::remove_var("TRACING_ZIPKIN_URL");
+            result.unwrap();
+            assert_eq!(
+                config,
+                Config::Zipkin(ZipkinConfig::Http(ZipkinHttp::new(
+                    "http://zipkin:9411".into()
+                )))
+            );
+        }
+
+        #[test]
+        fn zipkin_url_keeps_options() {
+            let mut http = ZipkinHttp::new("http://old".into());
+            http.flush_count = 5;
+            let mut config = Config::Zipkin(ZipkinConfig::Http(http));
+            config
+                .apply_overrides(lookup(&[("TRACING_ZIPKIN_URL", "http://new")]))
+                .unwrap();
+            let mut expected = ZipkinHttp::new("http://new".into());
+            expected.flush_count = 5;
+            assert_eq!(config, Config::Zipkin(ZipkinConfig::Http(expected)));
+        }
+    }
+
+    mod disabled {
+        use super::super::Config;
+
+        #[test]
+        fn deserialise() {
+            let text = "backend: disabled";
+            let config: Config = serde_yaml::from_str(text).unwrap();
+            assert_eq!(config, Config::Disabled);
+        }
+    }
 
+    mod noop {
         use super::super::Config;
 
         #[test]
@@ -73,6 +285,11 @@ mod tests {
             assert_eq!(config, Config::Noop);
         }
 
+        #[test]
+        fn validate() {
+            Config::Noop.validate().unwrap();
+        }
+
         #[test]
         fn serialise() {
             let config = Config::Noop;
@@ -82,11 +299,39 @@ mod tests {
     }
 
     mod zipkin {
-        use serde_yaml;
-
         use super::super::Config;
         use super::super::ZipkinConfig;
         use super::super::ZipkinHttp;
+        use crate::ConfigError;
+
+        fn config(url: &str) -> ZipkinHttp {
+            ZipkinHttp {
+                buffer_limit: 10000,
+                flush_count: 100,
+                flush_timeout_millis: None,
+                headers: Default::default(),
+                request_timeout_millis: 10000,
+                url: String::from(url),
+            }
+        }
+
+        #[test]
+        fn from_layers() {
+            let base = serde_json::json!({
+                "backend": "zipkin",
+                "options": {
+                    "transport": "http",
+                    "options": {"flush_count": 1, "url": "http://base:1234"},
+                },
+            });
+            let overlay = serde_json::json!({
+                "options": {"options": {"url": "http://override:1234"}},
+            });
+            let config = Config::from_layers(vec![base, overlay]).unwrap();
+            let mut expected = ZipkinHttp::new("http://override:1234".into());
+            expected.flush_count = 1;
+            assert_eq!(config, Config::Zipkin(ZipkinConfig::Http(expected)));
+        }
 
         #[test]
         fn deserialise() {
@@ -96,14 +341,17 @@ options:
   options:
     flush_count: 1
     flush_timeout_millis: 2000
+    request_timeout_millis: 500
     url: http://localhost:1234"#;
             let config: Config = serde_yaml::from_str(text).unwrap();
             assert_eq!(
                 config,
                 Config::Zipkin(ZipkinConfig::Http(ZipkinHttp {
+                    buffer_limit: 10000,
                     flush_count: 1,
                     flush_timeout_millis: Some(2000),
                     headers: Default::default(),
+                    request_timeout_millis: 500,
                     url: String::from("http://localhost:1234"),
                 }))
             );
@@ -120,9 +368,11 @@ options:
             assert_eq!(
                 config,
                 Config::Zipkin(ZipkinConfig::Http(ZipkinHttp {
+                    buffer_limit: 10000,
                     flush_count: 100,
                     flush_timeout_millis: None,
                     headers: Default::default(),
+                    request_timeout_millis: 10000,
                     url: String::from("http://localhost:1234"),
                 }))
             );
@@ -138,12 +388,39 @@ options:
             let _config: Config = serde_yaml::from_str(text).unwrap();
         }
 
+        #[test]
+        fn validate() {
+            let mut http = config("http://localhost:1234");
+            http.headers.insert("X-Token".into(), "secret".into());
+            let config = Config::Zipkin(ZipkinConfig::Http(http));
+            config.validate().unwrap();
+        }
+
+        #[test]
+        fn validate_invalid_header() {
+            let mut http = config("http://localhost:1234");
+            http.headers.insert("Not A Header".into(), "value".into());
+            let config = Config::Zipkin(ZipkinConfig::Http(http));
+            let error = config.validate().unwrap_err();
+            assert_eq!(error, ConfigError::InvalidHeaderName("Not A Header".into()));
+        }
+
+        #[test]
+        fn validate_invalid_url() {
+            let config = Config::Zipkin(ZipkinConfig::Http(config("not a url")));
+            let error = config.validate().unwrap_err();
+            assert_eq!(error, ConfigError::InvalidUrl("not a url".into()));
+            assert_eq!(error.to_string(), "invalid URL 'not a url'");
+        }
+
         #[test]
         fn serialise() {
             let config = Config::Zipkin(ZipkinConfig::Http(ZipkinHttp {
+                buffer_limit: 10000,
                 flush_count: 100,
                 flush_timeout_millis: None,
                 headers: Default::default(),
+                request_timeout_millis: 10000,
                 url: String::from("http://localhost:1234"),
             }));
             let text = serde_yaml::to_string(&config).unwrap();
@@ -153,9 +430,11 @@ options:
 options:
   transport: http
   options:
+    buffer_limit: 10000
     flush_count: 100
     flush_timeout_millis: null
     headers: {}
+    request_timeout_millis: 10000
     url: http://localhost:1234
 "#
             );
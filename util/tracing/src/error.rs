@@ -18,6 +18,25 @@ where
     error
 }
 
+/// Invalid configuration details returned by `Config::validate`.
+#[derive(thiserror::Error, Clone, Debug, Eq, PartialEq)]
+pub enum ConfigError {
+    #[error("invalid value '{value}' for environment variable {name}")]
+    InvalidEnvOverride { name: String, value: String },
+
+    #[error("invalid header name '{0}'")]
+    InvalidHeaderName(String),
+
+    #[error("invalid value for header '{0}'")]
+    InvalidHeaderValue(String),
+
+    #[error("invalid URL '{0}'")]
+    InvalidUrl(String),
+
+    #[error("environment variable {0} is required by the other overrides")]
+    MissingEnvOverride(&'static str),
+}
+
 /// Error information returned by functions in case of errors.
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -9,12 +9,17 @@ use replicante_util_upkeep::Upkeep;
 
 mod backends;
 pub mod carriers;
+mod channel;
 mod config;
 mod error;
+mod worker;
 
+pub use self::channel::SpanChannel;
 pub use self::config::Config;
 pub use self::error::fail_span;
+pub use self::error::ConfigError;
 pub use self::error::Error;
+pub use self::worker::traced_worker;
 
 /// Wrapper for easier optional `Tracer`s.
 #[derive(Clone)]
@@ -28,6 +33,20 @@ impl MaybeTracer {
         MaybeTracer(tracer.into())
     }
 
+    /// Wrap an existing `Tracer`, bypassing configuration with [`tracer`].
+    ///
+    /// Useful for embedders and tests that already own a `Tracer` instance.
+    ///
+    /// [`tracer`]: fn.tracer.html
+    pub fn from_existing(tracer: Arc<Tracer>) -> MaybeTracer {
+        MaybeTracer(Some(tracer))
+    }
+
+    /// Access the wrapped `Tracer`, if any, to pass it to middlewares and other components.
+    pub fn tracer(&self) -> Option<Arc<Tracer>> {
+        self.0.clone()
+    }
+
     /// Execute the block if a `Tracer` is available.
     pub fn with<B, T>(&self, block: B) -> Option<T>
     where
@@ -38,40 +57,113 @@ impl MaybeTracer {
 }
 
 /// Additional options passed to tracer configuration.
+///
+/// Use `Opts::builder` to set optional options or `Opts::new` to use their defaults.
 pub struct Opts<'a> {
     flush_timeout: Duration,
     logger: Logger,
     service_name: &'a str,
+    span_channel: SpanChannel,
     upkeep: &'a mut Upkeep,
 }
 
 impl<'a> Opts<'a> {
+    /// Tracer options with default values for all optional settings.
     pub fn new<S>(service_name: S, logger: Logger, upkeep: &'a mut Upkeep) -> Opts<'a>
     where
         S: Into<&'a str>,
     {
-        Opts {
-            flush_timeout: Duration::from_secs(1),
-            logger,
-            service_name: service_name.into(),
-            upkeep,
+        Opts::builder(service_name, logger, upkeep).build()
+    }
+
+    /// Configure tracer options starting from the required settings.
+    pub fn builder<S>(service_name: S, logger: Logger, upkeep: &'a mut Upkeep) -> OptsBuilder<'a>
+    where
+        S: Into<&'a str>,
+    {
+        OptsBuilder {
+            opts: Opts {
+                flush_timeout: Duration::from_secs(1),
+                logger,
+                service_name: service_name.into(),
+                span_channel: SpanChannel::default(),
+                upkeep,
+            },
         }
     }
 
     /// Set the muximum delay between span flushes.
     ///
-    /// Some tracers' collectors allow this option to be set through the configuration.
-    /// In that case, the value from the user configuration overrides this option.
+    /// Equivalent to `OptsBuilder::flush_timeout`.
     pub fn flush_timeout(mut self, timeout: Duration) -> Opts<'a> {
         self.flush_timeout = timeout;
         self
     }
 }
 
+/// Fluent configuration of tracer `Opts`.
+pub struct OptsBuilder<'a> {
+    opts: Opts<'a>,
+}
+
+impl<'a> OptsBuilder<'a> {
+    /// Build the configured `Opts`.
+    pub fn build(self) -> Opts<'a> {
+        self.opts
+    }
+
+    /// Set the muximum delay between span flushes.
+    ///
+    /// Some tracers' collectors allow this option to be set through the configuration.
+    /// In that case, the value from the user configuration overrides this option.
+    pub fn flush_timeout(mut self, timeout: Duration) -> OptsBuilder<'a> {
+        self.opts.flush_timeout = timeout;
+        self
+    }
+
+    /// Set the capacity of the channel finished spans are sent to the collector over.
+    ///
+    /// Keep a clone of the `SpanChannel` to report the number of dropped spans.
+    pub fn span_channel(mut self, channel: SpanChannel) -> OptsBuilder<'a> {
+        self.opts.span_channel = channel;
+        self
+    }
+}
+
 /// Creates a new tracer based on the given configuration.
 pub fn tracer(config: Config, opts: Opts) -> Result<Tracer> {
     match config {
+        Config::Disabled => self::backends::disabled(opts),
         Config::Noop => self::backends::noop(opts),
         Config::Zipkin(config) => self::backends::zipkin(config, opts),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use opentracingrust::tracers::NoopTracer;
+
+    use super::MaybeTracer;
+
+    #[test]
+    fn from_existing() {
+        let (tracer, receiver) = NoopTracer::new();
+        let tracer = Arc::new(tracer);
+        let maybe = MaybeTracer::from_existing(Arc::clone(&tracer));
+        assert!(Arc::ptr_eq(&maybe.tracer().unwrap(), &tracer));
+        let span = maybe
+            .with(|tracer| tracer.span("test"))
+            .expect("tracer should be set");
+        span.finish().unwrap();
+        assert_eq!(receiver.try_recv().unwrap().name(), "test");
+    }
+
+    #[test]
+    fn without_tracer() {
+        let maybe = MaybeTracer::new(None);
+        assert!(maybe.tracer().is_none());
+        assert!(maybe.with(|_| ()).is_none());
+    }
+}
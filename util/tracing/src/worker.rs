@@ -0,0 +1,108 @@
+use humthreads::ThreadScope;
+use opentracingrust::Log;
+use opentracingrust::Span;
+
+use crate::MaybeTracer;
+
+/// Span name used when the current thread has no name.
+const UNNAMED_WORKER: &str = "worker";
+
+/// Wrap a worker thread body so it runs inside a root span named after the thread.
+///
+/// The span is passed to the body, which can tag it or use it as the parent of other spans,
+/// and is finished when the body returns or panics.
+/// If no tracer is available the body is called with `None`.
+///
+/// # Example
+/// ```ignore
+/// use humthreads::Builder;
+/// use replicante_util_tracing::traced_worker;
+///
+/// let thread = Builder::new("r:worker")
+///     .spawn(traced_worker(tracer, |scope, span| {
+///         // ... do work ...
+///     }))?;
+/// upkeep.register_thread(thread);
+/// ```
+pub fn traced_worker<F, T>(tracer: MaybeTracer, body: F) -> impl FnOnce(ThreadScope) -> T
+where
+    F: FnOnce(ThreadScope, Option<&mut Span>) -> T,
+{
+    move |scope| {
+        let name = std::thread::current()
+            .name()
+            .unwrap_or(UNNAMED_WORKER)
+            .to_string();
+        let mut guard = SpanGuard(tracer.with(|tracer| tracer.span(&name)));
+        body(scope, guard.0.as_mut())
+    }
+}
+
+/// Finish the wrapped span when dropped, even if the worker panics.
+struct SpanGuard(Option<Span>);
+
+impl Drop for SpanGuard {
+    fn drop(&mut self) {
+        if let Some(mut span) = self.0.take() {
+            if std::thread::panicking() {
+                span.tag("error", true);
+                span.log(
+                    Log::new()
+                        .log("event", "error")
+                        .log("message", "worker panicked"),
+                );
+            }
+            // There is nowhere to report errors to from a worker exiting.
+            let _ = span.finish();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use humthreads::Builder;
+    use opentracingrust::tracers::NoopTracer;
+
+    use super::traced_worker;
+    use crate::MaybeTracer;
+
+    #[test]
+    fn worker_produces_span() {
+        let (tracer, receiver) = NoopTracer::new();
+        let tracer = MaybeTracer::from_existing(Arc::new(tracer));
+        let thread = Builder::new("test-worker")
+            .spawn(traced_worker(tracer, |_, span| {
+                span.expect("span should be set").tag("worker.test", true);
+                42
+            }))
+            .expect("to spawn test thread");
+        let result = thread.join().expect("to join test thread");
+        assert_eq!(result, 42);
+        let span = receiver.try_recv().expect("worker span was not finished");
+        assert_eq!(span.name(), "test-worker");
+    }
+
+    #[test]
+    fn worker_panic_finishes_span() {
+        let (tracer, receiver) = NoopTracer::new();
+        let tracer = MaybeTracer::from_existing(Arc::new(tracer));
+        let thread = Builder::new("test-panic")
+            .spawn(traced_worker(tracer, |_, _| panic!("worker failed")))
+            .expect("to spawn test thread");
+        assert!(thread.join().is_err());
+        let span = receiver.try_recv().expect("worker span was not finished");
+        assert_eq!(span.name(), "test-panic");
+    }
+
+    #[test]
+    fn worker_without_tracer() {
+        let thread = Builder::new("test-untraced")
+            .spawn(traced_worker(MaybeTracer::new(None), |_, span| {
+                span.is_none()
+            }))
+            .expect("to spawn test thread");
+        assert!(thread.join().expect("to join test thread"));
+    }
+}
@@ -1,14 +1,20 @@
 use std::sync::atomic::AtomicBool;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+use std::time::Instant;
 
 use crossbeam_channel::unbounded;
 use crossbeam_channel::Receiver;
 use crossbeam_channel::Select;
 use crossbeam_channel::Sender;
+use humthreads::Error as HumthreadsError;
 use humthreads::ErrorKind as HumthreadsErrorKind;
-use humthreads::MapThread;
 use humthreads::Thread;
+use opentracingrust::Span;
+use opentracingrust::SpanContext;
+use opentracingrust::Tracer;
 use signal_hook::SigId;
 use slog::debug;
 use slog::error;
@@ -17,21 +23,96 @@ use slog::warn;
 use slog::Discard;
 use slog::Logger;
 
+/// Type alias for shutdown callbacks to improve code readability.
+type Callback = Box<dyn Fn() -> Result<(), String> + Send>;
+
+/// Priority of shutdown callbacks registered with [`Upkeep::on_shutdown`].
+///
+/// [`Upkeep::on_shutdown`]: struct.Upkeep.html#method.on_shutdown
+pub const PRIORITY_DEFAULT: i32 = 0;
+
+/// Priority of shutdown callbacks registered with [`Upkeep::register_tracing_flush`].
+///
+/// Tracing is flushed after default callbacks so spans emitted by them are not lost.
+///
+/// [`Upkeep::register_tracing_flush`]: struct.Upkeep.html#method.register_tracing_flush
+pub const PRIORITY_TRACING_FLUSH: i32 = 100;
+
+/// Priority of shutdown callbacks registered with [`Upkeep::register_logging_flush`].
+///
+/// Logs are flushed last so errors reported by other callbacks (including the
+/// tracing flush) are not lost.
+///
+/// [`Upkeep::register_logging_flush`]: struct.Upkeep.html#method.register_logging_flush
+pub const PRIORITY_LOGGING_FLUSH: i32 = 200;
+
+/// Process exit code for clean shutdowns (see [`Upkeep::exit_code`]).
+///
+/// [`Upkeep::exit_code`]: struct.Upkeep.html#method.exit_code
+pub const EXIT_CODE_CLEAN: i32 = 0;
+
+/// Process exit code for unclean shutdowns not caused by a thread panic.
+pub const EXIT_CODE_UNCLEAN: i32 = 1;
+
+/// Process exit code for shutdowns caused by a required thread exiting without panicking.
+///
+/// These shutdowns are clean but, unlike other clean shutdowns, were not requested
+/// so supervisors may want to tell them apart (for example to restart the process).
+pub const EXIT_CODE_REQUIRED_THREAD_EXITED: i32 = 3;
+
+/// Process exit code for unclean shutdowns caused by a thread panic.
+///
+/// Matches the exit code of processes terminated by a panic on the main thread.
+pub const EXIT_CODE_THREAD_PANICKED: i32 = 101;
+
 /// Block the calling thread until shutdown is requested.
 ///
 /// Shutdown is requested when:
 ///
 ///   * The process receives SIGINT.
-///   * A registered thread panics.
+///   * A message is received on a registered trigger.
+///   * A registered thread panics (and can't be restarted).
 ///   * A required thread exists (optional threads are allowed to exit gracefully).
 ///
 /// # Shutdown Flow
 ///
-///  1. Request all registered threads to shutdown.
-///  2. Execute all on_shutdown callbacks.
-///  3  Wait for all registered threads to exit.
+///  1. Execute all on_shutdown_begin callbacks.
+///  2. Request all registered threads to shutdown.
+///  3. Execute all on_shutdown callbacks, in priority order.
+///  4. Wait for all registered threads to exit.
+///
+/// Threads are waited on forever unless a timeout is set with [`Upkeep::shutdown_timeout`].
+/// The timeout starts once all shutdown callbacks have finished.
+/// If threads are still running when the timeout expires they are logged and left running:
+/// the shutdown outcome is [`ShutdownOutcome::TimedOut`] and it is up to the caller
+/// to exit the process (as [`Upkeep::keepalive_and_exit`] does).
+///
+/// [`ShutdownOutcome::TimedOut`]: enum.ShutdownOutcome.html#variant.TimedOut
+/// [`Upkeep::keepalive_and_exit`]: #method.keepalive_and_exit
+/// [`Upkeep::shutdown_timeout`]: #method.shutdown_timeout
+///
+/// The shutdown is not clean if a fallible callback registered with
+/// [`Upkeep::on_shutdown_try`] returns an error.
+///
+/// [`Upkeep::on_shutdown_try`]: #method.on_shutdown_try
 ///
 /// Threads and handlers are iterated on in registration order.
+/// Shutdown callbacks with a lower priority run before those with a higher priority
+/// (see [`Upkeep::on_shutdown_priority`]) so, regardless of registration order:
+///
+///   1. Callbacks registered with [`Upkeep::on_shutdown`] run first.
+///   2. Then the tracing flush registered with [`Upkeep::register_tracing_flush`].
+///   3. Then the logging flush registered with [`Upkeep::register_logging_flush`].
+///
+/// [`Upkeep::on_shutdown`]: #method.on_shutdown
+/// [`Upkeep::on_shutdown_priority`]: #method.on_shutdown_priority
+/// [`Upkeep::register_logging_flush`]: #method.register_logging_flush
+/// [`Upkeep::register_tracing_flush`]: #method.register_tracing_flush
+///
+/// If a tracer is set with [`Upkeep::set_tracer`] the shutdown flow is traced
+/// with an `upkeep.shutdown` span and child spans for each phase, callback and thread.
+///
+/// [`Upkeep::set_tracer`]: #method.set_tracer
 ///
 /// # Signal Handling
 /// When a process is sent SIGINT the shutdown flow begins.
@@ -40,6 +121,12 @@ use slog::Logger;
 /// If a second SIGINT is received while the process is shutting down
 /// it will instead exit immediately.
 ///
+/// Handlers for SIGHUP can also be registered with [`Upkeep::register_reload_signal`]
+/// to execute [`Upkeep::on_reload`] callbacks, for example to reopen log files.
+///
+/// [`Upkeep::on_reload`]: #method.on_reload
+/// [`Upkeep::register_reload_signal`]: #method.register_reload_signal
+///
 /// # Example
 /// ```no_run
 /// # use replicante_util_upkeep::Upkeep;
@@ -48,27 +135,52 @@ use slog::Logger;
 /// up.keepalive();
 /// ```
 pub struct Upkeep {
-    callbacks: Vec<Box<dyn Fn()>>,
+    begin_callbacks: Vec<Callback>,
+    callbacks: Vec<(i32, Callback)>,
+    handle_sender: Sender<()>,
     logger: Logger,
     registered_signals: Vec<SigId>,
+    reload_callbacks: Vec<Callback>,
+    reload_receiver: Receiver<()>,
+    reload_sender: Option<Sender<()>>,
+    shutdown_outcome: Option<ShutdownOutcome>,
+    shutdown_reason: Option<ShutdownReason>,
+    shutdown_timeout: Option<Duration>,
+    shutdown_flag: Arc<AtomicBool>,
     signal_flag: Arc<AtomicBool>,
     signal_receiver: Receiver<()>,
     signal_sender: Option<Sender<()>>,
     threads: Vec<ThreadMeta>,
+    tracer: Option<Arc<Tracer>>,
+    triggers: Vec<Receiver<()>>,
 }
 
 impl Upkeep {
     pub fn new() -> Upkeep {
         let (signal_sender, signal_receiver) = unbounded();
         let signal_sender = Some(signal_sender);
+        let (reload_sender, reload_receiver) = unbounded();
+        let reload_sender = Some(reload_sender);
+        let (handle_sender, handle_receiver) = unbounded();
         Upkeep {
+            begin_callbacks: Vec::new(),
             callbacks: Vec::new(),
+            handle_sender,
             logger: Logger::root(Discard, o!()),
             registered_signals: Vec::new(),
+            reload_callbacks: Vec::new(),
+            reload_receiver,
+            reload_sender,
+            shutdown_outcome: None,
+            shutdown_reason: None,
+            shutdown_timeout: None,
+            shutdown_flag: Arc::new(AtomicBool::new(false)),
             signal_flag: Arc::new(AtomicBool::new(false)),
             signal_receiver,
             signal_sender,
             threads: Vec::new(),
+            tracer: None,
+            triggers: vec![handle_receiver],
         }
     }
 
@@ -76,64 +188,240 @@ impl Upkeep {
     ///
     /// # Returns
     /// This method returns `true` if the process shuts down cleanly.
+    /// Use [`Upkeep::keepalive_status`] to find out why a shutdown was not clean.
+    ///
+    /// [`Upkeep::keepalive_status`]: #method.keepalive_status
     pub fn keepalive(&mut self) -> bool {
+        self.keepalive_status().is_clean()
+    }
+
+    /// Block the calling thread waiting for the process to shutdown and report the outcome.
+    pub fn keepalive_status(&mut self) -> ShutdownOutcome {
         // Use crossbeam_channel::Select to poll for signals or thread exists:
         //
         //   - Generate a Select set to wait on.
         //   - Use the ready API to wait (select API seems to deadlock unless with timeout).
         //   - When a thread joins remove it from the vector.
-        let mut clean_exit = true;
-        loop {
-            let mut set = self.select_set();
-            let index = set.ready();
-            match index {
-                0 => {
-                    warn!(self.logger, "Shutdown: signal received");
-                    break;
-                }
-                n => {
-                    let thread = &self.threads[n - 1];
-                    let panicked = match thread.handle.join() {
-                        Ok(()) => false,
-                        Err(error) => match error.kind() {
-                            HumthreadsErrorKind::Join(_) => {
-                                error!(
-                                    self.logger,
-                                    "Thread panicked";
-                                    "error" => error.to_string(),
-                                );
-                                clean_exit = false;
-                                true
-                            }
-                            _ => false,
-                        },
-                    };
-                    if panicked {
-                        warn!(self.logger, "Shutdown: thread panicked");
-                        break;
-                    }
-                    if thread.required {
-                        warn!(self.logger, "Shutdown: thread exited");
-                        break;
+        let reason = loop {
+            let index = self.select_set().ready();
+            if index == 0 {
+                warn!(self.logger, "Shutdown: signal received");
+                break ShutdownReason::Signal;
+            }
+            if index == 1 {
+                // Consume the message so the reload signal is not left ready.
+                let _ = self.reload_receiver.try_recv();
+                self.reload();
+                continue;
+            }
+            if index <= self.triggers.len() + 1 {
+                // Consume the message so the trigger is not left ready.
+                let _ = self.triggers[index - 2].try_recv();
+                warn!(self.logger, "Shutdown: trigger received");
+                break ShutdownReason::Programmatic;
+            }
+
+            let index = index - 2 - self.triggers.len();
+            let thread = &self.threads[index];
+            let panicked = match thread.handle.join() {
+                Ok(()) => false,
+                Err(error) => match error.kind() {
+                    HumthreadsErrorKind::Join(_) => {
+                        error!(
+                            self.logger,
+                            "Thread panicked";
+                            "error" => error.to_string(),
+                        );
+                        true
                     }
-                }
+                    _ => false,
+                },
             };
+            if panicked {
+                if self.restart_thread(index) {
+                    continue;
+                }
+                warn!(self.logger, "Shutdown: thread panicked");
+                break ShutdownReason::ThreadPanicked;
+            }
+            if self.threads[index].required {
+                warn!(self.logger, "Shutdown: thread exited");
+                break ShutdownReason::ThreadExited;
+            }
 
             // Can reach here only if an optional thread exited without a panic.
-            drop(set);
-            self.threads.remove(index - 1);
+            self.threads.remove(index);
+        };
+
+        self.shutdown_flag.store(true, Ordering::Relaxed);
+        self.shutdown_reason = Some(reason);
+        let mut span = self.span("upkeep.shutdown", None);
+        if let Some(span) = span.as_mut() {
+            span.tag("shutdown.reason", format!("{:?}", reason));
         }
+        let context = span.as_ref().map(|span| span.context().clone());
+        let callbacks_ok = self.shutdown(context.as_ref());
+        let threads_ok = self.join_threads(context.as_ref());
+        self.finish_span(span);
+        let outcome = match (reason, callbacks_ok, threads_ok) {
+            (ShutdownReason::ThreadPanicked, _, _) | (_, _, Some(false)) => {
+                ShutdownOutcome::ThreadPanicked
+            }
+            (_, _, None) => ShutdownOutcome::TimedOut,
+            (_, false, _) => ShutdownOutcome::CallbackFailed,
+            (ShutdownReason::ThreadExited, _, _) => ShutdownOutcome::RequiredThreadExited,
+            _ => ShutdownOutcome::Clean,
+        };
+        self.shutdown_outcome = Some(outcome);
+        outcome
+    }
 
-        self.shutdown();
-        self.join_threads() && clean_exit
+    /// Block the calling thread until the process shuts down and then exit it.
+    ///
+    /// The process exits with the code returned by [`Upkeep::exit_code`]
+    /// once all callbacks are executed and all threads are joined.
+    ///
+    /// [`Upkeep::exit_code`]: #method.exit_code
+    pub fn keepalive_and_exit(mut self) -> ! {
+        self.keepalive();
+        let code = self.exit_code();
+        drop(self);
+        std::process::exit(code)
+    }
+
+    /// Process exit code matching the outcome of the last shutdown flow.
+    ///
+    ///   * [`EXIT_CODE_CLEAN`] if the shutdown was clean (or [`Upkeep::keepalive`] did not return).
+    ///   * [`EXIT_CODE_REQUIRED_THREAD_EXITED`] if a required thread exited without panicking.
+    ///   * [`EXIT_CODE_THREAD_PANICKED`] if a thread panicked.
+    ///   * [`EXIT_CODE_UNCLEAN`] for any other unclean shutdown.
+    ///
+    /// [`EXIT_CODE_CLEAN`]: constant.EXIT_CODE_CLEAN.html
+    /// [`EXIT_CODE_REQUIRED_THREAD_EXITED`]: constant.EXIT_CODE_REQUIRED_THREAD_EXITED.html
+    /// [`EXIT_CODE_THREAD_PANICKED`]: constant.EXIT_CODE_THREAD_PANICKED.html
+    /// [`EXIT_CODE_UNCLEAN`]: constant.EXIT_CODE_UNCLEAN.html
+    /// [`Upkeep::keepalive`]: #method.keepalive
+    pub fn exit_code(&self) -> i32 {
+        match self.shutdown_outcome {
+            None => EXIT_CODE_CLEAN,
+            Some(ShutdownOutcome::RequiredThreadExited) => EXIT_CODE_REQUIRED_THREAD_EXITED,
+            Some(ShutdownOutcome::ThreadPanicked) => EXIT_CODE_THREAD_PANICKED,
+            Some(outcome) if outcome.is_clean() => EXIT_CODE_CLEAN,
+            Some(_) => EXIT_CODE_UNCLEAN,
+        }
     }
 
     /// Register a callback to be executed when a shutdown request is received.
     pub fn on_shutdown<F>(&mut self, callback: F)
     where
-        F: Fn() + 'static,
+        F: Fn() + Send + 'static,
+    {
+        self.on_shutdown_priority(PRIORITY_DEFAULT, callback)
+    }
+
+    /// Register a callback to be executed, by priority, when a shutdown request is received.
+    ///
+    /// Callbacks with a lower priority are executed first while callbacks with
+    /// the same priority are executed in registration order.
+    pub fn on_shutdown_priority<F>(&mut self, priority: i32, callback: F)
+    where
+        F: Fn() + Send + 'static,
+    {
+        self.push_callback(
+            priority,
+            Box::new(move || {
+                callback();
+                Ok(())
+            }),
+        )
+    }
+
+    /// Register a fallible callback to be executed when a shutdown request is received.
+    ///
+    /// Errors returned by the callback are logged and cause [`Upkeep::keepalive`]
+    /// to report an unclean shutdown.
+    ///
+    /// [`Upkeep::keepalive`]: #method.keepalive
+    pub fn on_shutdown_try<F, E>(&mut self, callback: F)
+    where
+        F: Fn() -> Result<(), E> + Send + 'static,
+        E: std::fmt::Display,
+    {
+        self.push_callback(
+            PRIORITY_DEFAULT,
+            Box::new(move || callback().map_err(|error| error.to_string())),
+        )
+    }
+
+    /// Register a callback to flush logs once all other shutdown callbacks are done.
+    ///
+    /// The callback runs with [`PRIORITY_LOGGING_FLUSH`], after the tracing flush.
+    ///
+    /// [`PRIORITY_LOGGING_FLUSH`]: constant.PRIORITY_LOGGING_FLUSH.html
+    pub fn register_logging_flush<F>(&mut self, flush: F)
+    where
+        F: Fn() + Send + 'static,
+    {
+        self.on_shutdown_priority(PRIORITY_LOGGING_FLUSH, flush)
+    }
+
+    /// Register a callback to flush tracing spans once other shutdown callbacks are done.
+    ///
+    /// The callback runs with [`PRIORITY_TRACING_FLUSH`], before the logging flush.
+    ///
+    /// [`PRIORITY_TRACING_FLUSH`]: constant.PRIORITY_TRACING_FLUSH.html
+    pub fn register_tracing_flush<F>(&mut self, flush: F)
+    where
+        F: Fn() + Send + 'static,
     {
-        self.callbacks.push(Box::new(callback))
+        self.on_shutdown_priority(PRIORITY_TRACING_FLUSH, flush)
+    }
+
+    /// Register a callback to be executed as soon as a shutdown request is received.
+    ///
+    /// These callbacks are executed before registered threads are asked to shutdown
+    /// so they can be used to notify external systems (for example to deregister from
+    /// load balancers) before the, potentially slow, shutdown process starts.
+    pub fn on_shutdown_begin<F>(&mut self, callback: F)
+    where
+        F: Fn() + Send + 'static,
+    {
+        self.begin_callbacks.push(Box::new(move || {
+            callback();
+            Ok(())
+        }))
+    }
+
+    /// Register a callback to be executed when SIGHUP is received.
+    ///
+    /// Callbacks are executed, in registration order, by [`Upkeep::keepalive`]
+    /// once [`Upkeep::register_reload_signal`] is called.
+    ///
+    /// [`Upkeep::keepalive`]: #method.keepalive
+    /// [`Upkeep::register_reload_signal`]: #method.register_reload_signal
+    pub fn on_reload<F>(&mut self, callback: F)
+    where
+        F: Fn() + Send + 'static,
+    {
+        self.reload_callbacks.push(Box::new(move || {
+            callback();
+            Ok(())
+        }))
+    }
+
+    /// Register a signal handler for SIGHUP to execute reload callbacks.
+    pub fn register_reload_signal(&mut self) -> Result<(), ::std::io::Error> {
+        let sender = match self.reload_sender.take() {
+            Some(sender) => sender,
+            None => return Ok(()),
+        };
+        let callback = move || {
+            let _ = sender.send(());
+        };
+        let signal = signal_hook::consts::signal::SIGHUP;
+        let signal_id = unsafe { signal_hook::low_level::register(signal, callback) }?;
+        self.registered_signals.push(signal_id);
+        Ok(())
     }
 
     /// Register signal handers for SIGINT and SIGTERM.
@@ -147,6 +435,7 @@ impl Upkeep {
             signal_hook::consts::signal::SIGTERM,
         ];
         for signal in signals.into_iter() {
+            let shutdown_flag = Arc::clone(&self.shutdown_flag);
             let signal_flag = Arc::clone(&self.signal_flag);
             let signal_sender = sender.clone();
             let callback = move || {
@@ -154,6 +443,7 @@ impl Upkeep {
                     ::std::process::exit(1);
                 }
                 signal_flag.store(true, Ordering::Relaxed);
+                shutdown_flag.store(true, Ordering::Relaxed);
                 let _ = signal_sender.send(());
             };
             let signal_id = unsafe { signal_hook::low_level::register(signal, callback) }?;
@@ -173,8 +463,30 @@ impl Upkeep {
     /// [`Thread`]: https://docs.rs/humthreads/0.1.2/humthreads/struct.Thread.html
     pub fn register_thread<T: Send + 'static>(&mut self, thread: Thread<T>) {
         let thread = ThreadMeta {
-            handle: thread.map(|_| ()),
+            handle: Box::new(thread),
+            name: None,
             required: true,
+            restart: None,
+        };
+        self.threads.push(thread);
+    }
+
+    /// Similar to [`Upkeep::register_thread`] but the thread is named in [`ThreadStatus`]es.
+    ///
+    /// Use the humthreads full name of the thread so its activity can be reported.
+    ///
+    /// [`ThreadStatus`]: struct.ThreadStatus.html
+    /// [`Upkeep::register_thread`]: #method.register_thread
+    pub fn register_thread_named<S, T>(&mut self, name: S, thread: Thread<T>)
+    where
+        S: Into<String>,
+        T: Send + 'static,
+    {
+        let thread = ThreadMeta {
+            handle: Box::new(thread),
+            name: Some(name.into()),
+            required: true,
+            restart: None,
         };
         self.threads.push(thread);
     }
@@ -184,63 +496,478 @@ impl Upkeep {
     /// [`Upkeep::register_thread`]: #method.register_thread
     pub fn register_thread_optional<T: Send + 'static>(&mut self, thread: Thread<T>) {
         let thread = ThreadMeta {
-            handle: thread.map(|_| ()),
+            handle: Box::new(thread),
+            name: None,
+            required: false,
+            restart: None,
+        };
+        self.threads.push(thread);
+    }
+
+    /// Similar to [`Upkeep::register_thread_optional`] but the thread is named in
+    /// [`ThreadStatus`]es.
+    ///
+    /// Use the humthreads full name of the thread so its activity can be reported.
+    ///
+    /// [`ThreadStatus`]: struct.ThreadStatus.html
+    /// [`Upkeep::register_thread_optional`]: #method.register_thread_optional
+    pub fn register_thread_optional_named<S, T>(&mut self, name: S, thread: Thread<T>)
+    where
+        S: Into<String>,
+        T: Send + 'static,
+    {
+        let thread = ThreadMeta {
+            handle: Box::new(thread),
+            name: Some(name.into()),
             required: false,
+            restart: None,
         };
         self.threads.push(thread);
     }
 
+    /// Similar to [`Upkeep::register_thread_optional`] but panics can be recovered from.
+    ///
+    /// The `spawn` function is called to start the thread when it is registered and
+    /// again every time the thread panics, as long as the [`RestartPolicy`] allows it.
+    /// Once all restart attempts are exhausted a panic will shutdown the process.
+    ///
+    /// Threads are restarted on the thread calling [`Upkeep::keepalive`] so the
+    /// [`RestartPolicy`] backoff delays signal handling and other thread exits.
+    ///
+    /// [`RestartPolicy`]: struct.RestartPolicy.html
+    /// [`Upkeep::keepalive`]: #method.keepalive
+    /// [`Upkeep::register_thread_optional`]: #method.register_thread_optional
+    pub fn register_thread_restartable<F, S, T>(
+        &mut self,
+        name: S,
+        policy: RestartPolicy,
+        spawn: F,
+    ) -> Result<(), HumthreadsError>
+    where
+        F: Fn() -> Result<Thread<T>, HumthreadsError> + Send + 'static,
+        S: Into<String>,
+        T: Send + 'static,
+    {
+        let name = name.into();
+        let spawn = move || spawn().map(|thread| Box::new(thread) as Box<dyn ThreadHandle>);
+        let thread = ThreadMeta {
+            handle: spawn()?,
+            name: Some(name.clone()),
+            required: false,
+            restart: Some(ThreadRestart {
+                attempts: 0,
+                name,
+                policy,
+                spawn: Box::new(spawn),
+            }),
+        };
+        self.threads.push(thread);
+        Ok(())
+    }
+
+    /// Register a channel that begins the shutdown flow when a message is received.
+    ///
+    /// Triggers allow arbitrary conditions (such as a configuration watcher detecting
+    /// changes that can't be applied at runtime) to shutdown the process.
+    /// Shutdowns caused by triggers report [`ShutdownReason::Programmatic`].
+    ///
+    /// Disconnected triggers also begin the shutdown flow.
+    ///
+    /// [`ShutdownReason::Programmatic`]: enum.ShutdownReason.html#variant.Programmatic
+    pub fn register_trigger(&mut self, trigger: Receiver<()>) {
+        self.triggers.push(trigger);
+    }
+
+    /// Return a handle to begin the shutdown flow from other threads.
+    ///
+    /// Shutdowns requested through handles report [`ShutdownReason::Programmatic`].
+    /// Unlike registered triggers, dropping all handles does not begin the shutdown flow.
+    ///
+    /// [`ShutdownReason::Programmatic`]: enum.ShutdownReason.html#variant.Programmatic
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        ShutdownHandle {
+            flag: Arc::clone(&self.shutdown_flag),
+            sender: self.handle_sender.clone(),
+        }
+    }
+
+    /// Begin the shutdown flow from application code.
+    ///
+    /// The shutdown is processed by [`Upkeep::keepalive`] like any other, even if it
+    /// was requested before [`Upkeep::keepalive`] is called, and reports
+    /// [`ShutdownReason::Programmatic`].
+    ///
+    /// [`ShutdownReason::Programmatic`]: enum.ShutdownReason.html#variant.Programmatic
+    /// [`Upkeep::keepalive`]: #method.keepalive
+    pub fn trigger_shutdown(&self) {
+        self.shutdown_handle().shutdown();
+    }
+
+    /// Run [`Upkeep::keepalive`] on a dedicated `upkeep-keepalive` thread.
+    ///
+    /// Returns a handle to join the thread, which returns the clean exit flag,
+    /// and a [`ShutdownHandle`] to begin the shutdown flow.
+    ///
+    /// # Panics
+    /// If the thread could not be spawned.
+    ///
+    /// [`ShutdownHandle`]: struct.ShutdownHandle.html
+    /// [`Upkeep::keepalive`]: #method.keepalive
+    pub fn spawn_keepalive(mut self) -> (JoinHandle<bool>, ShutdownHandle) {
+        let handle = self.shutdown_handle();
+        let thread = std::thread::Builder::new()
+            .name("upkeep-keepalive".into())
+            .spawn(move || self.keepalive())
+            .expect("failed to spawn upkeep keepalive thread");
+        (thread, handle)
+    }
+
+    /// Set the tracer used to trace the shutdown flow.
+    pub fn set_tracer(&mut self, tracer: Arc<Tracer>) {
+        self.tracer = Some(tracer);
+    }
+
     /// Set the logger to be used by the `Upkeep` instance.
     pub fn set_logger(&mut self, logger: Logger) {
         self.logger = logger;
     }
 
+    /// Reason the last shutdown flow started, if [`Upkeep::keepalive`] returned.
+    ///
+    /// [`Upkeep::keepalive`]: #method.keepalive
+    pub fn shutdown_reason(&self) -> Option<ShutdownReason> {
+        self.shutdown_reason
+    }
+
+    /// Limit how long to wait for registered threads to exit during shutdown.
+    ///
+    /// The deadline starts only after all shutdown callbacks have finished,
+    /// so it does not include the time spent running them.
+    /// Once the timeout expires the names of threads still running are logged and
+    /// the shutdown flow ends with [`ShutdownOutcome::TimedOut`] without waiting for them.
+    /// The threads keep running until the process exits.
+    ///
+    /// [`ShutdownOutcome::TimedOut`]: enum.ShutdownOutcome.html#variant.TimedOut
+    pub fn shutdown_timeout(&mut self, timeout: Duration) {
+        self.shutdown_timeout = Some(timeout);
+    }
+
+    /// Check if the shutdown flow has started.
+    ///
+    /// Shutdown is requested once a shutdown signal is received or [`Upkeep::keepalive`]
+    /// begins the shutdown flow for any other reason.
+    ///
+    /// [`Upkeep::keepalive`]: #method.keepalive
+    pub fn shutdown_requested(&self) -> bool {
+        self.shutdown_flag.load(Ordering::Relaxed)
+    }
+
+    /// Return a flag components can check to detect when the shutdown flow has started.
+    ///
+    /// This allows components without access to the `Upkeep` instance, such as
+    /// request handlers, to change behaviour (for example failing readiness checks)
+    /// while the process is shutting down. See [`Upkeep::shutdown_requested`].
+    ///
+    /// To begin the shutdown flow instead use [`Upkeep::shutdown_handle`].
+    ///
+    /// [`Upkeep::shutdown_handle`]: #method.shutdown_handle
+    /// [`Upkeep::shutdown_requested`]: #method.shutdown_requested
+    pub fn shutdown_flag(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.shutdown_flag)
+    }
+
+    /// Status of the threads currently managed by this instance, in registration order.
+    ///
+    /// Optional threads that exited are no longer managed and are not reported.
+    /// Thread activities are looked up by name among the threads known to humthreads.
+    pub fn thread_statuses(&self) -> Vec<ThreadStatus> {
+        let known = humthreads::registered_threads();
+        self.threads
+            .iter()
+            .map(|thread| {
+                let activity = thread.name.as_ref().and_then(|name| {
+                    known
+                        .iter()
+                        .find(|status| &status.name == name || &status.short_name == name)
+                        .and_then(|status| status.activity.clone())
+                });
+                ThreadStatus {
+                    activity,
+                    name: thread.name.clone(),
+                    required: thread.required,
+                    restartable: thread.restart.is_some(),
+                }
+            })
+            .collect()
+    }
+
+    /// Finish a tracing span, if any, logging errors.
+    fn finish_span(&self, span: Option<Span>) {
+        if let Some(Err(error)) = span.map(Span::finish) {
+            error!(
+                self.logger,
+                "Failed to finish shutdown tracing span";
+                "error" => error.to_string(),
+            );
+        }
+    }
+
     /// Wait for each thread to join.
-    fn join_threads(&mut self) -> bool {
+    ///
+    /// Returns `None` if threads are still running when the shutdown timeout expires.
+    fn join_threads(&mut self, parent: Option<&SpanContext>) -> Option<bool> {
         debug!(self.logger, "Joining with registered threads");
-        let mut clean_exit = true;
-        for thread in self.threads.drain(..) {
-            if let Err(error) = thread.handle.join() {
+        let span = self.span("upkeep.join_threads", parent);
+        let context = span.as_ref().map(|span| span.context().clone());
+        let threads: Vec<ThreadMeta> = self.threads.drain(..).collect();
+        let clean_exit =
+            match self.shutdown_timeout {
+                None => Some(threads.iter().enumerate().fold(
+                    true,
+                    |clean_exit, (index, thread)| {
+                        self.join_thread(index, thread, context.as_ref()) && clean_exit
+                    },
+                )),
+                Some(timeout) => {
+                    let deadline = Instant::now() + timeout;
+                    match self.join_threads_until(threads, deadline, context.as_ref()) {
+                        Ok(clean_exit) => Some(clean_exit),
+                        Err(stuck) => {
+                            error!(
+                                self.logger,
+                                "Shutdown: threads did not exit in time";
+                                "threads" => stuck.join(", "),
+                                "timeout_millis" => timeout.as_millis() as u64,
+                            );
+                            None
+                        }
+                    }
+                }
+            };
+        self.finish_span(span);
+        clean_exit
+    }
+
+    /// Join a thread that exited (or block until it does).
+    ///
+    /// Returns `false` if the thread panicked.
+    fn join_thread(&self, index: usize, thread: &ThreadMeta, parent: Option<&SpanContext>) -> bool {
+        let mut thread_span = self.span("upkeep.join_thread", parent);
+        if let Some(span) = thread_span.as_mut() {
+            span.tag("thread.index", index as i64);
+        }
+        let result = thread.handle.join();
+        self.finish_span(thread_span);
+        match result {
+            Ok(()) => true,
+            Err(error) => {
                 if let HumthreadsErrorKind::JoinedAlready = error.kind() {
                     debug!(self.logger, "Joined thread twice");
-                    continue;
+                    return true;
                 }
                 error!(
                     self.logger,
                     "Thread panicked";
                     "error" => error.to_string(),
                 );
-                clean_exit = false;
+                false
             }
         }
-        clean_exit
     }
 
-    /// Return a crossbeam_channel::Select set to wait for signals or threads.
+    /// Join threads as they exit until the deadline.
+    ///
+    /// Returns the names of the threads still running when the deadline expires.
+    fn join_threads_until(
+        &self,
+        threads: Vec<ThreadMeta>,
+        deadline: Instant,
+        parent: Option<&SpanContext>,
+    ) -> Result<bool, Vec<String>> {
+        let mut clean_exit = true;
+        let mut pending: Vec<(usize, ThreadMeta)> = threads.into_iter().enumerate().collect();
+        while !pending.is_empty() {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            let ready = {
+                let mut set = Select::new();
+                for (_, thread) in &pending {
+                    thread.handle.select_add(&mut set);
+                }
+                set.ready_timeout(timeout)
+            };
+            match ready {
+                Ok(ready) => {
+                    let (index, thread) = pending.remove(ready);
+                    clean_exit = self.join_thread(index, &thread, parent) && clean_exit;
+                }
+                Err(_) => {
+                    let stuck = pending
+                        .iter()
+                        .map(|(index, thread)| match thread.name.as_ref() {
+                            Some(name) => name.clone(),
+                            None => format!("<unnamed thread #{}>", index),
+                        })
+                        .collect();
+                    return Err(stuck);
+                }
+            }
+        }
+        Ok(clean_exit)
+    }
+
+    /// Restart the thread at the given index, if allowed by its restart policy.
+    ///
+    /// Returns `true` if the thread was restarted.
+    fn restart_thread(&mut self, index: usize) -> bool {
+        let thread = &mut self.threads[index];
+        let restart = match thread.restart.as_mut() {
+            None => return false,
+            Some(restart) => restart,
+        };
+        if restart.attempts >= restart.policy.max_restarts {
+            error!(
+                self.logger,
+                "Thread restart attempts exhausted";
+                "thread" => &restart.name,
+                "attempts" => restart.attempts,
+            );
+            return false;
+        }
+
+        restart.attempts += 1;
+        warn!(
+            self.logger,
+            "Restarting panicked thread";
+            "thread" => &restart.name,
+            "attempt" => restart.attempts,
+        );
+        std::thread::sleep(restart.policy.backoff);
+        match (restart.spawn)() {
+            Ok(handle) => {
+                thread.handle = handle;
+                true
+            }
+            Err(error) => {
+                error!(
+                    self.logger,
+                    "Failed to restart thread";
+                    "thread" => &restart.name,
+                    "error" => error.to_string(),
+                );
+                false
+            }
+        }
+    }
+
+    /// Return a crossbeam_channel::Select set to wait for signals, triggers or threads.
     ///
     /// The returned set has the following propertied:
     ///
     ///   - idx 0 == signals receiver
-    ///   - idx n == self.threads.get(n - 1)
+    ///   - idx 1 == reload receiver
+    ///   - idx n in 2..=triggers.len() + 1 == self.triggers[n - 2]
+    ///   - idx n > triggers.len() + 1 == self.threads[n - 2 - triggers.len()]
     fn select_set<'a, 'b: 'a>(&'b self) -> Select<'a> {
         let mut set = Select::new();
         set.recv(&self.signal_receiver);
+        set.recv(&self.reload_receiver);
+        for trigger in &self.triggers {
+            set.recv(trigger);
+        }
         for thread in &self.threads {
             thread.handle.select_add(&mut set);
         }
         set
     }
 
+    /// Execute reload callbacks after SIGHUP is received.
+    fn reload(&self) {
+        warn!(self.logger, "Reload: signal received");
+        let span = self.span("upkeep.reload", None);
+        let context = span.as_ref().map(|span| span.context().clone());
+        self.run_callbacks(
+            "upkeep.callbacks_reload",
+            self.reload_callbacks.iter(),
+            context.as_ref(),
+        );
+        self.finish_span(span);
+    }
+
     /// Handle process shutdown and trigger callback notifications.
-    fn shutdown(&mut self) {
+    ///
+    /// Returns `false` if any callback failed.
+    fn shutdown(&mut self, parent: Option<&SpanContext>) -> bool {
+        debug!(self.logger, "Executing shutdown begin callbacks");
+        let begin_ok = self.run_callbacks(
+            "upkeep.callbacks_begin",
+            self.begin_callbacks.iter(),
+            parent,
+        );
         debug!(self.logger, "Requesting shutdowns for registered threads");
+        let span = self.span("upkeep.request_shutdown", parent);
         for thread in &self.threads {
             thread.handle.request_shutdown();
         }
+        self.finish_span(span);
         debug!(self.logger, "Executing shutdown callbacks");
-        for callback in &self.callbacks {
-            callback();
+        let callbacks = self.callbacks.iter().map(|(_, callback)| callback);
+        let callbacks_ok = self.run_callbacks("upkeep.callbacks", callbacks, parent);
+        begin_ok && callbacks_ok
+    }
+
+    /// Execute shutdown callbacks, each in its own span.
+    ///
+    /// Returns `false` if any callback failed.
+    fn run_callbacks<'a, I>(&self, name: &str, callbacks: I, parent: Option<&SpanContext>) -> bool
+    where
+        I: Iterator<Item = &'a Callback>,
+    {
+        let span = self.span(name, parent);
+        let context = span.as_ref().map(|span| span.context().clone());
+        let mut success = true;
+        for (index, callback) in callbacks.enumerate() {
+            let mut callback_span = self.span("upkeep.callback", context.as_ref());
+            if let Some(span) = callback_span.as_mut() {
+                span.tag("callback.index", index as i64);
+            }
+            if let Err(error) = callback() {
+                error!(
+                    self.logger,
+                    "Shutdown callback failed";
+                    "callback" => index,
+                    "error" => &error,
+                );
+                if let Some(span) = callback_span.as_mut() {
+                    span.tag("error", true);
+                    span.tag("message", error);
+                }
+                success = false;
+            }
+            self.finish_span(callback_span);
         }
+        self.finish_span(span);
+        success
+    }
+
+    /// Insert a shutdown callback after all callbacks with the same or lower priority.
+    fn push_callback(&mut self, priority: i32, callback: Callback) {
+        let index = self
+            .callbacks
+            .iter()
+            .position(|(other, _)| *other > priority)
+            .unwrap_or(self.callbacks.len());
+        self.callbacks.insert(index, (priority, callback));
+    }
+
+    /// Start a new tracing span, if a tracer is set.
+    fn span(&self, name: &str, parent: Option<&SpanContext>) -> Option<Span> {
+        self.tracer.as_ref().map(|tracer| {
+            let mut span = tracer.span(name);
+            if let Some(parent) = parent {
+                span.child_of(parent.clone());
+            }
+            span
+        })
     }
 }
 
@@ -258,9 +985,150 @@ impl Drop for Upkeep {
     }
 }
 
+/// Limits on how restartable threads are restarted after a panic.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RestartPolicy {
+    backoff: Duration,
+    max_restarts: usize,
+}
+
+impl RestartPolicy {
+    /// Restart threads up to `max_restarts` times without delay.
+    pub fn new(max_restarts: usize) -> RestartPolicy {
+        RestartPolicy {
+            backoff: Duration::from_secs(0),
+            max_restarts,
+        }
+    }
+
+    /// Set the delay to wait for before a thread is restarted.
+    pub fn backoff(mut self, backoff: Duration) -> RestartPolicy {
+        self.backoff = backoff;
+        self
+    }
+}
+
+/// Outcome of the shutdown flow, returned by [`Upkeep::keepalive_status`].
+///
+/// [`Upkeep::keepalive_status`]: struct.Upkeep.html#method.keepalive_status
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ShutdownOutcome {
+    /// A shutdown callback failed.
+    CallbackFailed,
+
+    /// The process shut down cleanly.
+    Clean,
+
+    /// A required thread exited without panicking and the process then shut down cleanly.
+    RequiredThreadExited,
+
+    /// A registered thread panicked, either causing the shutdown or while being joined.
+    ThreadPanicked,
+
+    /// Registered threads were still running when the shutdown timeout expired.
+    TimedOut,
+}
+
+impl ShutdownOutcome {
+    /// Check if the outcome is a clean shutdown.
+    ///
+    /// Required threads exiting without panicking are clean shutdowns.
+    pub fn is_clean(&self) -> bool {
+        matches!(
+            self,
+            ShutdownOutcome::Clean | ShutdownOutcome::RequiredThreadExited
+        )
+    }
+}
+
+/// Reason the shutdown flow started.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ShutdownReason {
+    /// A message was received on a registered trigger.
+    Programmatic,
+
+    /// The process received a shutdown signal.
+    Signal,
+
+    /// A required thread exited.
+    ThreadExited,
+
+    /// A registered thread panicked (and could not be restarted).
+    ThreadPanicked,
+}
+
+/// Cloneable handle to begin the shutdown flow of an [`Upkeep`] instance.
+///
+/// [`Upkeep`]: struct.Upkeep.html
+#[derive(Clone, Debug)]
+pub struct ShutdownHandle {
+    flag: Arc<AtomicBool>,
+    sender: Sender<()>,
+}
+
+impl ShutdownHandle {
+    /// Begin the shutdown flow, if it has not started already.
+    pub fn shutdown(&self) {
+        self.flag.store(true, Ordering::Relaxed);
+        // The keepalive loop may have exited already, in which case there is nothing to do.
+        let _ = self.sender.send(());
+    }
+}
+
+/// Point in time status of a thread managed by [`Upkeep`].
+///
+/// [`Upkeep`]: struct.Upkeep.html
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ThreadStatus {
+    /// Activity reported by the thread, if the thread is named and reports any.
+    pub activity: Option<String>,
+
+    /// Name of the thread, for threads registered with a name.
+    pub name: Option<String>,
+
+    /// Process shutdown begins when required threads exit.
+    pub required: bool,
+
+    /// Restartable threads are restarted when they panic.
+    pub restartable: bool,
+}
+
 struct ThreadMeta {
-    handle: MapThread<()>,
+    handle: Box<dyn ThreadHandle>,
+    name: Option<String>,
     required: bool,
+    restart: Option<ThreadRestart>,
+}
+
+/// Restart information for restartable threads.
+struct ThreadRestart {
+    attempts: usize,
+    name: String,
+    policy: RestartPolicy,
+    spawn: Box<dyn Fn() -> Result<Box<dyn ThreadHandle>, HumthreadsError> + Send>,
+}
+
+/// Handle on registered threads that hides their return type.
+///
+/// Unlike `humthreads::MapThread` this handle is `Send` so `Upkeep` can be moved across threads.
+trait ThreadHandle: Send {
+    fn join(&self) -> Result<(), HumthreadsError>;
+    fn request_shutdown(&self);
+    fn select_add<'a>(&'a self, select: &mut Select<'a>) -> usize;
+}
+
+impl<T: Send + 'static> ThreadHandle for Thread<T> {
+    fn join(&self) -> Result<(), HumthreadsError> {
+        Thread::join(self).map(|_| ())
+    }
+
+    fn request_shutdown(&self) {
+        Thread::request_shutdown(self)
+    }
+
+    fn select_add<'a>(&'a self, select: &mut Select<'a>) -> usize {
+        Thread::select_add(self, select)
+    }
 }
 
 #[cfg(test)]
@@ -269,11 +1137,24 @@ mod tests {
     use std::sync::atomic::AtomicUsize;
     use std::sync::atomic::Ordering;
     use std::sync::Arc;
+    use std::sync::Mutex;
     use std::time::Duration;
+    use std::time::Instant;
 
+    use crossbeam_channel::unbounded;
     use humthreads::Builder;
+    use opentracingrust::tracers::NoopTracer;
+    use opentracingrust::SpanReference;
 
+    use super::RestartPolicy;
+    use super::ShutdownOutcome;
+    use super::ShutdownReason;
+    use super::ThreadStatus;
     use super::Upkeep;
+    use super::EXIT_CODE_CLEAN;
+    use super::EXIT_CODE_REQUIRED_THREAD_EXITED;
+    use super::EXIT_CODE_THREAD_PANICKED;
+    use super::EXIT_CODE_UNCLEAN;
 
     #[test]
     fn callback() {
@@ -281,8 +1162,197 @@ mod tests {
         let mut up = Upkeep::new();
         let inner_flag = Arc::clone(&flag);
         up.on_shutdown(move || inner_flag.store(true, Ordering::Relaxed));
-        up.shutdown();
-        assert_eq!(true, flag.load(Ordering::Relaxed));
+        up.shutdown(None);
+        assert!(flag.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn callback_failure_is_unclean() {
+        let (sender, receiver) = unbounded();
+        let mut up = Upkeep::new();
+        up.on_shutdown_try(|| Err("flush failed"));
+        up.on_shutdown_try(|| Ok::<(), String>(()));
+        up.register_trigger(receiver);
+        sender.send(()).unwrap();
+        assert_eq!(up.keepalive_status(), ShutdownOutcome::CallbackFailed);
+        assert_eq!(up.exit_code(), EXIT_CODE_UNCLEAN);
+    }
+
+    #[test]
+    fn callback_try_success_is_clean() {
+        let (sender, receiver) = unbounded();
+        let mut up = Upkeep::new();
+        up.on_shutdown_try(|| Ok::<(), String>(()));
+        assert_eq!(up.exit_code(), EXIT_CODE_CLEAN);
+        up.register_trigger(receiver);
+        sender.send(()).unwrap();
+        let clean = up.keepalive();
+        assert!(clean);
+        assert_eq!(up.exit_code(), EXIT_CODE_CLEAN);
+    }
+
+    #[test]
+    fn callback_begin_runs_first() {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let mut up = Upkeep::new();
+        let inner_calls = Arc::clone(&calls);
+        up.on_shutdown(move || inner_calls.lock().unwrap().push("shutdown"));
+        let inner_calls = Arc::clone(&calls);
+        up.on_shutdown_begin(move || inner_calls.lock().unwrap().push("begin"));
+        up.shutdown(None);
+        assert_eq!(*calls.lock().unwrap(), vec!["begin", "shutdown"]);
+    }
+
+    #[test]
+    fn callback_priority_order() {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let mut up = Upkeep::new();
+        let inner_calls = Arc::clone(&calls);
+        up.on_shutdown_priority(10, move || inner_calls.lock().unwrap().push("late"));
+        let inner_calls = Arc::clone(&calls);
+        up.on_shutdown(move || inner_calls.lock().unwrap().push("first"));
+        let inner_calls = Arc::clone(&calls);
+        up.on_shutdown_priority(-10, move || inner_calls.lock().unwrap().push("early"));
+        let inner_calls = Arc::clone(&calls);
+        up.on_shutdown(move || inner_calls.lock().unwrap().push("second"));
+        up.shutdown(None);
+        assert_eq!(
+            *calls.lock().unwrap(),
+            vec!["early", "first", "second", "late"]
+        );
+    }
+
+    #[test]
+    fn flush_tracing_before_logging() {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let mut up = Upkeep::new();
+        let inner_calls = Arc::clone(&calls);
+        up.register_logging_flush(move || inner_calls.lock().unwrap().push("logging"));
+        let inner_calls = Arc::clone(&calls);
+        up.register_tracing_flush(move || inner_calls.lock().unwrap().push("tracing"));
+        let inner_calls = Arc::clone(&calls);
+        up.on_shutdown(move || inner_calls.lock().unwrap().push("shutdown"));
+        let (thread, handle) = up.spawn_keepalive();
+        handle.shutdown();
+        let clean = thread.join().expect("keepalive thread panicked");
+        assert!(clean);
+        assert_eq!(
+            *calls.lock().unwrap(),
+            vec!["shutdown", "tracing", "logging"]
+        );
+    }
+
+    #[test]
+    fn reload_signal_runs_callbacks() {
+        let reloads = Arc::new(AtomicUsize::new(0));
+        let mut up = Upkeep::new();
+        let inner_reloads = Arc::clone(&reloads);
+        up.on_reload(move || {
+            inner_reloads.fetch_add(1, Ordering::SeqCst);
+        });
+        up.register_reload_signal().unwrap();
+        let (thread, handle) = up.spawn_keepalive();
+        signal_hook::low_level::raise(signal_hook::consts::signal::SIGHUP).unwrap();
+        let mut waited = 0;
+        while reloads.load(Ordering::SeqCst) == 0 && waited < 100 {
+            std::thread::sleep(Duration::from_millis(10));
+            waited += 1;
+        }
+        assert_eq!(reloads.load(Ordering::SeqCst), 1);
+
+        // Reloads do not shutdown the process.
+        assert!(!thread.is_finished());
+        handle.shutdown();
+        let clean = thread.join().expect("keepalive thread panicked");
+        assert!(clean);
+    }
+
+    #[test]
+    fn shutdown_traced() {
+        let (tracer, receiver) = NoopTracer::new();
+        let mut up = Upkeep::new();
+        up.set_tracer(Arc::new(tracer));
+        up.on_shutdown(|| ());
+        let thread = Builder::new("shutdown_traced")
+            .spawn(|_| ())
+            .expect("to spawn test thread");
+        up.register_thread(thread);
+        let clean = up.keepalive();
+        assert!(clean);
+
+        let spans: Vec<_> = receiver.try_iter().collect();
+        let names: Vec<&str> = spans.iter().map(|span| span.name().as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "upkeep.callbacks_begin",
+                "upkeep.request_shutdown",
+                "upkeep.callback",
+                "upkeep.callbacks",
+                "upkeep.join_thread",
+                "upkeep.join_threads",
+                "upkeep.shutdown",
+            ]
+        );
+        // NoopTracer contexts carry no IDs so only check all spans but the root have a parent.
+        let (root, children) = spans.split_last().unwrap();
+        assert!(root.references().is_empty());
+        for span in children {
+            assert!(matches!(
+                span.references().as_slice(),
+                [SpanReference::ChildOf(_)]
+            ));
+        }
+    }
+
+    #[test]
+    fn spawn_keepalive_shutdown_handle() {
+        let flag = Arc::new(AtomicBool::new(false));
+        let mut up = Upkeep::new();
+        let inner_flag = Arc::clone(&flag);
+        up.on_shutdown(move || inner_flag.store(true, Ordering::Relaxed));
+        let (thread, handle) = up.spawn_keepalive();
+        assert_eq!(thread.thread().name(), Some("upkeep-keepalive"));
+        handle.shutdown();
+        let clean = thread.join().expect("keepalive thread panicked");
+        assert!(clean);
+        assert!(flag.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn trigger_shutdown_before_keepalive() {
+        let flag = Arc::new(AtomicBool::new(false));
+        let mut up = Upkeep::new();
+        let inner_flag = Arc::clone(&flag);
+        up.on_shutdown(move || inner_flag.store(true, Ordering::Relaxed));
+        up.trigger_shutdown();
+        assert!(up.shutdown_requested());
+        assert!(up.keepalive());
+        assert!(flag.load(Ordering::Relaxed));
+        assert_eq!(up.shutdown_reason(), Some(ShutdownReason::Programmatic));
+    }
+
+    #[test]
+    fn shutdown_requested_flag() {
+        let mut up = Upkeep::new();
+        let observer = up.shutdown_flag();
+        let seen = Arc::new(AtomicBool::new(false));
+        let inner_seen = Arc::clone(&seen);
+        let inner_observer = Arc::clone(&observer);
+        up.on_shutdown(move || {
+            inner_seen.store(inner_observer.load(Ordering::Relaxed), Ordering::Relaxed)
+        });
+        assert!(!up.shutdown_requested());
+        assert!(!observer.load(Ordering::Relaxed));
+
+        up.shutdown_handle().shutdown();
+        assert!(up.keepalive());
+        assert!(up.shutdown_requested());
+        assert!(observer.load(Ordering::Relaxed));
+        assert!(seen.load(Ordering::Relaxed));
+
+        // A later SIGINT/SIGTERM must still be treated as the first signal.
+        assert!(!up.signal_flag.load(Ordering::Relaxed));
     }
 
     #[test]
@@ -306,11 +1376,140 @@ mod tests {
             })
             .expect("to spawn test thread");
         up.register_thread(thread);
-        let clean = up.keepalive();
-        assert_eq!(true, clean);
+        let outcome = up.keepalive_status();
+        assert_eq!(outcome, ShutdownOutcome::RequiredThreadExited);
+        assert!(outcome.is_clean());
+        assert_eq!(up.exit_code(), EXIT_CODE_REQUIRED_THREAD_EXITED);
         assert_eq!(5, count.load(Ordering::Relaxed));
     }
 
+    #[test]
+    fn thread_statuses() {
+        let (sender, receiver) = unbounded();
+        let required = Builder::new("t:statuses:req")
+            .full_name("thread_statuses:required")
+            .spawn(move |scope| {
+                scope.activity("waiting for test");
+                sender.send(()).unwrap();
+                while !scope.should_shutdown() {
+                    std::thread::sleep(Duration::from_millis(5));
+                }
+            })
+            .expect("to spawn test thread");
+        let optional = Builder::new("t:statuses:opt")
+            .spawn(|scope| {
+                while !scope.should_shutdown() {
+                    std::thread::sleep(Duration::from_millis(5));
+                }
+            })
+            .expect("to spawn test thread");
+        let mut up = Upkeep::new();
+        up.register_thread_named("thread_statuses:required", required);
+        up.register_thread_optional(optional);
+        up.register_thread_restartable(
+            "thread_statuses:restartable",
+            RestartPolicy::new(1),
+            || {
+                Builder::new("t:statuses:rst").spawn(|scope| {
+                    while !scope.should_shutdown() {
+                        std::thread::sleep(Duration::from_millis(5));
+                    }
+                })
+            },
+        )
+        .expect("to spawn restartable thread");
+        receiver.recv().unwrap();
+
+        let statuses = up.thread_statuses();
+        assert_eq!(
+            statuses,
+            vec![
+                ThreadStatus {
+                    activity: Some("waiting for test".into()),
+                    name: Some("thread_statuses:required".into()),
+                    required: true,
+                    restartable: false,
+                },
+                ThreadStatus {
+                    activity: None,
+                    name: None,
+                    required: false,
+                    restartable: false,
+                },
+                ThreadStatus {
+                    activity: None,
+                    name: Some("thread_statuses:restartable".into()),
+                    required: false,
+                    restartable: true,
+                },
+            ]
+        );
+        up.shutdown(None);
+        assert_eq!(up.join_threads(None), Some(true));
+    }
+
+    #[test]
+    fn join_threads_until_deadline() {
+        let done = Builder::new("join_deadline_done")
+            .spawn(|_| ())
+            .expect("to spawn test thread");
+        let (release, stuck) = unbounded::<()>();
+        let hung = Builder::new("join_deadline_hung")
+            .spawn(move |_| {
+                let _ = stuck.recv();
+            })
+            .expect("to spawn test thread");
+        let mut up = Upkeep::new();
+        up.register_thread_named("done", done);
+        up.register_thread_named("hung", hung);
+        up.register_thread(
+            Builder::new("join_deadline_unnamed")
+                .spawn(|_| ())
+                .expect("to spawn test thread"),
+        );
+
+        let threads = up.threads.drain(..).collect();
+        let deadline = Instant::now() + Duration::from_millis(100);
+        let stuck = up.join_threads_until(threads, deadline, None);
+        assert_eq!(stuck, Err(vec!["hung".to_string()]));
+        release.send(()).unwrap();
+    }
+
+    #[test]
+    fn join_threads_with_timeout() {
+        let thread = Builder::new("join_timeout_worker")
+            .spawn(|scope| {
+                while !scope.should_shutdown() {
+                    ::std::thread::sleep(Duration::from_millis(5));
+                }
+            })
+            .expect("to spawn test thread");
+        let mut up = Upkeep::new();
+        up.shutdown_timeout(Duration::from_secs(5));
+        up.register_thread(thread);
+        up.trigger_shutdown();
+        assert!(up.keepalive());
+    }
+
+    #[test]
+    fn join_threads_timeout_outcome() {
+        let (release, wait) = unbounded::<()>();
+        let thread = Builder::new("join_timeout_stuck")
+            .spawn(move |_| {
+                let _ = wait.recv();
+            })
+            .expect("to spawn test thread");
+        let mut up = Upkeep::new();
+        up.shutdown_timeout(Duration::from_millis(50));
+        up.register_thread(thread);
+        up.trigger_shutdown();
+        let outcome = up.keepalive_status();
+        assert_eq!(outcome, ShutdownOutcome::TimedOut);
+        assert!(!outcome.is_clean());
+        assert_eq!(up.exit_code(), EXIT_CODE_UNCLEAN);
+        release.send(()).unwrap();
+    }
+
     #[test]
     fn thread_panics() {
         let flag = Arc::new(AtomicBool::new(false));
@@ -323,9 +1522,85 @@ mod tests {
             })
             .expect("to spawn test thread");
         up.register_thread(thread);
+        let outcome = up.keepalive_status();
+        assert!(flag.load(Ordering::Relaxed));
+        assert_eq!(outcome, ShutdownOutcome::ThreadPanicked);
+        assert!(!outcome.is_clean());
+        assert_eq!(up.exit_code(), EXIT_CODE_THREAD_PANICKED);
+    }
+
+    #[test]
+    fn thread_restarts() {
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let done = Arc::new(AtomicBool::new(false));
+        let mut up = Upkeep::new();
+        let inner_attempts = Arc::clone(&attempts);
+        let inner_done = Arc::clone(&done);
+        up.register_thread_restartable("thread_restarts", RestartPolicy::new(1), move || {
+            let attempts = Arc::clone(&inner_attempts);
+            let done = Arc::clone(&inner_done);
+            Builder::new("thread_restarts").spawn(move |_| {
+                if attempts.fetch_add(1, Ordering::Relaxed) == 0 {
+                    panic!("this panic is expected");
+                }
+                done.store(true, Ordering::Relaxed);
+            })
+        })
+        .expect("to spawn test thread");
+        let inner_done = Arc::clone(&done);
+        let thread = Builder::new("thread_restarts_wait")
+            .spawn(move |scope| {
+                while !inner_done.load(Ordering::Relaxed) && !scope.should_shutdown() {
+                    ::std::thread::sleep(Duration::from_millis(10));
+                }
+            })
+            .expect("to spawn test thread");
+        up.register_thread(thread);
+        let clean = up.keepalive();
+        assert!(clean);
+        assert!(done.load(Ordering::Relaxed));
+        assert_eq!(2, attempts.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn thread_restarts_exhausted() {
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let mut up = Upkeep::new();
+        let inner_attempts = Arc::clone(&attempts);
+        up.register_thread_restartable("thread_restarts", RestartPolicy::new(2), move || {
+            let attempts = Arc::clone(&inner_attempts);
+            Builder::new("thread_restarts").spawn(move |_| {
+                attempts.fetch_add(1, Ordering::Relaxed);
+                panic!("this panic is expected");
+            })
+        })
+        .expect("to spawn test thread");
+        let clean = up.keepalive();
+        assert!(!clean);
+        assert_eq!(3, attempts.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn trigger_shuts_down() {
+        let flag = Arc::new(AtomicBool::new(false));
+        let inner_flag = Arc::clone(&flag);
+        let thread = Builder::new("trigger_shuts_down")
+            .spawn(move |scope| {
+                while !scope.should_shutdown() {
+                    ::std::thread::sleep(Duration::from_millis(10));
+                }
+                inner_flag.store(true, Ordering::Relaxed);
+            })
+            .expect("to spawn test thread");
+        let (sender, receiver) = unbounded();
+        let mut up = Upkeep::new();
+        up.register_thread(thread);
+        up.register_trigger(receiver);
+        sender.send(()).unwrap();
         let clean = up.keepalive();
-        assert_eq!(true, flag.load(Ordering::Relaxed));
-        assert_eq!(false, clean);
+        assert!(clean);
+        assert!(flag.load(Ordering::Relaxed));
+        assert_eq!(up.shutdown_reason(), Some(ShutdownReason::Programmatic));
     }
 
     #[test]
@@ -345,10 +1620,10 @@ mod tests {
             .expect("to spawn test thread");
         let mut up = Upkeep::new();
         up.register_thread(thread);
-        up.shutdown();
+        up.shutdown(None);
         let clean = up.keepalive();
-        assert_eq!(true, flag.load(Ordering::Relaxed));
-        assert_eq!(true, clean);
+        assert!(flag.load(Ordering::Relaxed));
+        assert!(clean);
     }
 
     // Tests below are commented out because they cause undefined behaviours.
@@ -407,8 +1682,8 @@ mod tests {
     //    up.on_shutdown(move || inner_flag.store(true, Ordering::Relaxed));
     //    kill(Pid::this(), SIGINT).unwrap();
     //    let clean = up.keepalive();
-    //    assert_eq!(true, flag.load(Ordering::Relaxed));
-    //    assert_eq!(true, clean);
+    //    assert!(flag.load(Ordering::Relaxed));
+    //    assert!(clean);
     //}
 
     // This test aborts the entrie tests process.